@@ -0,0 +1,288 @@
+use super::parser::{Action, Kind, Production, Table};
+use super::token::{Token, TokenType};
+use std::fmt;
+
+/// A token the driver can read: all the table looks at is its kind.
+pub trait Terminal {
+    type Kind: Kind;
+    fn kind(&self) -> Self::Kind;
+}
+
+impl Terminal for Token {
+    type Kind = TokenType;
+    fn kind(&self) -> TokenType {
+        self.1.clone()
+    }
+}
+
+impl Terminal for char {
+    type Kind = char;
+    fn kind(&self) -> char {
+        *self
+    }
+}
+
+/// Semantic actions, called as the driver shifts tokens and reduces by
+/// productions. A parse returns the value made for the start variable.
+pub trait Actions<T: Terminal> {
+    type Value;
+
+    fn shift(&mut self, token: T) -> Self::Value;
+
+    /// `children` holds a value for each symbol on the production's right.
+    fn reduce(
+        &mut self,
+        index: usize,
+        production: &Production<T::Kind>,
+        children: Vec<Self::Value>,
+    ) -> Self::Value;
+}
+
+/// A parse tree: tokens at the leaves, and a node for each reduction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseTree<T> {
+    Leaf(T),
+    Node {
+        variable: String,
+        production: usize,
+        children: Vec<ParseTree<T>>,
+    },
+}
+
+impl<T> ParseTree<T> {
+    /// The tokens at the leaves, in order.
+    pub fn leaves(&self) -> Vec<&T> {
+        match self {
+            ParseTree::Leaf(token) => vec![token],
+            ParseTree::Node { children, .. } => children.iter().flat_map(|c| c.leaves()).collect(),
+        }
+    }
+}
+
+// the actions `Table::parse` builds its tree with
+struct TreeBuilder;
+
+impl<T: Terminal> Actions<T> for TreeBuilder {
+    type Value = ParseTree<T>;
+
+    fn shift(&mut self, token: T) -> ParseTree<T> {
+        ParseTree::Leaf(token)
+    }
+
+    fn reduce(
+        &mut self,
+        index: usize,
+        production: &Production<T::Kind>,
+        children: Vec<ParseTree<T>>,
+    ) -> ParseTree<T> {
+        ParseTree::Node {
+            variable: production.left.clone(),
+            production: index,
+            children,
+        }
+    }
+}
+
+/// A token the table has no action for. `None` is the end of input, both
+/// as what was found and among what was expected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError<T: Terminal> {
+    pub found: Option<T>,
+    pub expected: Vec<Option<T::Kind>>,
+}
+
+impl<T: Terminal> fmt::Display for ParseError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |k: Option<&T::Kind>| match k {
+            Some(k) => format!("{:?}", k),
+            None => "end of input".to_string(),
+        };
+        let expected: Vec<String> = self.expected.iter().map(|k| show(k.as_ref())).collect();
+        write!(
+            f,
+            "expected one of {}, found {}",
+            expected.join(", "),
+            show(self.found.as_ref().map(|t| t.kind()).as_ref())
+        )
+    }
+}
+
+impl<T: Terminal + fmt::Debug> std::error::Error for ParseError<T> {}
+
+impl<K: Kind> Table<K> {
+    /// Parses `tokens` into a tree.
+    pub fn parse<T, I>(&self, tokens: I) -> Result<ParseTree<T>, ParseError<T>>
+    where
+        T: Terminal<Kind = K>,
+        I: IntoIterator<Item = T>,
+    {
+        self.run(tokens, &mut TreeBuilder)
+    }
+
+    /// Parses `tokens`, calling `actions` on every shift and reduction.
+    pub fn run<T, I, A>(&self, tokens: I, actions: &mut A) -> Result<A::Value, ParseError<T>>
+    where
+        T: Terminal<Kind = K>,
+        I: IntoIterator<Item = T>,
+        A: Actions<T>,
+    {
+        let mut tokens = tokens.into_iter();
+        let mut states = vec![0];
+        let mut values = vec![];
+        let mut next = tokens.next();
+        loop {
+            let state = *states.last().unwrap();
+            let kind = next.as_ref().map(|t| t.kind());
+            match self.action(state, kind.as_ref()) {
+                Some(Action::Shift(to)) => {
+                    values.push(actions.shift(next.take().unwrap()));
+                    states.push(to);
+                    next = tokens.next();
+                }
+                Some(Action::Reduce(index)) => {
+                    let production = &self.produces[index];
+                    let len = production.right.len();
+                    let children = values.split_off(values.len() - len);
+                    states.truncate(states.len() - len);
+                    values.push(actions.reduce(index, production, children));
+                    let top = *states.last().unwrap();
+                    states.push(self.goto(top, &production.left).unwrap());
+                }
+                Some(Action::Accept) => return Ok(values.pop().unwrap()),
+                None => {
+                    return Err(ParseError {
+                        found: next,
+                        expected: self.expected(state),
+                    })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::lexer::Lexer;
+    use crate::frontend::parser::Grammar;
+    use crate::frontend::parser::GrammarSymbol::{self, *};
+
+    fn v(name: &str) -> GrammarSymbol<TokenType> {
+        Variable(name.to_string())
+    }
+
+    // E -> E + T | T, T -> T * F | F, F -> ( E ) | int
+    fn arithmetic() -> Grammar<TokenType> {
+        let mut g = Grammar::new("E");
+        g.add_production("E", vec![v("E"), Terminal(TokenType::PLUS), v("T")]);
+        g.add_production("E", vec![v("T")]);
+        g.add_production("T", vec![v("T"), Terminal(TokenType::ASTERISK), v("F")]);
+        g.add_production("T", vec![v("F")]);
+        g.add_production(
+            "F",
+            vec![
+                Terminal(TokenType::LPAREN),
+                v("E"),
+                Terminal(TokenType::RPAREN),
+            ],
+        );
+        g.add_production("F", vec![Terminal(TokenType::DECINT)]);
+        g
+    }
+
+    struct Calculator;
+
+    impl Actions<Token> for Calculator {
+        type Value = i64;
+
+        fn shift(&mut self, token: Token) -> i64 {
+            token.0.parse().unwrap_or(0)
+        }
+
+        fn reduce(&mut self, index: usize, _: &Production<TokenType>, c: Vec<i64>) -> i64 {
+            match index {
+                0 => c[0] + c[2],
+                2 => c[0] * c[2],
+                4 => c[1],
+                _ => c[0],
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_tree() {
+        let table = arithmetic().build_lalr1_table().unwrap();
+        let tree = table.parse(Lexer::new("1 + 2 * 3")).unwrap();
+        let leaves: Vec<&str> = tree.leaves().iter().map(|t| t.0.as_str()).collect();
+        assert_eq!(leaves, ["1", "+", "2", "*", "3"]);
+        match tree {
+            ParseTree::Node {
+                variable,
+                production,
+                children,
+            } => {
+                assert_eq!((variable.as_str(), production), ("E", 0));
+                assert_eq!(children.len(), 3);
+                assert_eq!(
+                    children[1],
+                    ParseTree::Leaf(("+".to_string(), TokenType::PLUS))
+                );
+            }
+            leaf => panic!("not a node: {:?}", leaf),
+        }
+    }
+
+    #[test]
+    fn test_actions() {
+        let table = arithmetic().build_lalr1_table().unwrap();
+        let tests = [
+            ("1 + 2 * 3", 7),
+            ("(1 + 2) * 3", 9),
+            ("2 * (3 + 4) * 5", 70),
+            ("42", 42),
+        ];
+        for (input, expected) in tests.iter() {
+            assert_eq!(
+                table.run(Lexer::new(input), &mut Calculator),
+                Ok(*expected),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let table = arithmetic().build_lalr1_table().unwrap();
+        let tests = [
+            ("1 + * 2", "expected one of DECINT, LPAREN, found ASTERISK"),
+            ("(1 + 2", "expected one of PLUS, RPAREN, found end of input"),
+            ("", "expected one of DECINT, LPAREN, found end of input"),
+        ];
+        for (input, expected) in tests.iter() {
+            let error = table.parse(Lexer::new(input)).unwrap_err();
+            assert_eq!(error.to_string(), *expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_empty_productions() {
+        // S -> a S b | empty
+        let mut g = Grammar::new("S");
+        g.add_production(
+            "S",
+            vec![Terminal('a'), Variable("S".to_string()), Terminal('b')],
+        );
+        g.add_production("S", vec![]);
+        let table = g.build_lalr1_table().unwrap();
+        for input in ["", "ab", "aaabbb"].iter() {
+            assert_eq!(
+                table.parse(input.chars()).unwrap().leaves().len(),
+                input.len()
+            );
+        }
+        for input in ["a", "abb", "ba"].iter() {
+            assert!(table.parse(input.chars()).is_err(), "{}", input);
+        }
+    }
+}
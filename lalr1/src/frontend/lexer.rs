@@ -2,26 +2,18 @@ use super::token;
 use std::iter::Peekable;
 use std::str::Chars;
 
-
 macro_rules! make_token {
     ($id:ident, $x:expr) => {{
         let mut tmp_string = String::new();
         tmp_string.push($x);
-        (tmp_string,token::TokenType::$id )
+        (tmp_string, token::TokenType::$id)
     }};
     ($id:ident, $x:expr, $y:expr) => {{
         let mut tmp_string = String::new();
         tmp_string.push($x);
         tmp_string.push($y);
-        (tmp_string,token::TokenType::$id)
+        (tmp_string, token::TokenType::$id)
     }};
-    // ($id:ident, ($($x:expr),+) )=> {
-    //     let mut tmp_string = String::new();
-    //     $(
-    //         tmp_string.push($x);
-    //     )+
-    //     (tmp_string,token::TokenType::$id )
-    // };
 }
 
 const CHAR0: char = 0 as char;
@@ -45,9 +37,10 @@ enum DFAState {
     DECFLOATACC,
 }
 
+// the end of input ends a token like any other character
 fn dfa_number_ident(s: &DFAState, ch: Option<char>) -> Option<DFAState> {
     use DFAState::*;
-    ch.map(|ch| match (s, ch) {
+    match (s, ch.unwrap_or(CHAR0)) {
         (START, '0') => Some(ONEZERO),
         (START, '1'..='9') => Some(DEC),
         (START, 'a'..='z') => Some(IDENT),
@@ -74,8 +67,7 @@ fn dfa_number_ident(s: &DFAState, ch: Option<char>) -> Option<DFAState> {
         (IDENT, 'A'..='Z') => Some(IDENT),
         (IDENT, _) => Some(IDENTACC),
         _ => None,
-    })
-    .flatten()
+    }
 }
 
 #[derive(Debug)]
@@ -84,7 +76,7 @@ pub struct Lexer<'a> {
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(input: &str) -> Lexer {
+    pub fn new(input: &str) -> Lexer<'_> {
         Lexer {
             input: input.chars().peekable(),
         }
@@ -100,10 +92,31 @@ impl<'a> Lexer<'a> {
                     make_token!(ASSIGN, ch)
                 }
             }
+            '!' => {
+                if self.peek_char().unwrap_or(CHAR0) == '=' {
+                    self.next_char();
+                    make_token!(NEQ, ch, '=')
+                } else {
+                    make_token!(BANG, ch)
+                }
+            }
+            '+' => make_token!(PLUS, ch),
+            '-' => make_token!(MINUS, ch),
+            '*' => make_token!(ASTERISK, ch),
+            '/' => make_token!(SLASH, ch),
+            '<' => make_token!(LT, ch),
+            '>' => make_token!(GT, ch),
             '.' => make_token!(DOT, ch),
+            ',' => make_token!(COMMA, ch),
             ';' => make_token!(SEMICOLON, ch),
-            '#'=> make_token!(EOF,ch),
-            // TODO
+            ':' => make_token!(COLON, ch),
+            '(' => make_token!(LPAREN, ch),
+            ')' => make_token!(RPAREN, ch),
+            '{' => make_token!(LBRACE, ch),
+            '}' => make_token!(RBRACE, ch),
+            '[' => make_token!(LBRACKET, ch),
+            ']' => make_token!(RBRACKET, ch),
+            '#' => make_token!(EOF, ch),
             _ => self.dfa(ch),
         })
     }
@@ -118,7 +131,9 @@ impl<'a> Lexer<'a> {
             if state > ACC {
                 break;
             }
-            self.next_char().map(|ch| dfa_string.push(ch));
+            if let Some(ch) = self.next_char() {
+                dfa_string.push(ch)
+            }
         }
 
         match state {
@@ -132,16 +147,8 @@ impl<'a> Lexer<'a> {
     }
 
     fn skip_whitespace(&mut self) {
-        loop {
-            match self.peek_char() {
-                Some(c) => match c {
-                    ' ' | '\t' | '\n' | '\r' => {
-                        self.next_char();
-                    }
-                    _ => break,
-                },
-                None => break,
-            }
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek_char() {
+            self.next_char();
         }
     }
 
@@ -152,7 +159,16 @@ impl<'a> Lexer<'a> {
 
     fn peek_char(&mut self) -> Option<char> {
         //康康下一个字符, 不取出
-        self.input.peek().map(|ch| *ch)
+        self.input.peek().copied()
+    }
+}
+
+// the tokens up to the end of the input, or a `#`
+impl Iterator for Lexer<'_> {
+    type Item = token::Token;
+
+    fn next(&mut self) -> Option<token::Token> {
+        self.next_token().filter(|t| t.1 != token::TokenType::EOF)
     }
 }
 
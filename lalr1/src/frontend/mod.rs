@@ -1,4 +1,4 @@
-
-pub mod token;
+pub mod driver;
 pub mod lexer;
 pub mod parser;
+pub mod token;
@@ -1,583 +1,477 @@
-use super::lexer::Lexer;
-use super::token::{Token, TokenType, FILLER};
-use std::collections::BTreeMap;
-use std::collections::BTreeSet;
-use std::collections::HashMap;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::hash::Hash;
 
-//干!
+/// What a grammar's terminals are: the kinds of tokens, not the tokens.
+pub trait Kind: Ord + Hash + Clone + fmt::Debug {}
 
-type GrammarVar = String;
-type GrammarTerminal = Token;
+impl<K: Ord + Hash + Clone + fmt::Debug> Kind for K {}
 
 //语法变量与语法常量
 #[derive(Ord, PartialOrd, Eq, PartialEq, Debug, Hash, Clone)]
-pub enum GrammarSymbol {
-    Terminal(GrammarTerminal), //语法常量
-    Variable(GrammarVar),      //语法变量
+pub enum GrammarSymbol<K> {
+    Terminal(K),      //语法常量
+    Variable(String), //语法变量
 }
 
-macro_rules! GTerminal {
-    ($x:expr) => {
-        GrammarSymbol::Terminal($x)
-    };
+impl<K> GrammarSymbol<K> {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, GrammarSymbol::Terminal(_))
+    }
+    pub fn is_variable(&self) -> bool {
+        !self.is_terminal()
+    }
 }
 
-macro_rules! GVariable {
-    ($x:expr) => {
-        GrammarSymbol::Variable($x)
-    };
+/// `left -> right`. An empty right side derives the empty string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Production<K> {
+    pub left: String,
+    pub right: Vec<GrammarSymbol<K>>,
 }
 
-impl Default for GrammarSymbol {
-    fn default() -> Self {
-        GrammarSymbol::Terminal((FILLER.to_string(), TokenType::VareEpsilon))
-    }
+/// A context-free grammar.
+///
+/// ```
+/// use lalr1::frontend::parser::{Grammar, GrammarSymbol::*};
+///
+/// let mut g = Grammar::new("S");
+/// g.add_production("S", vec![Terminal('a'), Variable("S".to_string())]);
+/// g.add_production("S", vec![]);
+/// let table = g.build_lalr1_table().unwrap();
+/// assert!(table.parse("aaa".chars()).is_ok());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Grammar<K> {
+    start: String,
+    produces: Vec<Production<K>>,
 }
-impl GrammarSymbol {
-    pub fn is_terminal(&self) -> bool {
-        match &self {
-            GrammarSymbol::Terminal(_) => true,
-            _ => false,
+
+impl<K: Kind> Grammar<K> {
+    /// An empty grammar deriving from the variable `start`.
+    pub fn new(start: &str) -> Self {
+        Grammar {
+            start: start.to_string(),
+            produces: vec![],
         }
     }
-    pub fn is_variable(&self) -> bool {
-        !self.is_terminal()
+
+    /// Adds `left -> right` and returns its index, which is what a parse
+    /// reports when it reduces by the production.
+    pub fn add_production(&mut self, left: &str, right: Vec<GrammarSymbol<K>>) -> usize {
+        self.produces.push(Production {
+            left: left.to_string(),
+            right,
+        });
+        self.produces.len() - 1
     }
-    pub fn is_epsilon(&self) -> bool {
-        match &self {
-            GrammarSymbol::Terminal((_, TokenType::VareEpsilon)) => true,
-            _ => false,
-        }
+
+    pub fn start(&self) -> &str {
+        &self.start
     }
-    pub fn unwrap_terminal(&self) -> &GrammarTerminal {
-        match &self {
-            GrammarSymbol::Terminal(t) => &t,
-            _ => panic!("\n\t not terminal"),
+
+    pub fn productions(&self) -> &[Production<K>] {
+        &self.produces
+    }
+
+    /// Builds the parse table, or reports the first conflict that makes
+    /// the grammar not LALR(1).
+    pub fn build_lalr1_table(&self) -> Result<Table<K>, Conflict<K>> {
+        let used = self.produces.iter().flat_map(|p| &p.right);
+        let variables = std::iter::once(&self.start).chain(used.filter_map(|s| match s {
+            GrammarSymbol::Variable(v) => Some(v),
+            GrammarSymbol::Terminal(_) => None,
+        }));
+        for v in variables {
+            if !self.produces.iter().any(|p| &p.left == v) {
+                return Err(Conflict::Undefined(v.clone()));
+            }
         }
+        Builder::new(self).build()
     }
 }
 
-type SymbolQueue = Vec<GrammarSymbol>;
+/// A parse table entry for a state and lookahead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Shift(usize),  // to the state
+    Reduce(usize), // by the production
+    Accept,
+}
 
-type ItemLeft = GrammarVar;
-type ItemRight = (usize, usize); //第一个是产生式的下标, 第二个usize代表下一个将要读取文法符号的的下标
-                                 // type GrammarItem = BTreeMap<ItemRight, Vec<GrammarSymbol>>;
-type GrammarItem = (ItemRight, Vec<GrammarTerminal>);
+/// Why a grammar has no LALR(1) table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict<K> {
+    /// A variable that is used, or is the start, but has no productions.
+    Undefined(String),
+    /// Two actions for the same state and lookahead; `None` is the end of
+    /// input.
+    Actions {
+        state: usize,
+        lookahead: Option<K>,
+        actions: (Action, Action),
+    },
+}
 
-pub struct Grammar {
-    pub produces: HashMap<GrammarVar, HashSet<usize>>, //左部->右部
-    pub vec_produces: Vec<(GrammarVar, SymbolQueue)>,
-    pub terminals: HashSet<GrammarSymbol>, //终结符
-    pub vars: HashSet<GrammarSymbol>,      //变量
-    pub start: GrammarSymbol,              //开始
+impl<K: fmt::Debug> fmt::Display for Conflict<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conflict::Undefined(v) => write!(f, "{} has no productions", v),
+            Conflict::Actions {
+                state,
+                lookahead,
+                actions,
+            } => {
+                let kind = |a: &Action| match a {
+                    Action::Shift(_) => "shift",
+                    Action::Reduce(_) => "reduce",
+                    Action::Accept => "accept",
+                };
+                write!(
+                    f,
+                    "{}/{} conflict in state {} on ",
+                    kind(&actions.0),
+                    kind(&actions.1),
+                    state
+                )?;
+                match lookahead {
+                    Some(k) => write!(f, "{:?}", k),
+                    None => f.write_str("end of input"),
+                }
+            }
+        }
+    }
 }
 
-#[derive(Debug)]
-enum LRControll {
-    Shift(usize),
-    Reduce(ItemLeft, ItemRight),
-    Error(String),
-    GOTO(usize),
-    ACC,
+impl<K: fmt::Debug> std::error::Error for Conflict<K> {}
+
+/// An LALR(1) parse table. Build it once with `Grammar::build_lalr1_table`
+/// and use it for any number of parses.
+#[derive(Debug, Clone)]
+pub struct Table<K> {
+    pub(super) produces: Vec<Production<K>>,
+    pub(super) actions: HashMap<(usize, Option<K>), Action>, // None is the end of input
+    pub(super) gotos: HashMap<(usize, String), usize>,
 }
 
-impl Default for LRControll {
-    fn default() -> Self {
-        LRControll::Error("default wrong".to_string())
+impl<K: Kind> Table<K> {
+    pub fn action(&self, state: usize, lookahead: Option<&K>) -> Option<Action> {
+        self.actions.get(&(state, lookahead.cloned())).copied()
     }
-}
 
-impl Grammar {
-    ///empty Grammar_Struct
-    pub fn new() -> Self {
-        Grammar {
-            produces: HashMap::new(),
-            vec_produces: vec![], //S'为起始
-            terminals: HashSet::new(),
-            vars: HashSet::new(),
-            start: GrammarSymbol::default(),
-        }
+    pub fn goto(&self, state: usize, variable: &str) -> Option<usize> {
+        self.gotos.get(&(state, variable.to_string())).copied()
     }
 
-    ///vec包括拓广文法的S'
-    pub fn from(vec: Vec<(GrammarVar, SymbolQueue)>, start: String) -> Self {
-        let mut produces = HashMap::new();
-        vec.iter().enumerate().for_each(|(i, (left, _))| {
-            let m = produces.entry(left.clone()).or_insert(HashSet::new());
-            m.insert(i);
-        });
+    pub fn productions(&self) -> &[Production<K>] {
+        &self.produces
+    }
 
-        Grammar {
-            vars: produces
-                .iter()
-                .map(|(i, _)| GrammarSymbol::Variable(i.clone()))
-                .collect(),
+    /// The lookaheads `state` has an action for, in order.
+    pub fn expected(&self, state: usize) -> Vec<Option<K>> {
+        let mut expected: Vec<Option<K>> = self
+            .actions
+            .keys()
+            .filter(|(s, _)| *s == state)
+            .map(|(_, k)| k.clone())
+            .collect();
+        expected.sort();
+        expected
+    }
+}
 
-            produces: produces,
-            vec_produces: vec,
-            terminals: HashSet::new(),
+// production, position of the dot, lookahead; production 0 is S' -> S
+type Item<K> = (usize, usize, Option<K>);
+type ItemSet<K> = BTreeSet<Item<K>>;
 
-            start: GrammarSymbol::Variable(start),
-        }
-    }
+// builds the canonical LR(1) collection, then merges states with the
+// same core into LALR(1) ones
+struct Builder<K> {
+    produces: Vec<Production<K>>, // the grammar's, after S' -> S
+    nullable: BTreeSet<String>,
+    first: HashMap<String, BTreeSet<K>>,
+}
 
-    //
-    //产生式右部
-    fn get_rights(&self, left: &GrammarSymbol) -> Vec<ItemRight> {
-        match left {
-            GrammarSymbol::Variable(left) => self
-                .produces
-                .get(left)
-                .map(|right| right.iter().map(|x| (*x, 0)).collect())
-                .unwrap_or_default(),
-            _ => vec![],
-        }
+impl<K: Kind> Builder<K> {
+    fn new(grammar: &Grammar<K>) -> Self {
+        let mut produces = vec![Production {
+            left: String::new(),
+            right: vec![GrammarSymbol::Variable(grammar.start.clone())],
+        }];
+        produces.extend(grammar.produces.iter().cloned());
+        let mut builder = Builder {
+            produces,
+            nullable: BTreeSet::new(),
+            first: HashMap::new(),
+        };
+        builder.compute_first();
+        builder
     }
 
-    fn get_closure(&self, key_items: BTreeSet<GrammarItem>) -> BTreeSet<GrammarItem> {
-        //通过核心项目得到闭包
-        let mut closure: BTreeSet<GrammarItem> = key_items;
-        //消除左递归
+    fn compute_first(&mut self) {
         loop {
-            let a = closure.len();
-            let mut tmp: BTreeSet<GrammarItem> = BTreeSet::new();
-            closure
-                .iter()
-                .for_each(|((produces_index, index), look_ahead)| {
-                    tmp = self
-                        .get_rights(
-                            self.vec_produces
-                                .get(*produces_index)
-                                .map(|(_, right)| right.get(*index))
-                                .flatten()
-                                .unwrap_or(&GrammarSymbol::default()),
-                        )
-                        .into_iter()
-                        .map(|(pi, i)| {
-                            (
-                                (pi, i),
-                                self.first(*produces_index, *index, &look_ahead.to_vec()),
-                            )
-                        })
-                        .collect();
-                });
-            closure.extend(tmp);
-            let b = closure.len();
-            if a == b {
+            let mut changed = false;
+            for p in &self.produces {
+                let (first, nullable) = self.first_of(&p.right);
+                let entry = self.first.entry(p.left.clone()).or_default();
+                let before = entry.len();
+                entry.extend(first);
+                changed |= entry.len() != before;
+                if nullable {
+                    changed |= self.nullable.insert(p.left.clone());
+                }
+            }
+            if !changed {
                 break;
             }
         }
-        
-        closure
-    }
-    fn go(
-        &self,
-        old_items: &BTreeSet<GrammarItem>,
-        eat_symbol: &GrammarSymbol,
-    ) -> BTreeSet<GrammarItem> {
-        let key_items: BTreeSet<GrammarItem> = old_items
-            .iter()
-            .filter(|((produces_index, index), la)| {
-                self.vec_produces
-                    .get(*produces_index)
-                    .map(|(_, right)| right.get(*index))
-                    .flatten()
-                    == Some(eat_symbol)
-            })
-            .map(|((produces_index, index), la)| ((*produces_index, *index + 1), la.clone()))
-            .collect();
-        self.get_closure(key_items)
     }
 
-    fn get_canonical_collection(
-        &self,
-    ) -> (
-        Vec<BTreeSet<GrammarItem>>,
-        HashMap<(usize, GrammarSymbol), usize>,
-        Vec<usize>,
-        BTreeMap<(usize, usize, usize), BTreeSet<GrammarTerminal>>,
-    ) {
-        let mut start_set = BTreeSet::new();
-        start_set.insert(((0, 0), vec![("#".to_string(), TokenType::EOF)])); //S'为起始
-        let mut tmp_canonical_collection: HashSet<BTreeSet<GrammarItem>> = HashSet::new();
-        tmp_canonical_collection.insert(self.get_closure(start_set));
-        let mut move_table = BTreeMap::new();
-        let all_symbols = {
-            let mut tmp = HashSet::new();
-            tmp.extend(&self.terminals);
-            tmp.extend(&self.vars);
-            tmp
-        };
-        
-        loop {
-            let a = tmp_canonical_collection.len();
-
-            let mut tmp_items: HashSet<BTreeSet<GrammarItem>> = HashSet::new();
-
-            tmp_canonical_collection.iter().for_each(|closure| {
-                all_symbols.iter().for_each(|eat_symbol| {
-                    let next_closure = self.go(closure, eat_symbol);
-                    
-                    move_table.insert((closure.clone(), eat_symbol.clone()), next_closure.clone());
-                    tmp_items.insert(next_closure.clone());
-            
-                });
-            });
-            tmp_canonical_collection.extend(tmp_items);
-            let b = tmp_canonical_collection.len();
-            if a == b {
-                break;
+    // FIRST of a string of symbols, and whether it derives the empty string
+    fn first_of(&self, symbols: &[GrammarSymbol<K>]) -> (BTreeSet<K>, bool) {
+        let mut first = BTreeSet::new();
+        for symbol in symbols {
+            match symbol {
+                GrammarSymbol::Terminal(k) => {
+                    first.insert(k.clone());
+                    return (first, false);
+                }
+                GrammarSymbol::Variable(v) => {
+                    if let Some(f) = self.first.get(v) {
+                        first.extend(f.iter().cloned());
+                    }
+                    if !self.nullable.contains(v) {
+                        return (first, false);
+                    }
+                }
             }
         }
+        (first, true)
+    }
+
+    fn next_symbol(&self, item: &Item<K>) -> Option<&GrammarSymbol<K>> {
+        self.produces[item.0].right.get(item.1)
+    }
 
-        let canonical_collection: Vec<BTreeSet<GrammarItem>> =
-            tmp_canonical_collection.into_iter().collect();
-
-        let mut start = vec![];
-        for (i, v) in canonical_collection.iter().enumerate() {
-            if v == &self.get_closure({
-                let mut start_set = BTreeSet::new();
-                start_set.insert(((0, 0), vec![("#".to_string(), TokenType::EOF)]));
-                start_set
-            }) {
-                start.push(i);
+    fn closure(&self, items: ItemSet<K>) -> ItemSet<K> {
+        let mut closure = items.clone();
+        let mut work: Vec<Item<K>> = items.into_iter().collect();
+        while let Some((p, dot, lookahead)) = work.pop() {
+            let right = &self.produces[p].right;
+            let variable = match right.get(dot) {
+                Some(GrammarSymbol::Variable(v)) => v,
+                _ => continue,
+            };
+            let (first, nullable) = self.first_of(&right[dot + 1..]);
+            let mut lookaheads: Vec<Option<K>> = first.into_iter().map(Some).collect();
+            if nullable {
+                lookaheads.push(lookahead);
+            }
+            for (q, _) in self
+                .produces
+                .iter()
+                .enumerate()
+                .filter(|(_, q)| &q.left == variable)
+            {
+                for la in &lookaheads {
+                    let item = (q, 0, la.clone());
+                    if closure.insert(item.clone()) {
+                        work.push(item);
+                    }
+                }
             }
         }
+        closure
+    }
 
-        let tmp: BTreeMap<&BTreeSet<GrammarItem>, usize> = canonical_collection
+    fn go(&self, items: &ItemSet<K>, symbol: &GrammarSymbol<K>) -> ItemSet<K> {
+        let kernel = items
             .iter()
-            .enumerate()
-            .map(|(x, y)| (y, x))
+            .filter(|item| self.next_symbol(item) == Some(symbol))
+            .map(|(p, dot, la)| (*p, *dot + 1, la.clone()))
             .collect();
-        let simple_move_table: HashMap<(usize, GrammarSymbol), usize> = move_table
-            .into_iter()
-            .map(|((now, symbol), next)| {
-                (
-                    tmp.get(&now).map(|x| *x).unwrap_or_default(),
-                    symbol.clone(),
-                    tmp.get(&next).map(|x| *x).unwrap_or_default(),
-                )
-            })
-            .map(|(now, symbol, next)| ((now, symbol), next))
-            .collect();
-
-        // println!("all closures are {:?}", canonical_collection);
-        // println!("\t all move_table = {:?}", move_table);
-        // println!("\t simple_table = {:?}", simple_move_table);
-        let table = self.lalr1(&canonical_collection, &simple_move_table, all_symbols);
-        (canonical_collection, simple_move_table, start, table)
+        self.closure(kernel)
     }
 
-    fn lalr1(
-        &self,
-        canonical_collection: &Vec<BTreeSet<GrammarItem>>,
-        simple_move_table: &HashMap<(usize, GrammarSymbol), usize>,
-        all_symbols: HashSet<&GrammarSymbol>,
-    ) -> BTreeMap<(usize, usize, usize), BTreeSet<GrammarTerminal>> {
-        let mut bc = BTreeMap::new();
-        let mut table = BTreeMap::new();
-        //lalr(1)的分析开始!
-        //1.传播符表和计算表的建立
-        // panic!("canonical_collection = {:?}",canonical_collection);
-        let key_items: Vec<BTreeSet<GrammarItem>> = canonical_collection
-            .iter()
-            .map(|set| {
-                set.iter()
-                    .filter(|((pi, i), la)| *i != 0 || (*i == 0 && *pi == 0)) //wa!!!
-                    .map(|((pi, i), la)| ((*pi, *i), la.clone()))
-                    .collect()
-            })
-            .collect();
-        key_items.iter().enumerate().for_each(|(goto_i, K)| {
-            K.iter().for_each(|((pi, i), la)| {
-                //for A->alpha . beta in K
-                let j = self.get_closure({
-                    let mut t = BTreeSet::new();
-                    t.insert(((*pi, *i), vec![("#".to_string(), TokenType::EOF)]));
-                    t
-                });
-
-                all_symbols.iter().for_each(|X| {
-                    let goto_i_x = simple_move_table
-                        .get(&(goto_i, X.clone().clone()))
-                        .map(|a| *a)
-                        .unwrap();
-
-                    if let Some(Itemj) = key_items.get(goto_i_x) {
-                        Itemj.iter().for_each(|((pi2, i2), nla)| {
-                            if j.contains(&((*pi2, i2 - 1), nla.to_vec()))
-                                && la != &vec![("#".to_string(), TokenType::EOF)]
-                            {
-                                //自发
-                                // todo!();
-                                //(闭包下标, 产生式下标)
-                                table
-                                    .entry((goto_i, *pi, *i))
-                                    .or_insert(BTreeSet::new())
-                                    .extend(la.to_vec());
-                                table
-                                    .entry((goto_i_x, *pi2, *i2))
-                                    .or_insert(BTreeSet::new())
-                                    .extend(nla.to_vec());
-                            }
-                            if j.contains(&(
-                                (*pi2, i2 - 1),
-                                vec![("#".to_string(), TokenType::EOF)],
-                            )) {
-                                //传播
-                                // todo!();
-                                bc.entry((goto_i, *pi, *i))
-                                    .or_insert(BTreeSet::new())
-                                    .insert(((goto_i_x, pi2, i2), la));
-                            }
-                        });
+    fn build(&self) -> Result<Table<K>, Conflict<K>> {
+        // the LR(1) states, numbered in the order they are found
+        let mut start = BTreeSet::new();
+        start.insert((0, 0, None));
+        let mut states = vec![self.closure(start)];
+        let mut index: HashMap<ItemSet<K>, usize> = HashMap::new();
+        index.insert(states[0].clone(), 0);
+        let mut moves: BTreeMap<(usize, GrammarSymbol<K>), usize> = BTreeMap::new();
+        let mut i = 0;
+        while i < states.len() {
+            let symbols: BTreeSet<GrammarSymbol<K>> = states[i]
+                .iter()
+                .filter_map(|item| self.next_symbol(item).cloned())
+                .collect();
+            for symbol in symbols {
+                let next = self.go(&states[i], &symbol);
+                let j = match index.get(&next) {
+                    Some(j) => *j,
+                    None => {
+                        states.push(next.clone());
+                        index.insert(next, states.len() - 1);
+                        states.len() - 1
                     }
-                });
-            });
-        });
-
-        //2.由传播符表来填充计算表
-        bc.iter().for_each(|((ci, pi, i), vec_next)| {
-            vec_next.iter().for_each(|((nci, npi, ni), nla)| {
-                table
-                    .entry((*nci, **npi, **ni))
-                    .or_insert(nla.to_vec().into_iter().collect())
-                    .extend(nla.to_vec());
-            });
-        });
-
-        // panic!(
-        //     "\n \t 1.broadcast = {:?} \n\t 2.table_compute = {:?} \n\t3. cc={:?}",
-        //     bc, table, canonical_collection
-        // );
-        table
-    }
+                };
+                moves.insert((i, symbol), j);
+            }
+            i += 1;
+        }
 
-    fn build_lalr1_table(&self) -> (HashMap<(usize, GrammarSymbol), LRControll>, Vec<usize>) {
-        let (canonical_collection, move_table, start, latable) = self.get_canonical_collection();
-        let mut lalr1_table: HashMap<(usize, GrammarSymbol), LRControll> = HashMap::new();
-        // panic!(
-        //     "\n\t cc = {:?} \n\t la={:?}",
-        //     canonical_collection,
-        //     latable
-        // );
-        canonical_collection
-            .into_iter()
-            .enumerate()
-            .for_each(|(k, closure)| {
-                closure
-                    .into_iter()
-                    .for_each(|((produces_index, index), la)| {
-                        match self
-                            .vec_produces
-                            .get(produces_index)
-                            .map(|(A, rights)| (A, rights.get(index)))
-                        {
-                            Some((_A, Some(syb))) => match syb {
-                                GrammarSymbol::Terminal(a) => {
-                                    if let Some(j) = move_table.get(&(k, GTerminal!(a.clone()))) {
-                                        lalr1_table.insert(
-                                            (k, GTerminal!(a.clone())),
-                                            LRControll::Shift(*j),
-                                        );
-                                    }
-                                }
-                                GrammarSymbol::Variable(B) => {
-                                    if let Some(j) =
-                                        move_table.get(&(k, GrammarSymbol::Variable(B.to_string())))
-                                    {
-                                        lalr1_table.insert(
-                                            (k, GrammarSymbol::Variable(B.to_string())),
-                                            LRControll::GOTO(*j),
-                                        );
-                                    }
-                                }
-                                _ => panic!(
-                                    "\n \tbuild lr0_table failed have GrammarSymbol::Epsilon"
-                                ),
-                            },
-                            Some((A, None)) => {
-                                if A == &"S'".to_string() {
-                                    lalr1_table.insert(
-                                        (k, GTerminal!(("#".to_string(), TokenType::EOF))),
-                                        LRControll::ACC,
-                                    );
-                                } else {
-                                    let left = self
-                                        .vec_produces
-                                        .get(produces_index)
-                                        .map(|(a, _)| a.clone())
-                                        .unwrap_or(FILLER.to_string());
-                                    // panic!("there lalar_move_table = {:?} \n\t k = {}, pi = {},index={} \n\t la={:?}", latable, k,produces_index,index,la);
-                                    let tmp: HashMap<(usize, GrammarSymbol), LRControll> = latable
-                                        .get(&(k, produces_index, index))
-                                        .map(|la| {
-                                            // let tmp: HashMap<(usize, GrammarSymbol), LRControll> =
-                                            la.iter()
-                                                .map(|las| {
-                                                    (
-                                                        (k, GTerminal!(las.clone())),
-                                                        LRControll::Reduce(
-                                                            left.to_string(),
-                                                            (produces_index, index),
-                                                        ),
-                                                    )
-                                                })
-                                                .collect()
-                                        })
-                                        .unwrap();
-
-                                    lalr1_table.insert(
-                                        (k, GTerminal!(("#".to_string(), TokenType::EOF))),
-                                        LRControll::Reduce(
-                                            left.to_string(),
-                                            (produces_index, index),
-                                        ),
-                                    );
-                                    lalr1_table.extend(tmp);
-                                }
-                            }
-                            _ => panic!("vec_pro index out of bounds"),
-                        }
-                    });
+        // merge the states by core, keeping the first one's number order
+        let mut cores: HashMap<BTreeSet<(usize, usize)>, usize> = HashMap::new();
+        let mut merged: Vec<ItemSet<K>> = vec![];
+        let mut renumber = vec![];
+        for state in &states {
+            let core = state.iter().map(|(p, dot, _)| (*p, *dot)).collect();
+            let n = *cores.entry(core).or_insert_with(|| {
+                merged.push(BTreeSet::new());
+                merged.len() - 1
             });
-        // println!("\t lr0_table = {:?}", lr0_table);
-        (lalr1_table, start)
-    }
-
-    pub fn run(&self, input: &str) {
-        use LRControll::*;
-        let mut lexer = Lexer::new(input);
-
-        let (lalr1_table, start) = self.build_lalr1_table();
-
-        let mut symbols = vec![GTerminal!(("#".to_string(), TokenType::EOF))];
-        let mut states = start;
-
-        let mut now_token = lexer.next_token().unwrap();
+            merged[n].extend(state.iter().cloned());
+            renumber.push(n);
+        }
 
-        //TODO 终结符如何与非终结符区分?
-        loop {
-            let now_state = states.last().map(|x| *x).unwrap();
-            match lalr1_table
-                .get(&(now_state, GTerminal!(now_token.clone())))
-                .unwrap_or(&LRControll::default())
-            {
-                Shift(next_state) => {
-                    symbols.push(GTerminal!(now_token));
-                    states.push(*next_state);
-                    now_token = lexer.next_token().unwrap();
+        let mut table = Table {
+            produces: self.produces[1..].to_vec(),
+            actions: HashMap::new(),
+            gotos: HashMap::new(),
+        };
+        for ((from, symbol), to) in moves {
+            let (from, to) = (renumber[from], renumber[to]);
+            match symbol {
+                GrammarSymbol::Terminal(k) => {
+                    insert(&mut table.actions, from, Some(k), Action::Shift(to))?
                 }
-                Reduce(left, (right, _)) => {
-                    for _ in 0..self
-                        .vec_produces
-                        .get(*right)
-                        .map(|(_, r)| r.len())
-                        .unwrap_or(0)
-                    {
-                        symbols.pop();
-                        states.pop();
-                    }
-
-                    let next_state = lalr1_table.get(&(
-                        states.last().map(|x| *x).unwrap(),
-                        GVariable!(left.to_string()),
-                    ));
-                    match next_state {
-                        Some(GOTO(next_s)) => states.push(*next_s),
-                        _ => panic!("\n\t 1. \twant goto but have others={:?} \n\t 2. the env is {:?}, {:?} \n\t 3. the stack is {:?} {:?} \n\r 4. table = {:?}", next_state, now_state, left,symbols,states, lalr1_table),
-                    }
-                    symbols.push(GVariable!(left.to_string()));
+                GrammarSymbol::Variable(v) => {
+                    table.gotos.insert((from, v), to);
                 }
-                ACC => break,
-                _ => panic!(
-                    "\n\t 1. the state is {:?} \t input is {:?} \n\t 2. the stack is {:?} && {:?} \n\t3. the table is {:?}",
-                    now_state, now_token, symbols, states, lalr1_table
-                ),
             }
         }
-    }
-
-    //求First(beta z)
-    fn first(&self, pi: usize, i: usize, z: &Vec<GrammarTerminal>) -> Vec<GrammarTerminal> {
-        let beta = self
-            .vec_produces
-            .get(pi)
-            .map(|(a, b)| b.get(i + 1))
-            .flatten(); //这里的加一  A->a.X \beta, z
-        if let Some(beta) = beta {
-            let mut Y = beta;
-            let mut tmp: BTreeSet<GrammarTerminal> = BTreeSet::new();
-            loop {
-                match Y {
-                    GrammarSymbol::Terminal(a) => {
-                        tmp.insert(a.clone());
-                        break;
-                    }
-                    GrammarSymbol::Variable(v) => {
-                        self.produces.get(v).map(|Xs| {
-                            Xs.iter().for_each(|pi| {
-                                //X -> Y1Y2...Yn
-                                let pro_tmp = self.vec_produces.get(*pi);
-                                let mut i = 0;
-                                loop {
-                                    match pro_tmp.map(|(_, r)| r.get(i)).flatten() {
-                                        Some(Yi) => {
-                                            if Yi.is_epsilon() {
-                                                i += 1;
-                                            } else if Yi.is_terminal() {
-                                                tmp.insert(Yi.unwrap_terminal().clone());
-                                                break;
-                                            } else {
-                                                Y = &Yi;
-                                                break;
-                                            }
-                                        }
-                                        _ => {
-                                            tmp.extend(z.clone());
-                                            break;
-                                        }
-                                    }
-                                }
-                            });
-                        });
-                    }
+        for (state, items) in merged.iter().enumerate() {
+            for (p, dot, lookahead) in items {
+                if *dot < self.produces[*p].right.len() {
+                    continue;
                 }
+                let action = match p {
+                    0 => Action::Accept,
+                    p => Action::Reduce(p - 1),
+                };
+                insert(&mut table.actions, state, lookahead.clone(), action)?;
             }
-            tmp.into_iter().collect()
-        } else {
-            z.clone()
+        }
+        Ok(table)
+    }
+}
+
+fn insert<K: Kind>(
+    actions: &mut HashMap<(usize, Option<K>), Action>,
+    state: usize,
+    lookahead: Option<K>,
+    action: Action,
+) -> Result<(), Conflict<K>> {
+    match actions.get(&(state, lookahead.clone())) {
+        Some(old) if *old != action => Err(Conflict::Actions {
+            state,
+            lookahead,
+            actions: (*old, action),
+        }),
+        _ => {
+            actions.insert((state, lookahead), action);
+            Ok(())
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::GrammarSymbol::*;
     use super::*;
+    use crate::frontend::token::TokenType;
+
+    fn v(name: &str) -> GrammarSymbol<TokenType> {
+        Variable(name.to_string())
+    }
+
+    // S -> L = R | R, L -> * R | id, R -> L: LALR(1) but not SLR(1)
+    fn assignments() -> Grammar<TokenType> {
+        let mut g = Grammar::new("S");
+        g.add_production("S", vec![v("L"), Terminal(TokenType::ASSIGN), v("R")]);
+        g.add_production("S", vec![v("R")]);
+        g.add_production("L", vec![Terminal(TokenType::ASTERISK), v("R")]);
+        g.add_production("L", vec![Terminal(TokenType::IDENT)]);
+        g.add_production("R", vec![v("L")]);
+        g
+    }
+
+    #[test]
+    fn test_first() {
+        let mut g = Grammar::new("S");
+        g.add_production(
+            "S",
+            vec![Variable("A".into()), Variable("B".into()), Terminal('c')],
+        );
+        g.add_production("A", vec![Terminal('a')]);
+        g.add_production("A", vec![]);
+        g.add_production("B", vec![Terminal('b')]);
+        g.add_production("B", vec![]);
+        let builder = Builder::new(&g);
+        let first: Vec<char> = builder.first["S"].iter().cloned().collect();
+        assert_eq!(first, ['a', 'b', 'c']);
+        assert!(builder.nullable.contains("A") && !builder.nullable.contains("S"));
+    }
+
+    #[test]
+    fn test_lalr1_table() {
+        let table = assignments().build_lalr1_table().unwrap();
+        // the canonical LR(1) collection has 14 states, LALR(1) merges 4 pairs
+        let states: BTreeSet<usize> = table.actions.keys().map(|(s, _)| *s).collect();
+        assert_eq!(states.len(), 10);
+        assert_eq!(
+            table.expected(0),
+            [Some(TokenType::IDENT), Some(TokenType::ASTERISK)]
+        );
+        let accept = table.goto(0, "S").unwrap();
+        assert_eq!(table.action(accept, None), Some(Action::Accept));
+    }
+
     #[test]
-    fn test_lalr1_run() {
-        let v = vec![
-            (
-                "S'".to_string(),
-                vec![GrammarSymbol::Variable("S".to_string())],
-            ),
-            (
-                "S".to_string(),
-                vec![GrammarSymbol::Terminal(("a".to_string(), TokenType::IDENT))],
-            ),
-            (
-                "S".to_string(),
-                vec![
-                    GrammarSymbol::Terminal(("a".to_string(), TokenType::IDENT)),
-                    GrammarSymbol::Variable("S".to_string()),
-                ],
-            ),
-        ];
-        let mut g = Grammar::from(v, "S".to_string());
-        let mut ts = HashSet::new();
-        ts.insert(GrammarSymbol::Terminal(("a".to_string(), TokenType::IDENT)));
-        g.terminals = ts;
-        g.run(&"a a a  #");
+    fn test_conflicts() {
+        let mut g = Grammar::new("E");
+        g.add_production("E", vec![v("E"), Terminal(TokenType::PLUS), v("E")]);
+        g.add_production("E", vec![Terminal(TokenType::IDENT)]);
+        let conflict = g.build_lalr1_table().unwrap_err();
+        assert_eq!(
+            conflict.to_string(),
+            "shift/reduce conflict in state 4 on PLUS"
+        );
+
+        let mut g = Grammar::new("S");
+        g.add_production("S", vec![v("A")]);
+        g.add_production("S", vec![v("B")]);
+        g.add_production("A", vec![Terminal(TokenType::IDENT)]);
+        g.add_production("B", vec![Terminal(TokenType::IDENT)]);
+        let conflict = g.build_lalr1_table().unwrap_err();
+        assert_eq!(
+            conflict.to_string(),
+            "reduce/reduce conflict in state 1 on end of input"
+        );
+
+        let mut g: Grammar<TokenType> = Grammar::new("S");
+        g.add_production("S", vec![v("T")]);
+        assert_eq!(
+            g.build_lalr1_table().unwrap_err().to_string(),
+            "T has no productions"
+        );
+        assert_eq!(
+            Grammar::<TokenType>::new("S")
+                .build_lalr1_table()
+                .unwrap_err(),
+            Conflict::Undefined("S".to_string())
+        );
     }
 }
@@ -1,9 +1,10 @@
-pub const FILLER: &'static str = "";
+pub const FILLER: &str = "";
 
-#[derive(PartialOrd, Eq, PartialEq, Ord, Debug, Clone,Hash)]
+#[derive(PartialOrd, Eq, PartialEq, Ord, Debug, Clone, Hash, Default)]
 pub enum TokenType {
     ILLEGAL,
     EOF,
+    #[default]
     VareEpsilon,
 
     //identifier + literal
@@ -47,10 +48,6 @@ pub enum TokenType {
     RETURN,
 }
 
-impl Default for TokenType{
-    fn default() -> Self { TokenType::VareEpsilon }
-}
-
 pub type Literal = String;
 pub type Token = (Literal, TokenType); //(Literal, Type)
 
@@ -1,27 +1,24 @@
+#![allow(clippy::upper_case_acronyms)]
+//! An LALR(1) parser generator.
+//!
+//! Declare a [`Grammar`], build its [`Table`] once, then run the table
+//! over any stream of [`Terminal`]s, either into a [`ParseTree`] or
+//! through your own [`Actions`]:
+//!
+//! ```
+//! use lalr1::frontend::lexer::Lexer;
+//! use lalr1::frontend::token::TokenType::*;
+//! use lalr1::{Grammar, GrammarSymbol::*};
+//!
+//! // L -> L , int | int
+//! let mut g = Grammar::new("L");
+//! g.add_production("L", vec![Variable("L".to_string()), Terminal(COMMA), Terminal(DECINT)]);
+//! g.add_production("L", vec![Terminal(DECINT)]);
+//! let table = g.build_lalr1_table().unwrap();
+//! let tree = table.parse(Lexer::new("1, 2, 3")).unwrap();
+//! assert_eq!(tree.leaves().len(), 5);
+//! ```
 pub mod frontend;
-// use std::collections::BTreeSet;
-// use std::collections::HashMap;
-// use std::collections::HashSet;
-// use frontend::parser::Grammer;
 
-// fn main() {
-
-//     let mut g = Grammer::new();
-//         g.start = "S".to_string();
-//         let mut t = HashSet::new();
-//         t.insert("a".to_string());
-//         t.insert("b".to_string());
-//         g.terminals = t;
-
-//         let mut v = HashSet::new();
-//         v.insert("S".to_string());
-//         // v.insert("B".to_string());
-//         g.vars = v;
-//         let mut p = HashMap::new();
-//         p.insert("S".to_string(), vec!["a".to_string(), "b".to_string()]);
-
-//         g.produces = p;
-
-//         g.run("a#".to_string());
-
-// }
\ No newline at end of file
+pub use frontend::driver::{Actions, ParseError, ParseTree, Terminal};
+pub use frontend::parser::{Action, Conflict, Grammar, GrammarSymbol, Production, Table};
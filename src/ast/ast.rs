@@ -1,4 +1,5 @@
-use crate::token::token::{self, Token};
+use crate::token::precedence::{get_precedence, Pri};
+use crate::token::token::{self, Span, Symbol, Token};
 
 type TokenAST = Option<token::Token>;
 
@@ -6,21 +7,35 @@ type TokenAST = Option<token::Token>;
 pub enum ASTNode {
     Program(Program),
     LetStatement(LetStatement),
+    ClassStatement(ClassStatement),
+    ImportStatement(ImportStatement),
+    AssignExpression(AssignExpression),
     BlockStatement(BlockStatement),
     Boolean(Boolean),
     CallExpression(CallExpression),
     ExpressionStatement(ExpressionStatement),
     FuncLiteral(FuncLiteral),
+    MacroLiteral(FuncLiteral), // macro(params) { body }, never named
     Identifier(Identifier),
     IfExpression(IfExpression),
+    MatchExpression(MatchExpression),
+    WhileExpression(WhileExpression),
+    ForExpression(ForExpression),
+    BreakStatement(TokenAST),
+    ContinueStatement(TokenAST),
+    MemberExpression(MemberExpression),
+    Super(TokenAST),
     InfixExpression(InfixExpression),
     IntegerLiteral(IntegerLiteral),
+    FloatLiteral(FloatLiteral),
     PrefixExpression(PrefixExpression),
     ReturnStatement(ReturnStatement),
     StringLiteral(TokenAST, String),
-    ArrayLiteral(TokenAST, Vec<Box<ASTNode>>),
-    IndexLiteral(TokenAST, Box<ASTNode>, Box<ASTNode>), //token, left, index
-    HashLiteral(TokenAST, Vec<(Box<ASTNode>, Box<ASTNode>)>),
+    InterpolatedString(Vec<Box<ASTNode>>), //"a ${x} b": literal, expression, ..., literal
+    ArrayLiteral(TokenAST, Vec<Box<ASTNode>>, TokenAST), //token, elements, ]
+    IndexLiteral(TokenAST, Box<ASTNode>, Box<ASTNode>, TokenAST), //token, left, index, ]
+    SliceExpression(SliceExpression),
+    HashLiteral(TokenAST, Vec<(Box<ASTNode>, Box<ASTNode>)>, TokenAST), //token, pairs, }
     None,
 }
 
@@ -33,14 +48,229 @@ impl ASTNode {
     }
     pub fn is_none(&self) -> bool {
         use self::ASTNode::None;
-        match self {
-            None => true,
-            _ => false,
-        }
+        matches!(self, None)
     }
     pub fn is_some(&self) -> bool {
         !self.is_none()
     }
+
+    /// The source range this node was parsed from.
+    pub fn span(&self) -> Span {
+        use self::ASTNode::*;
+        match self {
+            Program(p) => p
+                .statements
+                .iter()
+                .fold(Span::default(), |span, stmt| span.to(stmt.span())),
+            LetStatement(s) => token_span(&s.token).to(s.name.span()).to(s.value.span()),
+            ClassStatement(c) => c.token.span.to(c.end.span),
+            ImportStatement(i) => i.token.span.to(i.end.span),
+            AssignExpression(a) => a.target.span().to(a.value.span()),
+            BlockStatement(b) => b.token.span.to(b.end.span),
+            Boolean(b) => b.token.span,
+            CallExpression(c) => c.func.span().to(c.end.span),
+            ExpressionStatement(e) => e.token.span.to(e.expression.span()),
+            FuncLiteral(f) | MacroLiteral(f) => f.token.span.to(f.body.span()),
+            Identifier(i) => i.span(),
+            IfExpression(i) => i
+                .token
+                .span
+                .to(i.consequence.span())
+                .to(i.alternative.span()),
+            MatchExpression(m) => m.token.span.to(m.end.span),
+            WhileExpression(w) => w.token.span.to(w.body.span()),
+            ForExpression(f) => f.token.span.to(f.body.span()),
+            BreakStatement(t) | ContinueStatement(t) | Super(t) => token_span(t),
+            MemberExpression(m) => m.object.span().to(m.name.span()),
+            InfixExpression(i) => i.left.span().to(i.right.span()),
+            IntegerLiteral(i) => i.token.span,
+            FloatLiteral(f) => f.token.span,
+            PrefixExpression(p) => p.token.span.to(p.right.span()),
+            ReturnStatement(r) => token_span(&r.token).to(r.return_value.span()),
+            StringLiteral(t, _) => token_span(t),
+            InterpolatedString(parts) => match (parts.first(), parts.last()) {
+                (Some(first), Some(last)) => first.span().to(last.span()),
+                _ => Span::default(),
+            },
+            ArrayLiteral(t, _, end) => token_span(t).to(token_span(end)),
+            IndexLiteral(_, left, _, end) => left.span().to(token_span(end)),
+            SliceExpression(s) => s.left.span().to(s.end.span),
+            HashLiteral(t, _, end) => token_span(t).to(token_span(end)),
+            None => Span::default(),
+        }
+    }
+}
+
+impl ASTNode {
+    /// The variant name, e.g. `"LetStatement"`.
+    pub fn kind(&self) -> &'static str {
+        use self::ASTNode::*;
+        match self {
+            Program(_) => "Program",
+            LetStatement(_) => "LetStatement",
+            ClassStatement(_) => "ClassStatement",
+            ImportStatement(_) => "ImportStatement",
+            AssignExpression(_) => "AssignExpression",
+            BlockStatement(_) => "BlockStatement",
+            Boolean(_) => "Boolean",
+            CallExpression(_) => "CallExpression",
+            ExpressionStatement(_) => "ExpressionStatement",
+            FuncLiteral(_) => "FuncLiteral",
+            MacroLiteral(_) => "MacroLiteral",
+            Identifier(_) => "Identifier",
+            IfExpression(_) => "IfExpression",
+            MatchExpression(_) => "MatchExpression",
+            WhileExpression(_) => "WhileExpression",
+            ForExpression(_) => "ForExpression",
+            BreakStatement(_) => "BreakStatement",
+            ContinueStatement(_) => "ContinueStatement",
+            MemberExpression(_) => "MemberExpression",
+            Super(_) => "Super",
+            InfixExpression(_) => "InfixExpression",
+            IntegerLiteral(_) => "IntegerLiteral",
+            FloatLiteral(_) => "FloatLiteral",
+            PrefixExpression(_) => "PrefixExpression",
+            ReturnStatement(_) => "ReturnStatement",
+            StringLiteral(_, _) => "StringLiteral",
+            InterpolatedString(_) => "InterpolatedString",
+            ArrayLiteral(_, _, _) => "ArrayLiteral",
+            IndexLiteral(_, _, _, _) => "IndexLiteral",
+            SliceExpression(_) => "SliceExpression",
+            HashLiteral(_, _, _) => "HashLiteral",
+            None => "None",
+        }
+    }
+
+    /// The child nodes in source order. `ASTNode::None` placeholders are
+    /// left out.
+    pub fn children(&self) -> Vec<&ASTNode> {
+        use self::ASTNode::*;
+        let children: Vec<&ASTNode> = match self {
+            Program(p) => p.statements.iter().map(|s| s.as_ref()).collect(),
+            LetStatement(s) => {
+                let mut nodes = s.pattern.as_ref().map(|p| p.nodes()).unwrap_or_default();
+                nodes.push(&s.value);
+                nodes
+            }
+            ClassStatement(c) => c.members.iter().map(|m| m.as_ref()).collect(),
+            AssignExpression(a) => vec![&a.target, &a.value],
+            BlockStatement(b) => b.statements.iter().map(|s| s.as_ref()).collect(),
+            CallExpression(c) => std::iter::once(c.func.as_ref())
+                .chain(c.args.iter().map(|a| a.as_ref()))
+                .chain(c.named.iter().map(|(_, a)| a.as_ref()))
+                .collect(),
+            ExpressionStatement(e) => vec![&e.expression],
+            FuncLiteral(f) | MacroLiteral(f) => f
+                .params
+                .iter()
+                .map(|p| p.default.as_ref())
+                .chain(std::iter::once(f.body.as_ref()))
+                .collect(),
+            IfExpression(i) => vec![&i.condition, &i.consequence, &i.alternative],
+            MatchExpression(m) => std::iter::once(m.value.as_ref())
+                .chain(m.arms.iter().flat_map(|arm| {
+                    let mut nodes = arm.pattern.nodes();
+                    nodes.extend([arm.guard.as_ref(), arm.body.as_ref()]);
+                    nodes
+                }))
+                .collect(),
+            WhileExpression(w) => vec![&w.condition, &w.body],
+            ForExpression(f) => vec![&f.iterable, &f.body],
+            MemberExpression(m) => vec![&m.object],
+            InfixExpression(i) => vec![&i.left, &i.right],
+            PrefixExpression(p) => vec![&p.right],
+            ReturnStatement(r) => vec![&r.return_value],
+            InterpolatedString(parts) | ArrayLiteral(_, parts, _) => {
+                parts.iter().map(|e| e.as_ref()).collect()
+            }
+            IndexLiteral(_, left, index, _) => vec![left, index],
+            SliceExpression(s) => vec![&s.left, &s.low, &s.high],
+            HashLiteral(_, pairs, _) => pairs
+                .iter()
+                .flat_map(|(k, v)| vec![k.as_ref(), v.as_ref()])
+                .collect(),
+            Boolean(_)
+            | Identifier(_)
+            | IntegerLiteral(_)
+            | FloatLiteral(_)
+            | StringLiteral(_, _)
+            | ImportStatement(_)
+            | BreakStatement(_)
+            | ContinueStatement(_)
+            | Super(_)
+            | None => vec![],
+        };
+        children.into_iter().filter(|c| c.is_some()).collect()
+    }
+
+    /// Like `children`, but for rewriting the tree in place.
+    pub fn children_mut(&mut self) -> Vec<&mut ASTNode> {
+        use self::ASTNode::*;
+        let children: Vec<&mut ASTNode> = match self {
+            Program(p) => p.statements.iter_mut().map(|s| s.as_mut()).collect(),
+            LetStatement(s) => {
+                let mut nodes = s
+                    .pattern
+                    .as_mut()
+                    .map(|p| p.nodes_mut())
+                    .unwrap_or_default();
+                nodes.push(&mut s.value);
+                nodes
+            }
+            ClassStatement(c) => c.members.iter_mut().map(|m| m.as_mut()).collect(),
+            AssignExpression(a) => vec![&mut a.target, &mut a.value],
+            BlockStatement(b) => b.statements.iter_mut().map(|s| s.as_mut()).collect(),
+            CallExpression(c) => std::iter::once(c.func.as_mut())
+                .chain(c.args.iter_mut().map(|a| a.as_mut()))
+                .chain(c.named.iter_mut().map(|(_, a)| a.as_mut()))
+                .collect(),
+            ExpressionStatement(e) => vec![&mut e.expression],
+            FuncLiteral(f) | MacroLiteral(f) => f
+                .params
+                .iter_mut()
+                .map(|p| p.default.as_mut())
+                .chain(std::iter::once(f.body.as_mut()))
+                .collect(),
+            IfExpression(i) => vec![&mut i.condition, &mut i.consequence, &mut i.alternative],
+            MatchExpression(m) => std::iter::once(m.value.as_mut())
+                .chain(m.arms.iter_mut().flat_map(|arm| {
+                    let mut nodes = arm.pattern.nodes_mut();
+                    nodes.extend([arm.guard.as_mut(), arm.body.as_mut()]);
+                    nodes
+                }))
+                .collect(),
+            WhileExpression(w) => vec![&mut w.condition, &mut w.body],
+            ForExpression(f) => vec![&mut f.iterable, &mut f.body],
+            MemberExpression(m) => vec![&mut m.object],
+            InfixExpression(i) => vec![&mut i.left, &mut i.right],
+            PrefixExpression(p) => vec![&mut p.right],
+            ReturnStatement(r) => vec![&mut r.return_value],
+            InterpolatedString(parts) | ArrayLiteral(_, parts, _) => {
+                parts.iter_mut().map(|e| e.as_mut()).collect()
+            }
+            IndexLiteral(_, left, index, _) => vec![left, index],
+            SliceExpression(s) => vec![&mut s.left, &mut s.low, &mut s.high],
+            HashLiteral(_, pairs, _) => pairs
+                .iter_mut()
+                .flat_map(|(k, v)| vec![k.as_mut(), v.as_mut()])
+                .collect(),
+            Boolean(_)
+            | Identifier(_)
+            | IntegerLiteral(_)
+            | FloatLiteral(_)
+            | StringLiteral(_, _)
+            | ImportStatement(_)
+            | BreakStatement(_)
+            | ContinueStatement(_)
+            | Super(_)
+            | None => vec![],
+        };
+        children.into_iter().filter(|c| c.is_some()).collect()
+    }
+}
+
+fn token_span(token: &TokenAST) -> Span {
+    token.as_ref().map(|t| t.span).unwrap_or_default()
 }
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
@@ -58,7 +288,10 @@ impl Program {
 pub struct LetStatement {
     pub token: TokenAST,
     pub name: Identifier,
+    pub pattern: Option<Pattern>, // let [a, b] = ..; the name is unused then
+    pub ty: Option<Type>,         // let name: int = ..
     pub value: Box<ASTNode>,
+    pub doc: Option<String>, // `///` lines right before the let
 }
 
 impl LetStatement {
@@ -66,7 +299,10 @@ impl LetStatement {
         LetStatement {
             token: None,
             name: Identifier::default(),
+            pattern: None,
+            ty: None,
             value: Box::new(ASTNode::None),
+            doc: None,
         }
     }
     pub fn is_none(&self) -> bool {
@@ -74,14 +310,78 @@ impl LetStatement {
     }
 }
 
+// class Name < Superclass { let field = value; fn method(self) { body } }
+// members are the fields' LetStatements and the methods' named
+// FuncLiterals, in source order
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub struct ClassStatement {
+    token: token::Token,
+    pub name: Identifier,
+    pub superclass: Option<Identifier>,
+    pub members: Vec<Box<ASTNode>>,
+    pub end: token::Token, // }
+}
+
+impl ClassStatement {
+    pub fn new(token: token::Token) -> Self {
+        ClassStatement {
+            token: token,
+            name: Identifier::default(),
+            superclass: None,
+            members: vec![],
+            end: Token::default(),
+        }
+    }
+}
+
+// import "path" as name; the path is relative to the importing file
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub struct ImportStatement {
+    token: token::Token,
+    pub path: String,
+    pub alias: Identifier,
+    pub end: token::Token, // ;
+}
+
+impl ImportStatement {
+    pub fn new(token: token::Token) -> Self {
+        ImportStatement {
+            token: token,
+            path: String::new(),
+            alias: Identifier::default(),
+            end: Token::default(),
+        }
+    }
+}
+
+// target = value, where the target is a variable or an index into one.
+// `target op= value` is kept as `target = target op value` with the
+// compound operator as the token.
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub struct AssignExpression {
+    pub token: token::Token,
+    pub target: Box<ASTNode>,
+    pub value: Box<ASTNode>,
+}
+
+impl AssignExpression {
+    pub fn new(token: token::Token) -> Self {
+        AssignExpression {
+            token: token,
+            target: Box::new(ASTNode::None),
+            value: Box::new(ASTNode::None),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
 pub struct Identifier {
     pub token: TokenAST,
-    pub value: String,
+    pub value: Symbol,
 }
 
 impl Identifier {
-    pub fn new(token: token::Token, value: String) -> Self {
+    pub fn new(token: token::Token, value: Symbol) -> Self {
         Self {
             token: Some(token),
             value,
@@ -91,9 +391,13 @@ impl Identifier {
     pub fn default() -> Self {
         Identifier {
             token: None,
-            value: String::default(),
+            value: Symbol::default(),
         }
     }
+
+    pub fn span(&self) -> Span {
+        token_span(&self.token)
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
@@ -113,12 +417,28 @@ impl ReturnStatement {
 
 //Expression
 
-#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+// the token is just where the statement starts, which may be a `(` that
+// did not survive into the tree, so like a span it is left out of equality
+#[derive(Debug, Clone)]
 pub struct ExpressionStatement {
     token: token::Token,
     pub expression: Box<ASTNode>,
 }
 
+impl PartialEq for ExpressionStatement {
+    fn eq(&self, other: &Self) -> bool {
+        self.expression == other.expression
+    }
+}
+
+impl Eq for ExpressionStatement {}
+
+impl std::hash::Hash for ExpressionStatement {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.expression.hash(state);
+    }
+}
+
 impl ExpressionStatement {
     pub fn new(token: token::Token) -> Self {
         ExpressionStatement {
@@ -128,7 +448,7 @@ impl ExpressionStatement {
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+#[derive(Debug, PartialEq, Clone, Eq, Hash, Default)]
 pub struct IntegerLiteral {
     token: token::Token,
     pub value: i64,
@@ -142,15 +462,37 @@ impl IntegerLiteral {
     }
 }
 
-impl Default for IntegerLiteral {
-    fn default() -> Self {
-        IntegerLiteral {
-            token: Token::default(),
-            value: 0,
+// f64 is neither Eq nor Hash, so literals compare by bit pattern
+#[derive(Debug, Clone)]
+pub struct FloatLiteral {
+    token: token::Token,
+    pub value: f64,
+}
+
+impl FloatLiteral {
+    pub fn new(token: token::Token) -> Self {
+        FloatLiteral {
+            token: token,
+            value: 0.0,
         }
     }
 }
 
+impl PartialEq for FloatLiteral {
+    fn eq(&self, other: &Self) -> bool {
+        self.token == other.token && self.value.to_bits() == other.value.to_bits()
+    }
+}
+
+impl Eq for FloatLiteral {}
+
+impl std::hash::Hash for FloatLiteral {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.token.hash(state);
+        self.value.to_bits().hash(state);
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
 pub struct PrefixExpression {
     token: token::Token,
@@ -220,10 +562,274 @@ impl IfExpression {
     }
 }
 
+// match value { pattern => body, pattern if guard => body }
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub struct MatchExpression {
+    token: token::Token,
+    pub value: Box<ASTNode>,
+    pub arms: Vec<MatchArm>,
+    pub end: token::Token, // }
+}
+
+impl MatchExpression {
+    pub fn new(token: token::Token) -> Self {
+        MatchExpression {
+            token: token,
+            value: Box::new(ASTNode::None),
+            arms: vec![],
+            end: Token::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Box<ASTNode>, // ASTNode::None without `if`
+    pub body: Box<ASTNode>,
+}
+
+/// A type annotation.
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub enum Type {
+    Int,
+    Float,
+    Bool,
+    Str,
+    Range,
+    /// Anything; fits every other type and every type fits it.
+    Any,
+    /// `[int]`
+    Array(Box<Type>),
+    /// `{str: int}`
+    Map(Box<Type>, Box<Type>),
+    /// `fn(int, str) -> bool`
+    Func(Vec<Type>, Box<Type>),
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Int => f.write_str("int"),
+            Type::Float => f.write_str("float"),
+            Type::Bool => f.write_str("bool"),
+            Type::Str => f.write_str("str"),
+            Type::Range => f.write_str("range"),
+            Type::Any => f.write_str("any"),
+            Type::Array(element) => write!(f, "[{}]", element),
+            Type::Map(key, value) => write!(f, "{{{}: {}}}", key, value),
+            Type::Func(params, ret) => {
+                let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+                write!(f, "fn({}) -> {}", params.join(", "), ret)
+            }
+        }
+    }
+}
+
+/// The left side of a match arm.
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub enum Pattern {
+    /// A number, string or boolean, compared with `==`.
+    Literal(Box<ASTNode>),
+    /// A name, bound to whatever is there.
+    Binding(Identifier),
+    /// `_`
+    Wildcard,
+    /// `[a, b, ...rest]`; the rest is a binding, or a wildcard for `...`.
+    Array(Vec<Pattern>, Option<Box<Pattern>>),
+    /// `{"key": pattern}`; the keys are literals and other keys may be present.
+    Hash(Vec<(Box<ASTNode>, Pattern)>),
+}
+
+impl Pattern {
+    /// The names it binds, in source order.
+    pub fn bindings(&self) -> Vec<&Identifier> {
+        match self {
+            Pattern::Binding(id) => vec![id],
+            Pattern::Literal(_) | Pattern::Wildcard => vec![],
+            Pattern::Array(elements, rest) => elements
+                .iter()
+                .chain(rest.as_deref())
+                .flat_map(|e| e.bindings())
+                .collect(),
+            Pattern::Hash(pairs) => pairs.iter().flat_map(|(_, p)| p.bindings()).collect(),
+        }
+    }
+
+    pub fn bindings_mut(&mut self) -> Vec<&mut Identifier> {
+        match self {
+            Pattern::Binding(id) => vec![id],
+            Pattern::Literal(_) | Pattern::Wildcard => vec![],
+            Pattern::Array(elements, rest) => elements
+                .iter_mut()
+                .chain(rest.as_deref_mut())
+                .flat_map(|e| e.bindings_mut())
+                .collect(),
+            Pattern::Hash(pairs) => pairs
+                .iter_mut()
+                .flat_map(|(_, p)| p.bindings_mut())
+                .collect(),
+        }
+    }
+
+    /// The literal nodes inside, in source order.
+    pub fn nodes(&self) -> Vec<&ASTNode> {
+        match self {
+            Pattern::Literal(node) => vec![node],
+            Pattern::Binding(_) | Pattern::Wildcard => vec![],
+            Pattern::Array(elements, _) => elements.iter().flat_map(|e| e.nodes()).collect(),
+            Pattern::Hash(pairs) => pairs
+                .iter()
+                .flat_map(|(key, value)| {
+                    let mut nodes = vec![key.as_ref()];
+                    nodes.extend(value.nodes());
+                    nodes
+                })
+                .collect(),
+        }
+    }
+
+    pub fn nodes_mut(&mut self) -> Vec<&mut ASTNode> {
+        match self {
+            Pattern::Literal(node) => vec![node],
+            Pattern::Binding(_) | Pattern::Wildcard => vec![],
+            Pattern::Array(elements, _) => {
+                elements.iter_mut().flat_map(|e| e.nodes_mut()).collect()
+            }
+            Pattern::Hash(pairs) => pairs
+                .iter_mut()
+                .flat_map(|(key, value)| {
+                    let mut nodes = vec![key.as_mut()];
+                    nodes.extend(value.nodes_mut());
+                    nodes
+                })
+                .collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Literal(node) => write!(f, "{}", node),
+            Pattern::Binding(id) => write!(f, "{}", id.value),
+            Pattern::Wildcard => f.write_str("_"),
+            Pattern::Array(elements, rest) => {
+                f.write_str("[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                if let Some(rest) = rest {
+                    if !elements.is_empty() {
+                        f.write_str(", ")?;
+                    }
+                    match rest.as_ref() {
+                        Pattern::Wildcard => f.write_str("...")?,
+                        rest => write!(f, "...{}", rest)?,
+                    }
+                }
+                f.write_str("]")
+            }
+            Pattern::Hash(pairs) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+// while (condition) { body }
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub struct WhileExpression {
+    token: token::Token,
+    pub condition: Box<ASTNode>,
+    pub body: Box<ASTNode>,
+}
+
+impl WhileExpression {
+    pub fn new(token: token::Token) -> Self {
+        WhileExpression {
+            token: token,
+            condition: Box::new(ASTNode::None),
+            body: Box::new(ASTNode::None),
+        }
+    }
+}
+
+// for (variable in iterable) { body }
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub struct ForExpression {
+    token: token::Token,
+    pub variable: Identifier,
+    pub iterable: Box<ASTNode>,
+    pub body: Box<ASTNode>,
+}
+
+impl ForExpression {
+    pub fn new(token: token::Token) -> Self {
+        ForExpression {
+            token: token,
+            variable: Identifier::default(),
+            iterable: Box::new(ASTNode::None),
+            body: Box::new(ASTNode::None),
+        }
+    }
+}
+
+// left[low:high]
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub struct SliceExpression {
+    token: token::Token, // [
+    pub left: Box<ASTNode>,
+    pub low: Box<ASTNode>,  // ASTNode::None when left out
+    pub high: Box<ASTNode>, // ASTNode::None when left out
+    pub end: token::Token,  // ]
+}
+
+impl SliceExpression {
+    pub fn new(token: token::Token) -> Self {
+        SliceExpression {
+            token: token,
+            left: Box::new(ASTNode::None),
+            low: Box::new(ASTNode::None),
+            high: Box::new(ASTNode::None),
+            end: Token::default(),
+        }
+    }
+}
+
+// object.name
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub struct MemberExpression {
+    token: token::Token, // .
+    pub object: Box<ASTNode>,
+    pub name: Identifier,
+}
+
+impl MemberExpression {
+    pub fn new(token: token::Token) -> Self {
+        MemberExpression {
+            token: token,
+            object: Box::new(ASTNode::None),
+            name: Identifier::default(),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
 pub struct BlockStatement {
     token: token::Token,
     pub statements: Vec<Box<ASTNode>>,
+    pub end: token::Token, // }
 }
 
 impl BlockStatement {
@@ -231,6 +837,7 @@ impl BlockStatement {
         BlockStatement {
             token: token,
             statements: vec![],
+            end: Token::default(),
         }
     }
 }
@@ -238,15 +845,56 @@ impl BlockStatement {
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
 pub struct FuncLiteral {
     token: token::Token,
-    pub params: Vec<Option<Box<Identifier>>>,
+    pub name: Option<Identifier>, // only methods are named
+    pub params: Vec<Parameter>,
+    pub ret: Option<Type>, // fn(..) -> int { .. }
     pub body: Box<ASTNode>,
 }
 
+// name, name = default or ...name, each optionally annotated as name: int;
+// a rest parameter comes last
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub struct Parameter {
+    pub name: Identifier,
+    pub ty: Option<Type>, // for a rest parameter, the type of the whole array
+    pub default: Box<ASTNode>, // ASTNode::None when the argument is required
+    pub rest: bool,
+}
+
+impl Parameter {
+    pub fn new(name: Identifier) -> Self {
+        Parameter {
+            name,
+            ty: None,
+            default: Box::new(ASTNode::None),
+            rest: false,
+        }
+    }
+}
+
+impl std::fmt::Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.rest {
+            f.write_str("...")?;
+        }
+        write!(f, "{}", self.name.value)?;
+        if let Some(ty) = &self.ty {
+            write!(f, ": {}", ty)?;
+        }
+        if self.default.is_some() {
+            write!(f, " = {}", self.default)?;
+        }
+        Ok(())
+    }
+}
+
 impl FuncLiteral {
     pub fn new(token: token::Token) -> Self {
         FuncLiteral {
             token: token,
+            name: None,
             params: Vec::new(),
+            ret: None,
             body: Box::new(ASTNode::None),
         }
     }
@@ -255,8 +903,10 @@ impl FuncLiteral {
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
 pub struct CallExpression {
     token: token::Token,
-    pub func: Box<ASTNode>,      //函数名
-    pub args: Vec<Box<ASTNode>>, //函数定义时的参数
+    pub func: Box<ASTNode>,                     //函数名
+    pub args: Vec<Box<ASTNode>>,                //函数定义时的参数
+    pub named: Vec<(Identifier, Box<ASTNode>)>, // name: value, after the positional args
+    pub end: token::Token,                      // )
 }
 
 impl CallExpression {
@@ -265,6 +915,262 @@ impl CallExpression {
             token: token,
             func: Box::new(ASTNode::None),
             args: Vec::new(),
+            named: Vec::new(),
+            end: Token::default(),
+        }
+    }
+}
+
+// Printing uses the parser's own precedence table, so an expression gets
+// parentheses exactly where reparsing it would otherwise group differently.
+fn binding_power(node: &ASTNode) -> u8 {
+    match node {
+        ASTNode::InfixExpression(i) => get_precedence(i.token.ty),
+        ASTNode::PrefixExpression(_) => Pri::PREFIX as u8,
+        ASTNode::AssignExpression(_) => Pri::ASSIGN as u8,
+        _ => u8::MAX,
+    }
+}
+
+// operators are left-associative, so a right operand of equal precedence
+// needs parentheses and a left one does not
+fn write_operand(
+    f: &mut std::fmt::Formatter<'_>,
+    node: &ASTNode,
+    precedence: u8,
+    right: bool,
+) -> std::fmt::Result {
+    let bp = binding_power(node);
+    if bp < precedence || (right && bp == precedence) {
+        write!(f, "({})", node)
+    } else {
+        write!(f, "{}", node)
+    }
+}
+
+fn write_list(f: &mut std::fmt::Formatter<'_>, nodes: &[Box<ASTNode>]) -> std::fmt::Result {
+    for (i, node) in nodes.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", node)?;
+    }
+    Ok(())
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    f.write_str("\"")?;
+    write_string_body(f, s)?;
+    f.write_str("\"")
+}
+
+// the inverse of the lexer's escapes
+fn write_string_body(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    use std::fmt::Write;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '$' if chars.peek() == Some(&'{') => f.write_str("\\$")?,
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            '\r' => f.write_str("\\r")?,
+            c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+fn write_let(f: &mut std::fmt::Formatter<'_>, stmt: &LetStatement) -> std::fmt::Result {
+    if let Some(doc) = &stmt.doc {
+        for line in doc.split('\n') {
+            writeln!(f, "/// {}", line)?;
+        }
+    }
+    match (&stmt.pattern, &stmt.ty) {
+        (Some(pattern), _) => write!(f, "let {} = {};", pattern, stmt.value),
+        (None, Some(ty)) => write!(f, "let {}: {} = {};", stmt.name.value, ty, stmt.value),
+        (None, None) => write!(f, "let {} = {};", stmt.name.value, stmt.value),
+    }
+}
+
+fn write_assign(f: &mut std::fmt::Formatter<'_>, assign: &AssignExpression) -> std::fmt::Result {
+    use token::TokenType::*;
+    match (assign.token.ty, assign.value.as_ref()) {
+        (PlusASSIGN, ASTNode::InfixExpression(infix))
+        | (MinusASSIGN, ASTNode::InfixExpression(infix))
+        | (AsteriskASSIGN, ASTNode::InfixExpression(infix))
+        | (SlashASSIGN, ASTNode::InfixExpression(infix)) => {
+            write!(f, "{} {} {}", assign.target, assign.token.ty, infix.right)
+        }
+        (_, value) => write!(f, "{} = {}", assign.target, value),
+    }
+}
+
+/// Prints the node as source code that parses back to an equal node.
+impl std::fmt::Display for ASTNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use self::ASTNode::*;
+        match self {
+            Program(p) => {
+                for (i, stmt) in p.statements.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", stmt)?;
+                }
+                Ok(())
+            }
+            LetStatement(l) => write_let(f, l),
+            ClassStatement(c) => {
+                write!(f, "class {}", c.name.value)?;
+                if let Some(superclass) = &c.superclass {
+                    write!(f, " < {}", superclass.value)?;
+                }
+                if c.members.is_empty() {
+                    return f.write_str(" {}");
+                }
+                f.write_str(" { ")?;
+                for member in &c.members {
+                    write!(f, "{} ", member)?;
+                }
+                f.write_str("}")
+            }
+            ImportStatement(i) => {
+                f.write_str("import ")?;
+                write_string(f, &i.path)?;
+                write!(f, " as {};", i.alias.value)
+            }
+            AssignExpression(a) => write_assign(f, a),
+            ReturnStatement(r) => write!(f, "return {};", r.return_value),
+            ExpressionStatement(e) => write!(f, "{};", e.expression),
+            BlockStatement(b) => {
+                if b.statements.is_empty() {
+                    return f.write_str("{}");
+                }
+                f.write_str("{ ")?;
+                for stmt in &b.statements {
+                    write!(f, "{} ", stmt)?;
+                }
+                f.write_str("}")
+            }
+            Identifier(i) => write!(f, "{}", i.value),
+            IntegerLiteral(i) if i.token.literal.is_empty() => write!(f, "{}", i.value),
+            IntegerLiteral(i) => write!(f, "{}", i.token.literal),
+            FloatLiteral(fl) if fl.token.literal.is_empty() => write!(f, "{:?}", fl.value),
+            FloatLiteral(fl) => write!(f, "{}", fl.token.literal),
+            Boolean(b) => write!(f, "{}", b.value),
+            StringLiteral(_, s) => write_string(f, s),
+            InterpolatedString(parts) => {
+                f.write_str("\"")?;
+                for part in parts {
+                    match part.as_ref() {
+                        StringLiteral(_, s) => write_string_body(f, s)?,
+                        expr => write!(f, "${{{}}}", expr)?,
+                    }
+                }
+                f.write_str("\"")
+            }
+            PrefixExpression(p) => {
+                f.write_str(&p.operator)?;
+                write_operand(f, &p.right, Pri::PREFIX as u8, false)
+            }
+            InfixExpression(i) => {
+                let precedence = get_precedence(i.token.ty);
+                write_operand(f, &i.left, precedence, false)?;
+                write!(f, " {} ", i.operator)?;
+                write_operand(f, &i.right, precedence, true)
+            }
+            IfExpression(i) => {
+                write!(f, "if ({}) {}", i.condition, i.consequence)?;
+                if i.alternative.is_some() {
+                    write!(f, " else {}", i.alternative)?;
+                }
+                Ok(())
+            }
+            MatchExpression(m) => {
+                write!(f, "match {} {{", m.value)?;
+                for (i, arm) in m.arms.iter().enumerate() {
+                    f.write_str(if i > 0 { ", " } else { " " })?;
+                    write!(f, "{}", arm.pattern)?;
+                    if arm.guard.is_some() {
+                        write!(f, " if {}", arm.guard)?;
+                    }
+                    // a body starting with `{` is read as a block
+                    match arm.body.as_ref() {
+                        HashLiteral(..) => write!(f, " => ({})", arm.body)?,
+                        body => write!(f, " => {}", body)?,
+                    }
+                }
+                f.write_str(if m.arms.is_empty() { "}" } else { " }" })
+            }
+            WhileExpression(w) => write!(f, "while ({}) {}", w.condition, w.body),
+            ForExpression(fe) => write!(
+                f,
+                "for ({} in {}) {}",
+                fe.variable.value, fe.iterable, fe.body
+            ),
+            BreakStatement(_) => f.write_str("break;"),
+            ContinueStatement(_) => f.write_str("continue;"),
+            FuncLiteral(func) => {
+                let params: Vec<String> = func.params.iter().map(|p| p.to_string()).collect();
+                f.write_str("fn")?;
+                if let Some(name) = &func.name {
+                    write!(f, " {}", name.value)?;
+                }
+                write!(f, "({}) ", params.join(", "))?;
+                if let Some(ret) = &func.ret {
+                    write!(f, "-> {} ", ret)?;
+                }
+                write!(f, "{}", func.body)
+            }
+            MacroLiteral(m) => {
+                let params: Vec<String> = m.params.iter().map(|p| p.to_string()).collect();
+                write!(f, "macro({}) {}", params.join(", "), m.body)
+            }
+            CallExpression(c) => {
+                write_operand(f, &c.func, Pri::CALL as u8, false)?;
+                f.write_str("(")?;
+                write_list(f, &c.args)?;
+                for (i, (name, value)) in c.named.iter().enumerate() {
+                    if i > 0 || !c.args.is_empty() {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", name.value, value)?;
+                }
+                f.write_str(")")
+            }
+            ArrayLiteral(_, elements, _) => {
+                f.write_str("[")?;
+                write_list(f, elements)?;
+                f.write_str("]")
+            }
+            MemberExpression(m) => {
+                write_operand(f, &m.object, Pri::INDEX as u8, false)?;
+                write!(f, ".{}", m.name.value)
+            }
+            Super(_) => f.write_str("super"),
+            IndexLiteral(_, left, index, _) => {
+                write_operand(f, left, Pri::INDEX as u8, false)?;
+                write!(f, "[{}]", index)
+            }
+            SliceExpression(s) => {
+                write_operand(f, &s.left, Pri::INDEX as u8, false)?;
+                write!(f, "[{}:{}]", s.low, s.high)
+            }
+            HashLiteral(_, pairs, _) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                f.write_str("}")
+            }
+            None => Ok(()),
         }
     }
 }
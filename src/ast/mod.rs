@@ -1,2 +1,3 @@
 pub mod ast;
-pub mod test;
+#[cfg(test)]
+mod test;
@@ -1,32 +1,113 @@
-//TODO
+use super::ast;
+use crate::mylexer::lexer::Lexer;
+use crate::parser::parser::Parser;
+use crate::token::token::{Token, TokenType};
 
-// use crate::token::token;
-// use super::ast;
-// use super::ast::Node;
-// #[test]
-// fn test_string(){
-//     // let mut program = ast::Program::new();
-//     // let mut letstmt = ast::LetStatement::new();
-//     // letstmt.token = token::Token::new_with_string(token::LET, "let");
-//     // letstmt.name = ast::Identifier::default();
-//     // letstmt
-//     let  program =ast::Program{
-//         statements: vec![
-//             Some(Box::new(
-//                 ast::LetStatement{
-//                     token: token::Token::new_with_string(token::LET, "let"),
-//                     name: ast::Identifier{
-//                         token: token::Token::new_with_string(token::IDENT, "myVar"),
-//                         value: "myVar".to_string(),
-//                     },
-//                     value: Some(Box::new(ast::Identifier{
-//                         token: token::Token::new_with_string(token::IDENT, "anotherVar"),
-//                         value: "anotherVar".to_string(),
-//                     })),
-//                 } )
-//             )
-//         ],
-//     };
+#[test]
+fn test_string() {
+    let mut letstmt = ast::LetStatement::new();
+    letstmt.token = Some(Token::new(TokenType::Let, "let"));
+    letstmt.name = ast::Identifier::new(Token::new(TokenType::IDENT, "myVar"), "myVar".into());
+    letstmt.value = Box::new(ast::ASTNode::Identifier(ast::Identifier::new(
+        Token::new(TokenType::IDENT, "anotherVar"),
+        "anotherVar".into(),
+    )));
+    let mut program = ast::Program::new();
+    program
+        .statements
+        .push(Box::new(ast::ASTNode::LetStatement(letstmt)));
 
-//     assert_eq!(program.node_to_string(), "let myVar = anotherVar;");
-// }
+    assert_eq!(
+        ast::ASTNode::Program(program).to_string(),
+        "let myVar = anotherVar;"
+    );
+}
+
+fn parse(input: &str) -> ast::ASTNode {
+    Parser::new(Lexer::new(input).with_doc_comments())
+        .parse_program()
+        .unwrap()
+}
+
+#[test]
+fn test_minimal_parentheses() {
+    let tests = [
+        ("(a + b) * c", "(a + b) * c;"),
+        ("a + (b * c)", "a + b * c;"),
+        ("(a * b) + c", "a * b + c;"),
+        ("a * (b / c)", "a * (b / c);"),
+        ("(a * b) / c", "a * b / c;"),
+        ("-(a + b)", "-(a + b);"),
+        ("!(-a)", "!-a;"),
+        ("-a * b", "-a * b;"),
+        ("a && (b || c)", "a && (b || c);"),
+        ("(a && b) || c", "a && b || c;"),
+        ("a < b == (c >= d)", "a < b == c >= d;"),
+        ("add(a + b, -c) * 2", "add(a + b, -c) * 2;"),
+        ("x = y + 1;", "x = y + 1;"),
+        ("x += (1 + 2);", "x += 1 + 2;"),
+        (
+            "let  s =\"a\\\"b\\n\\u{7f}\" ;",
+            "let s = \"a\\\"b\\n\\u{7f}\";",
+        ),
+        (
+            "let n = 0x_ff + 1_000 * 2.5e3;",
+            "let n = 0x_ff + 1_000 * 2.5e3;",
+        ),
+        (
+            "if (a>b) { return a; } else { b }",
+            "if (a > b) { return a; } else { b; };",
+        ),
+        ("fn(x,y){x+y;}(1, 2)", "fn(x, y) { x + y; }(1, 2);"),
+        ("{\"k\": 1}", "{\"k\": 1};"),
+        ("(a.b).c((x).y)", "a.b.c(x.y);"),
+        ("fn(a,b=1,...c){}(x:1,)", "fn(a, b = 1, ...c) {}(x: 1);"),
+        ("map(xs, |x| x * 2)", "map(xs, fn(x) { x * 2; });"),
+        ("x => y => x + y", "fn(x) { fn(y) { x + y; }; };"),
+        ("|| 1", "fn() { 1; };"),
+        (
+            "match x{[a,...]if a>0=>{a}_=>({}),}",
+            "match x { [a, ...] if a > 0 => { a; }, _ => ({}) };",
+        ),
+        (
+            "class P<Q{let x=1;fn f(self){super.f()}};",
+            "class P < Q { let x = 1; fn f(self) { super.f(); } }",
+        ),
+    ];
+    for (input, expected) in tests.iter() {
+        assert_eq!(parse(input).to_string(), *expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let tests = [
+        "let a = 10; let b = a * (a - 1) / 2 % 3;",
+        "/// doc\n/// more\nlet f = fn(a, b) { let c = a <= b; return !c; };",
+        "f(g(1), h(2, 3))(4);",
+        "if (true) { 1 } else { if (false) { 2 } }",
+        "let x = y = z;",
+        "(a) + b; (a + b); -(-(1)); !(!true);",
+        "x -= 1; x *= 2; x /= -3;",
+        "let s = \"tab\\there\\\\ \\u{1F600}\";",
+        "a - b - c; a - (b - c); (a + b) * (c + d);",
+        "class A {} class B < A { let x = [1]; fn get(self, i) { self.x[i] + super.get(i) } }",
+        "p.x.y = q.f(1)[0]; (-p).x; (a + b).c;",
+        "match f(x) { -1 => 0, [a, [b, _], ...rest] if a < b => a, {\"k\": [], 2: v} => { v }, s => s }",
+        "match m {}",
+        "let f = fn(a, b = a + 1, ...rest) { a }; f(1, 2, c: 3, d: fn(x = {}) { x }());",
+        "let [a, [b, _], ...rest] = xs; let {\"k\": v, 1: [...]} = m;",
+        "a[1:n - 1][:2][i:][:]; (0..n)[-1]; for (i in 1..=n * 2) { s[i..i + 1] }",
+        "let n: int = 5; let f = fn(a: [int], ...r: [any]) -> {str: fn(int, float) -> bool} { a };",
+        "class C { let x: str = \"\"; fn m(self, y: range = 1..2) -> fn() -> any { |z: int| z } }",
+        "\"a ${x + 1} \\${b} ${\"c${d[\"e\"]}\"}${{}}\\\\\";",
+        "import \"lib/math.mk\" as math; import \"a \\\"b\\\".mk\" as b; math.pi;",
+        "let m = macro(a, b = 1, ...c) { quote(unquote(a) + b) }; m(x, c: macro() {});",
+    ];
+    for input in tests.iter() {
+        let ast = parse(input);
+        let printed = ast.to_string();
+        assert_eq!(parse(&printed), ast, "{} printed as {}", input, printed);
+        assert_eq!(parse(&printed).to_string(), printed);
+    }
+}
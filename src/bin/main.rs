@@ -1,8 +1,22 @@
 use monkey::repl::repl;
 use std::io;
 use std::io::prelude::*;
+use std::path::Path;
 fn main() {
-    io::stdout().write("Hello Monkey\n".as_bytes()).unwrap();
+    // monkey [--types] [file]
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let types = args.first().map(String::as_str) == Some("--types");
+    if types {
+        args.remove(0);
+    }
+    if let Some(path) = args.first() {
+        if types {
+            repl::print_types(Path::new(path));
+        }
+        repl::run_file(Path::new(path));
+        return;
+    }
+    io::stdout().write_all("Hello Monkey\n".as_bytes()).unwrap();
     io::stdout().flush().unwrap();
 
     repl::start();
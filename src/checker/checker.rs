@@ -0,0 +1,503 @@
+use crate::ast::ast::{self, ASTNode, Type};
+use crate::token::token::{Span, Symbol};
+
+use std::collections::HashMap;
+
+/// One type error: what does not fit and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.span.start, self.message)
+    }
+}
+
+/// Every type error in a program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeErrors {
+    pub errors: Vec<TypeError>,
+}
+
+impl std::fmt::Display for TypeErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TypeErrors {}
+
+/// Checks a program against its type annotations.
+pub fn check(program: &ASTNode) -> Result<(), TypeErrors> {
+    Checker::new().check(program)
+}
+
+#[derive(Clone)]
+struct Binding {
+    ty: Type,
+    declared: bool, // from an annotation rather than inferred from a value
+}
+
+#[derive(Clone, Default)]
+struct Scope {
+    vars: HashMap<Symbol, Binding>,
+    function: bool, // a function body, which may run after outer names change
+}
+
+/// Works out what it can of each expression's type without running it and
+/// reports values that do not fit their annotation, operators the
+/// evaluator would reject and calls of things that are not functions.
+///
+/// Checking is gradual: whatever cannot be known, such as an unannotated
+/// parameter, is `any`, which fits everywhere. A name bound without an
+/// annotation takes its value's type until it is assigned something else.
+pub struct Checker {
+    scopes: Vec<Scope>,
+    returns: Vec<Type>, // the declared return type of each enclosing function
+    errors: Vec<TypeError>,
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Checker {
+            scopes: vec![Scope::default()],
+            returns: vec![],
+            errors: vec![],
+        }
+    }
+
+    /// Checks `program`. Its top-level names stay known to later calls,
+    /// unless it has errors.
+    pub fn check(&mut self, program: &ASTNode) -> Result<(), TypeErrors> {
+        let globals = self.scopes[0].clone();
+        self.type_of(program);
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        self.scopes = vec![globals];
+        self.returns.clear();
+        Err(TypeErrors {
+            errors: std::mem::take(&mut self.errors),
+        })
+    }
+
+    fn error<S: Into<String>>(&mut self, message: S, span: Span) {
+        self.errors.push(TypeError {
+            message: message.into(),
+            span,
+        });
+    }
+
+    // `actual`, the type of `node`, must fit `expected`
+    fn expect(&mut self, expected: &Type, actual: &Type, node: &ASTNode) {
+        if !fits(expected, actual) {
+            self.error(
+                format!("expected {}, found {}", expected, actual),
+                node.span(),
+            );
+        }
+    }
+
+    fn bind(&mut self, name: Symbol, ty: Type, declared: bool) {
+        let scope = self.scopes.last_mut().unwrap();
+        scope.vars.insert(name, Binding { ty, declared });
+    }
+
+    fn lookup(&self, name: Symbol) -> Option<&Binding> {
+        self.scopes.iter().rev().find_map(|s| s.vars.get(&name))
+    }
+
+    // an inferred type is only trusted inside the function that bound it,
+    // since a closure may run after the name is assigned something else
+    fn type_of_name(&self, name: Symbol) -> Type {
+        let mut crossed = false;
+        for scope in self.scopes.iter().rev() {
+            if let Some(binding) = scope.vars.get(&name) {
+                if crossed && !binding.declared {
+                    return Type::Any;
+                }
+                return binding.ty.clone();
+            }
+            crossed |= scope.function;
+        }
+        Type::Any
+    }
+
+    fn widen(&mut self, name: Symbol) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(binding) = scope.vars.get_mut(&name) {
+                binding.ty = Type::Any;
+                return;
+            }
+        }
+    }
+
+    fn enter(&mut self, function: bool) {
+        self.scopes.push(Scope {
+            vars: HashMap::new(),
+            function,
+        });
+    }
+
+    fn leave(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn type_of(&mut self, node: &ASTNode) -> Type {
+        use ASTNode::*;
+        match node {
+            Program(p) => self.type_of_statements(&p.statements),
+            BlockStatement(b) => self.type_of_statements(&b.statements),
+            ExpressionStatement(e) => self.type_of(&e.expression),
+            LetStatement(stmt) => self.check_let(stmt),
+            ClassStatement(class) => {
+                self.bind(class.name.value, Type::Any, false);
+                self.enter(true);
+                for member in &class.members {
+                    self.type_of(member);
+                }
+                self.leave();
+                Type::Any
+            }
+            // exports are not checked across files
+            ImportStatement(import) => {
+                self.bind(import.alias.value, Type::Any, false);
+                Type::Any
+            }
+            AssignExpression(assign) => self.check_assign(assign),
+            Identifier(id) => self.type_of_name(id.value),
+            IntegerLiteral(_) => Type::Int,
+            FloatLiteral(_) => Type::Float,
+            Boolean(_) => Type::Bool,
+            StringLiteral(_, _) => Type::Str,
+            InterpolatedString(parts) => {
+                for part in parts {
+                    self.type_of(part);
+                }
+                Type::Str
+            }
+            PrefixExpression(p) => {
+                let right = self.type_of(&p.right);
+                match (p.operator.as_str(), right) {
+                    ("!", _) => Type::Bool,
+                    ("-", Type::Int) => Type::Int,
+                    ("-", Type::Float) => Type::Float,
+                    (_, Type::Any) => Type::Any,
+                    (op, right) => {
+                        self.error(format!("cannot apply `{}` to {}", op, right), node.span());
+                        Type::Any
+                    }
+                }
+            }
+            InfixExpression(i) => {
+                let left = self.type_of(&i.left);
+                let right = self.type_of(&i.right);
+                match i.operator.as_str() {
+                    "&&" | "||" => Type::Bool,
+                    op => {
+                        if let Some(ty) = infix_type(op, &left, &right) {
+                            return ty;
+                        }
+                        self.error(
+                            format!("cannot apply `{}` to {} and {}", op, left, right),
+                            node.span(),
+                        );
+                        Type::Any
+                    }
+                }
+            }
+            IfExpression(i) => {
+                self.type_of(&i.condition);
+                let consequence = self.type_of(&i.consequence);
+                if i.alternative.is_none() {
+                    return Type::Any;
+                }
+                let alternative = self.type_of(&i.alternative);
+                join(&consequence, &alternative)
+            }
+            MatchExpression(m) => self.check_match(m),
+            WhileExpression(w) => {
+                self.type_of(&w.condition);
+                self.type_of(&w.body);
+                Type::Any
+            }
+            ForExpression(f) => {
+                let iterable = self.type_of(&f.iterable);
+                let item = match iterable {
+                    Type::Array(element) => *element,
+                    Type::Str => Type::Str,
+                    Type::Range => Type::Int,
+                    Type::Any => Type::Any,
+                    other => {
+                        self.error(format!("cannot iterate over {}", other), f.iterable.span());
+                        Type::Any
+                    }
+                };
+                self.bind(f.variable.value, item, false);
+                self.type_of(&f.body);
+                Type::Any
+            }
+            ReturnStatement(r) => {
+                let value = self.type_of(&r.return_value);
+                if let Some(ret) = self.returns.last().cloned() {
+                    self.expect(&ret, &value, &r.return_value);
+                }
+                Type::Any
+            }
+            FuncLiteral(func) => self.check_function(func),
+            // macros are gone once expanded; one left over is an error at runtime
+            MacroLiteral(_) => Type::Any,
+            CallExpression(c) => {
+                let callee = self.type_of(&c.func);
+                let args: Vec<Type> = c.args.iter().map(|a| self.type_of(a)).collect();
+                for (_, value) in &c.named {
+                    self.type_of(value);
+                }
+                match callee {
+                    Type::Func(params, ret) => {
+                        for ((param, arg), node) in params.iter().zip(&args).zip(&c.args) {
+                            self.expect(param, arg, node);
+                        }
+                        *ret
+                    }
+                    Type::Any => Type::Any,
+                    other => {
+                        self.error(format!("calling {} as a function", other), c.func.span());
+                        Type::Any
+                    }
+                }
+            }
+            ArrayLiteral(_, elements, _) => {
+                let elements: Vec<Type> = elements.iter().map(|e| self.type_of(e)).collect();
+                Type::Array(Box::new(join_all(&elements)))
+            }
+            HashLiteral(_, pairs, _) => {
+                let mut keys = vec![];
+                let mut values = vec![];
+                for (key, value) in pairs {
+                    keys.push(self.type_of(key));
+                    values.push(self.type_of(value));
+                }
+                Type::Map(Box::new(join_all(&keys)), Box::new(join_all(&values)))
+            }
+            IndexLiteral(_, left, index, _) => {
+                let container = self.type_of(left);
+                let index = self.type_of(index);
+                match (container, index) {
+                    (Type::Map(_, value), _) => *value,
+                    (Type::Array(element), Type::Int) => *element,
+                    (Type::Str, Type::Int) | (Type::Str, Type::Range) => Type::Str,
+                    (Type::Array(element), Type::Range) => Type::Array(element),
+                    (Type::Any, _) | (_, Type::Any) => Type::Any,
+                    (container, index) => {
+                        self.error(
+                            format!("cannot index {} with {}", container, index),
+                            node.span(),
+                        );
+                        Type::Any
+                    }
+                }
+            }
+            SliceExpression(s) => {
+                let container = self.type_of(&s.left);
+                for bound in [&s.low, &s.high] {
+                    if bound.is_some() {
+                        let ty = self.type_of(bound);
+                        self.expect(&Type::Int, &ty, bound);
+                    }
+                }
+                match container {
+                    Type::Array(_) | Type::Str | Type::Any => container,
+                    other => {
+                        self.error(format!("cannot slice {}", other), s.left.span());
+                        Type::Any
+                    }
+                }
+            }
+            MemberExpression(m) => {
+                self.type_of(&m.object);
+                Type::Any
+            }
+            BreakStatement(_) | ContinueStatement(_) | Super(_) | None => Type::Any,
+        }
+    }
+
+    // a block's value is its last statement's
+    fn type_of_statements(&mut self, statements: &[Box<ASTNode>]) -> Type {
+        let mut ty = Type::Any;
+        for stmt in statements {
+            ty = self.type_of(stmt);
+        }
+        ty
+    }
+
+    fn check_let(&mut self, stmt: &ast::LetStatement) -> Type {
+        if let Some(pattern) = &stmt.pattern {
+            let value = self.type_of(&stmt.value);
+            for id in pattern.bindings() {
+                self.bind(id.value, Type::Any, false);
+            }
+            return value;
+        }
+        let name = stmt.name.value;
+        // bound before the value so that a function can call itself
+        match (&stmt.ty, stmt.value.as_ref()) {
+            (Some(ty), _) => self.bind(name, ty.clone(), true),
+            (None, ASTNode::FuncLiteral(func)) => self.bind(name, signature(func), false),
+            (None, _) => {}
+        }
+        let value = self.type_of(&stmt.value);
+        match &stmt.ty {
+            Some(ty) => {
+                self.expect(ty, &value, &stmt.value);
+                ty.clone()
+            }
+            None => {
+                self.bind(name, value.clone(), false);
+                value
+            }
+        }
+    }
+
+    fn check_match(&mut self, m: &ast::MatchExpression) -> Type {
+        self.type_of(&m.value);
+        let mut arms = vec![];
+        for arm in &m.arms {
+            self.enter(false);
+            for node in arm.pattern.nodes() {
+                self.type_of(node);
+            }
+            for id in arm.pattern.bindings() {
+                self.bind(id.value, Type::Any, false);
+            }
+            self.type_of(&arm.guard);
+            arms.push(self.type_of(&arm.body));
+            self.leave();
+        }
+        join_all(&arms)
+    }
+
+    fn check_assign(&mut self, assign: &ast::AssignExpression) -> Type {
+        let value = self.type_of(&assign.value);
+        // a[i][j] = v checks v against the element type of a declared a
+        let mut root = assign.target.as_ref();
+        while let ASTNode::IndexLiteral(_, left, _, _) = root {
+            root = left;
+        }
+        let target = self.type_of(&assign.target);
+        if let ASTNode::Identifier(id) = root {
+            match self.lookup(id.value) {
+                Some(binding) if binding.declared => {
+                    self.expect(&target, &value, &assign.value);
+                }
+                Some(_) if !fits(&target, &value) => self.widen(id.value),
+                _ => {}
+            }
+        }
+        value
+    }
+
+    fn check_function(&mut self, func: &ast::FuncLiteral) -> Type {
+        let ret = func.ret.clone().unwrap_or(Type::Any);
+        self.enter(true);
+        for param in &func.params {
+            let ty = match (&param.ty, param.rest) {
+                (Some(ty), _) => ty.clone(),
+                (None, true) => Type::Array(Box::new(Type::Any)),
+                (None, false) => Type::Any,
+            };
+            if param.default.is_some() {
+                let default = self.type_of(&param.default);
+                self.expect(&ty, &default, &param.default);
+            }
+            self.bind(param.name.value, ty, param.ty.is_some());
+        }
+        self.returns.push(ret.clone());
+        let value = self.type_of(&func.body);
+        if let Some(last) = func.body.children().last() {
+            self.expect(&ret, &value, last);
+        }
+        self.returns.pop();
+        self.leave();
+        signature(func)
+    }
+}
+
+// what calling `func` takes and gives back, as far as its annotations say
+fn signature(func: &ast::FuncLiteral) -> Type {
+    let params = func
+        .params
+        .iter()
+        .filter(|p| !p.rest)
+        .map(|p| p.ty.clone().unwrap_or(Type::Any))
+        .collect();
+    let ret = func.ret.clone().unwrap_or(Type::Any);
+    Type::Func(params, Box::new(ret))
+}
+
+// whether a value of type `actual` can be used where `expected` is wanted
+fn fits(expected: &Type, actual: &Type) -> bool {
+    match (expected, actual) {
+        (Type::Any, _) | (_, Type::Any) => true,
+        (Type::Float, Type::Int) => true,
+        (Type::Array(a), Type::Array(b)) => fits(a, b),
+        (Type::Map(k1, v1), Type::Map(k2, v2)) => fits(k1, k2) && fits(v1, v2),
+        (Type::Func(p1, r1), Type::Func(p2, r2)) => {
+            p1.iter().zip(p2).all(|(a, b)| fits(b, a)) && fits(r1, r2)
+        }
+        (a, b) => a == b,
+    }
+}
+
+// the type of a value that is one of `a` or `b`
+fn join(a: &Type, b: &Type) -> Type {
+    match (a, b) {
+        (a, b) if a == b => a.clone(),
+        (Type::Int, Type::Float) | (Type::Float, Type::Int) => Type::Float,
+        _ => Type::Any,
+    }
+}
+
+fn join_all(types: &[Type]) -> Type {
+    match types.split_first() {
+        Some((first, rest)) => rest.iter().fold(first.clone(), |a, b| join(&a, b)),
+        None => Type::Any,
+    }
+}
+
+// the result of `left op right`, or None where the evaluator would fail
+fn infix_type(op: &str, left: &Type, right: &Type) -> Option<Type> {
+    let compare = matches!(op, "<" | ">" | "<=" | ">=" | "==" | "!=");
+    let numeric = |t: &Type| matches!(t, Type::Int | Type::Float);
+    if op == ".." || op == "..=" {
+        let int = |t: &Type| matches!(t, Type::Int | Type::Any);
+        return Some(Type::Range).filter(|_| int(left) && int(right));
+    }
+    match (left, right) {
+        (Type::Any, _) | (_, Type::Any) if compare => Some(Type::Bool),
+        (Type::Any, _) | (_, Type::Any) => Some(Type::Any),
+        (Type::Int, Type::Int) if compare => Some(Type::Bool),
+        (Type::Int, Type::Int) => Some(Type::Int),
+        (l, r) if numeric(l) && numeric(r) && compare => Some(Type::Bool),
+        (l, r) if numeric(l) && numeric(r) => Some(Type::Float),
+        (Type::Str, Type::Str) if op == "+" => Some(Type::Str),
+        (Type::Str, Type::Str) => None,
+        _ if op != "==" && op != "!=" => None,
+        (l, r) if same_kind(l, r) => Some(Type::Bool),
+        _ => None,
+    }
+}
+
+// what the evaluator compares when it checks two values have the same type
+fn same_kind(a: &Type, b: &Type) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
@@ -0,0 +1,3 @@
+pub mod checker;
+#[cfg(test)]
+mod test;
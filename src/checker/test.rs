@@ -0,0 +1,96 @@
+use super::checker::{self, Checker};
+use crate::ast::ast;
+use crate::mylexer::lexer::Lexer;
+use crate::parser::parser::Parser;
+
+fn parse(input: &str) -> ast::ASTNode {
+    Parser::new(Lexer::new(input)).parse_program().unwrap()
+}
+
+#[test]
+fn test_well_typed() {
+    let tests = [
+        "let n: int = 5; n + 1;",
+        "let x: float = 1; x * 2.5;",
+        "let f = fn(a: int, b: [str]) -> bool { a > 0 }; f(1, [\"x\"]);",
+        "let g = fn(x) { x + 1 }; g(\"s\");",
+        "let x = 1; x = \"s\"; x + \"t\";",
+        "let m: {str: int} = {\"a\": 1}; m[\"a\"] + 1;",
+        "let fact = fn(n: int) -> int { if (n < 2) { return 1; } n * fact(n - 1) }; fact(5);",
+        "let x = 1; let f = fn() { x + \"!\" }; x = \"a\"; f();",
+        "let xs: [any] = [1, \"a\"]; for (i in 1..3) { xs[i] + 1; }",
+        "let s: str = \"abc\"[1:] + \"${1}\"[0];",
+        "let double: fn(int) -> int = |x: int| x * 2;",
+        "class P { let x: int = 1; fn get(self) -> int { self.x } } P().get() + 1;",
+        "let a: [int] = []; a[0] = 1; a[1:][0] - 1;",
+        "match 1 { 0 => \"zero\", n if n > 0 => \"positive\", _ => \"negative\" } + \"!\";",
+    ];
+    for input in tests.iter() {
+        if let Err(errors) = checker::check(&parse(input)) {
+            panic!("{}: {}", input, errors);
+        }
+    }
+}
+
+#[test]
+fn test_type_errors() {
+    let tests = [
+        ("let n: int = \"five\";", "1:14: expected int, found str"),
+        (
+            "let f = fn(a: int, b: [str]) -> bool { a };",
+            "1:40: expected bool, found int",
+        ),
+        (
+            "let f = fn(a: int) { a }; f(\"x\");",
+            "1:29: expected int, found str",
+        ),
+        (
+            "fn(a: int) -> str { return a; }",
+            "1:28: expected str, found int",
+        ),
+        (
+            "let n: int = 1; n = 2.5;",
+            "1:21: expected int, found float",
+        ),
+        (
+            "let xs: [int] = [1]; xs[0] = \"a\";",
+            "1:30: expected int, found str",
+        ),
+        (
+            "let n: int = 1; fn() { n = true }",
+            "1:28: expected int, found bool",
+        ),
+        ("1 + \"a\"", "1:1: cannot apply `+` to int and str"),
+        ("\"a\" == \"b\"", "1:1: cannot apply `==` to str and str"),
+        ("-\"a\"", "1:1: cannot apply `-` to str"),
+        ("5(1)", "1:1: calling int as a function"),
+        ("for (x in 5) {}", "1:11: cannot iterate over int"),
+        ("[1, 2][\"a\"]", "1:1: cannot index [int] with str"),
+        ("true[1:]", "1:1: cannot slice bool"),
+        (
+            "let f: fn(int) -> int = fn(s: str) -> int { 1 };",
+            "1:25: expected fn(int) -> int, found fn(str) -> int",
+        ),
+        (
+            "let a: int = true; let b: str = 1 + 2;",
+            "1:14: expected int, found bool\n1:33: expected str, found int",
+        ),
+    ];
+    for (input, expected) in tests.iter() {
+        match checker::check(&parse(input)) {
+            Ok(()) => panic!("{} type checked", input),
+            Err(errors) => assert_eq!(errors.to_string(), *expected, "{}", input),
+        }
+    }
+}
+
+#[test]
+fn test_checker_keeps_globals() {
+    let mut checker = Checker::new();
+    checker.check(&parse("let n: int = 1;")).unwrap();
+    assert!(checker.check(&parse("n = \"s\";")).is_err());
+
+    // nothing from a program with errors is kept
+    assert!(checker.check(&parse("let m: int = 1; m = true;")).is_err());
+    checker.check(&parse("m = true;")).unwrap();
+}
@@ -0,0 +1,195 @@
+//! Lossless concrete syntax tree.
+//!
+//! The CST keeps every token of the input, whitespace and comments included,
+//! grouped into nodes that mirror the `ast::ASTNode`s the parser produced.
+//! Printing a CST gives back the original source byte for byte, which is what
+//! formatting and refactoring tools need.
+
+use crate::ast::ast::ASTNode;
+use crate::mylexer::Lexer;
+use crate::parser::parser::Parser;
+use crate::token::token::{Span, Token};
+use crate::Result;
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstToken {
+    pub token: Token,
+    pub text: String, // exact source text, unlike token.literal for strings
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CstElement {
+    Node(CstNode),
+    Token(CstToken),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstNode {
+    pub kind: &'static str, // the ASTNode variant, see ASTNode::kind
+    pub children: Vec<CstElement>,
+}
+
+/// Parses `input` into a CST rooted at a `Program` node.
+pub fn parse(input: &str) -> Result<CstNode> {
+    let tokens: Vec<CstToken> = Lexer::new(input)
+        .with_trivia()
+        .with_doc_comments()
+        .map(|token| CstToken {
+            text: token.span.slice(input).to_string(),
+            token,
+        })
+        .collect();
+    let ast = Parser::from_tokens(tokens.iter().map(|t| t.token.clone()).collect::<Vec<_>>())
+        .parse_program()?;
+
+    let mut tokens = tokens.into_iter().peekable();
+    let mut root = build(&ast, &mut tokens);
+    // trivia after the last statement
+    root.children.extend(tokens.map(CstElement::Token));
+    Ok(root)
+}
+
+// Takes the tokens inside `node`'s span, wrapping those inside a child's span
+// in a child node. Tokens between children, trivia included, stay with the
+// parent, so every token ends up in exactly one place.
+fn build(node: &ASTNode, tokens: &mut Peekable<IntoIter<CstToken>>) -> CstNode {
+    let mut children = vec![];
+    let mut nodes = node.children();
+    nodes.sort_by_key(|child| child.span().start.offset);
+
+    for child in nodes {
+        let span = child.span();
+        if span.is_dummy() {
+            continue;
+        }
+        while let Some(token) = tokens.next_if(|t| t.token.span.start.offset < span.start.offset) {
+            children.push(CstElement::Token(token));
+        }
+        children.push(CstElement::Node(build(child, tokens)));
+    }
+
+    let span = node.span();
+    if !span.is_dummy() {
+        while let Some(token) = tokens.next_if(|t| t.token.span.end.offset <= span.end.offset) {
+            children.push(CstElement::Token(token));
+        }
+    }
+    CstNode {
+        kind: node.kind(),
+        children,
+    }
+}
+
+impl CstNode {
+    /// Every token under this node, in source order.
+    pub fn tokens(&self) -> Vec<&CstToken> {
+        let mut tokens = vec![];
+        for child in &self.children {
+            match child {
+                CstElement::Node(node) => tokens.extend(node.tokens()),
+                CstElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    pub fn span(&self) -> Span {
+        self.tokens()
+            .iter()
+            .fold(Span::default(), |span, t| span.to(t.token.span))
+    }
+
+    /// Drops the trivia and parses what is left. Formatting is lost, so this
+    /// only goes one way.
+    pub fn to_ast(&self) -> Result<ASTNode> {
+        let tokens: Vec<Token> = self.tokens().iter().map(|t| t.token.clone()).collect();
+        Ok(Parser::from_tokens(tokens).parse_program()?)
+    }
+}
+
+impl std::fmt::Display for CstNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for token in self.tokens() {
+            f.write_str(&token.text)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_cst {
+    use super::{parse, CstElement};
+    use crate::mylexer::Lexer;
+    use crate::parser::parser::Parser;
+
+    const SOURCES: [&str; 13] = [
+        "let a = 10;",
+        "  let   add = fn(x,y){ x + y; } ;\n\n add(1 ,\t2)  ",
+        "/// doc\nlet s = \"a\\tb\\u{41}\"; // trailing\n/* block /* nested */ */ s\n",
+        "if (a <= 0x1F) { return 1_000; } else { -2.5e3 }",
+        "let f = |x, y = 1| x * y; // short\nxs => f(xs, ||  0)",
+        "match v {\n  {\"k\": [1, x]} if x => x,\n  _ => -1,\n}",
+        "class P < Q {\n  let x = 1; // field\n  fn f(self) { super.f(self.x) }\n}\nP().f()",
+        "\"hi ${ user[\"name\"] /* who */ }, ${ {1: 2}[1] }!\"",
+        "for (i in 0 ..= n) { s[ i : ][:-1] + xs[1..3] }",
+        "let f = fn(a : int, b: [str]=[])->{str: bool} { {} };\nlet n:float = 1;",
+        "import \"lib/math.mk\"  as math ; // math\nmath.pi",
+        "let twice = macro (e) {\n  quote(unquote(e) * 2)\n};\ntwice(1)",
+        "",
+    ];
+
+    #[test]
+    fn test_round_trip() {
+        for input in SOURCES.iter() {
+            let cst = parse(input).unwrap();
+            assert_eq!(cst.to_string(), *input);
+        }
+    }
+
+    #[test]
+    fn test_to_ast() {
+        for input in SOURCES.iter() {
+            let expected = Parser::new(Lexer::new(input).with_doc_comments())
+                .parse_program()
+                .unwrap();
+            assert_eq!(parse(input).unwrap().to_ast().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_structure() {
+        let input = "let x = 1 + 2; // sum";
+        let cst = parse(input).unwrap();
+        assert_eq!(cst.kind, "Program");
+
+        let stmt = match &cst.children[0] {
+            CstElement::Node(node) => node,
+            child => panic!("not a node: {:?}", child),
+        };
+        assert_eq!(stmt.kind, "LetStatement");
+        assert_eq!(stmt.to_string(), "let x = 1 + 2");
+        assert_eq!(stmt.span().slice(input), "let x = 1 + 2");
+
+        let kinds: Vec<_> = stmt
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                CstElement::Node(node) => Some(node.kind),
+                CstElement::Token(_) => None,
+            })
+            .collect();
+        assert_eq!(kinds, vec!["InfixExpression"]);
+
+        // the `;` and the comment belong to the program
+        let rest: Vec<_> = cst.children[1..]
+            .iter()
+            .map(|child| match child {
+                CstElement::Token(token) => token.text.as_str(),
+                CstElement::Node(node) => panic!("unexpected node {:?}", node),
+            })
+            .collect();
+        assert_eq!(rest, vec![";", " ", "// sum"]);
+    }
+}
@@ -0,0 +1,50 @@
+//! Functions every program can call without defining them. A builtin is
+//! only found when no binding has its name, so a program may shadow it.
+
+use super::evaluator::{new_error, NamedArgs};
+use crate::object::object::TheObject;
+use crate::token::token::Symbol;
+
+const NAMES: &[&str] = &["len"];
+
+/// The builtin called `name`, if there is one.
+pub fn lookup(name: Symbol) -> Option<Box<TheObject>> {
+    NAMES
+        .contains(&name.as_str())
+        .then(|| Box::new(TheObject::Builtin(name)))
+}
+
+pub(crate) fn call(name: Symbol, args: &[Box<TheObject>], named: &NamedArgs) -> Box<TheObject> {
+    if !named.is_empty() {
+        return new_error(format!("{} takes no named arguments", name));
+    }
+    match name.as_str() {
+        "len" => len(args),
+        _ => new_error(format!("unknown builtin: {}", name)),
+    }
+}
+
+// characters of a string, elements of an array, pairs of a map
+fn len(args: &[Box<TheObject>]) -> Box<TheObject> {
+    let arg = match args {
+        [arg] => arg,
+        _ => {
+            return new_error(format!(
+                "wrong number of arguments: expected 1, got {}",
+                args.len()
+            ))
+        }
+    };
+    let n = match arg.as_ref() {
+        TheObject::Stringobj(s) => s.chars().count(),
+        TheObject::Array(items) => items.len(),
+        TheObject::Map(map) => map.len(),
+        other => {
+            return new_error(format!(
+                "argument to len not supported, got {}",
+                other.type_of()
+            ))
+        }
+    };
+    Box::new(TheObject::Integer(n as i64))
+}
@@ -1,8 +1,18 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
+use super::builtins;
+use super::macro_expansion;
 use crate::ast::ast;
+use crate::checker::checker;
+use crate::mylexer::Lexer;
 use crate::object::object;
 use crate::object::object::TheObject;
+use crate::parser::parser::Parser;
+use crate::token::token::Symbol;
 
 macro_rules! new_box {
     ($b:expr) => {
@@ -25,35 +35,59 @@ macro_rules! theobject_null {
 const TRUE: object::TheObject = object::TheObject::Boolean(true);
 const FALSE: object::TheObject = object::TheObject::Boolean(false);
 
-pub fn eval(node: &ast::ASTNode, mut env: &mut Environment) -> Box<object::TheObject> {
+pub fn eval(node: &ast::ASTNode, env: &mut Environment) -> Box<object::TheObject> {
     use crate::ast::ast::ASTNode::*;
 
     match node {
         //关键的一行代码
-        Program(ref value) => return eval_program(value, &mut env),
-        ExpressionStatement(ref value) => return eval(&value.expression, &mut env),
+        Program(ref value) => return eval_program(value, env),
+        ExpressionStatement(ref value) => return eval(&value.expression, env),
         IntegerLiteral(ref value) => return new_box!(object::TheObject::Integer(value.value)),
+        FloatLiteral(ref value) => return new_box!(object::TheObject::Float(value.value)),
         Boolean(ref boolean_obj) => return bool_obj(boolean_obj.value),
         PrefixExpression(ref value) => {
-            return eval_prefix_expr(&value.operator, &eval(&value.right, &mut env))
+            return eval_prefix_expr(&value.operator, &eval(&value.right, env))
+        }
+        InfixExpression(ref value) if value.operator == "&&" || value.operator == "||" => {
+            return eval_logical_expr(value, env)
         }
         InfixExpression(ref value) => {
             return eval_infix_expr(
                 &value.operator,
-                &eval(&value.left, &mut env),
-                &eval(&value.right, &mut env),
+                &eval(&value.left, env),
+                &eval(&value.right, env),
             )
         }
-        BlockStatement(ref value) => return eval_statements(&value.statements, &mut env),
-        IfExpression(ref value) => return eval_ifexpression(value, &mut env),
-        Identifier(ref value) => return eval_identifier(value, &env),
+        BlockStatement(ref value) => return eval_statements(&value.statements, env),
+        IfExpression(ref value) => return eval_ifexpression(value, env),
+        MatchExpression(ref value) => return eval_match_expression(value, env),
+        WhileExpression(ref value) => return eval_while_expression(value, env),
+        ForExpression(ref value) => return eval_for_expression(value, env),
+        BreakStatement(_) => return new_box!(object::TheObject::Break),
+        ContinueStatement(_) => return new_box!(object::TheObject::Continue),
+        Identifier(ref value) => return eval_identifier(value, env),
         LetStatement(ref value) => {
-            let val = eval(&value.value, &mut env);
+            let val = eval(&value.value, env);
             if val.is_error() {
                 return val;
             }
-            env.store.insert(value.name.value.clone(), val.clone());
-            return eval_identifier(&value.name, &env);
+            if let Some(ref pattern) = value.pattern {
+                if let Err(reason) = match_pattern(pattern, &val, env) {
+                    return new_error(format!("cannot destructure {}: {}", pattern, reason));
+                }
+            } else {
+                env.set(value.name.value, val.clone());
+            }
+            return val;
+        }
+        AssignExpression(ref value) => return eval_assign_expression(value, env),
+        ClassStatement(ref value) => return eval_class_statement(value, env),
+        ImportStatement(ref value) => return eval_import_statement(value, env),
+        MemberExpression(ref value) => return eval_member_expression(value, env),
+        Super(_) => {
+            return env
+                .get(Symbol::from("super"))
+                .unwrap_or_else(|| new_error("super outside of a subclass method"))
         }
         ReturnStatement(ref value) => {
             let val = eval(&value.return_value, env);
@@ -62,6 +96,7 @@ pub fn eval(node: &ast::ASTNode, mut env: &mut Environment) -> Box<object::TheOb
             }
             return new_box!(object::TheObject::ReturnValue(val));
         }
+        MacroLiteral(_) => return new_error("a macro must be bound by a top-level let"),
         FuncLiteral(ref val) => {
             return new_box!(object::TheObject::Func(
                 val.params.clone(),
@@ -69,37 +104,65 @@ pub fn eval(node: &ast::ASTNode, mut env: &mut Environment) -> Box<object::TheOb
                 env.clone()
             ))
         }
+        CallExpression(ref value) if macro_expansion::is_quote(value) => {
+            return macro_expansion::quote(&value.args[0], env)
+        }
         CallExpression(ref value) => {
-            let function = eval(&value.func, &mut env); //函数对象
+            let function = eval(&value.func, env); //函数对象
             if function.as_ref().is_error() {
                 return function;
             }
 
             //函数参数列表
-            let args = eval_expressions(&value.args, &mut env);
+            let args = eval_expressions(&value.args, env);
             if args.len() == 1 && args[0].as_ref().is_error() {
                 return args[0].clone();
             }
-            return apply_function(&function, &args);
+            let mut named = vec![];
+            for (name, arg) in &value.named {
+                let arg = eval(arg, env);
+                if arg.as_ref().is_error() {
+                    return arg;
+                }
+                named.push((name.value, arg));
+            }
+            return apply_function(&function, &args, &named);
         }
         StringLiteral(_, ref value) => {
             return new_box!(object::TheObject::Stringobj(value.clone()))
         }
-        ArrayLiteral(_, ref values) => {
-            return new_box!(object::TheObject::Array(eval_expressions(
-                &values, &mut env
-            )))
+        InterpolatedString(ref parts) => {
+            let mut s = String::new();
+            for part in parts {
+                let value = eval(part, env);
+                match value.as_ref() {
+                    object::TheObject::Stringobj(text) => s.push_str(text),
+                    v if v.is_error() => return value,
+                    v => s.push_str(&v.display()),
+                }
+            }
+            return new_box!(object::TheObject::Stringobj(s));
         }
-        IndexLiteral(_, ref left, ref right) => {
-            let left = eval(&left, &mut env);
-            let index = eval(&right, &mut env);
-
+        ArrayLiteral(_, ref values, _) => {
+            return new_box!(object::TheObject::Array(eval_expressions(values, env)))
+        }
+        IndexLiteral(_, ref left, ref right, _) => {
+            let left = eval(left, env);
+            if left.is_error() {
+                return left;
+            }
+            let index = eval(right, env);
+            if index.is_error() {
+                return index;
+            }
             return eval_index_expression(&left, &index);
         }
-        HashLiteral(_, ref pairs) => {
+        SliceExpression(ref value) => return eval_slice_expression(value, env),
+        HashLiteral(_, ref pairs, _) => {
             use crate::object::object::TheObject::*;
 
-            let mut map: HashMap<String, Box<object::TheObject>> = HashMap::new();
+            let mut map: HashMap<String, (Box<object::TheObject>, Box<object::TheObject>)> =
+                HashMap::new();
             // let map : HashMap<String,Box<object::TheObject>> = pairs.iter()
             //                                                         .map(|(k,v)| (format!("{:?}",eval(k,&mut env)) , eval(v,&mut env)))
             //                                                         .collect();
@@ -111,70 +174,394 @@ pub fn eval(node: &ast::ASTNode, mut env: &mut Environment) -> Box<object::TheOb
             //     return error[0].clone();
             // }
             for (k, v) in pairs.iter() {
-                let key = eval(k, &mut env);
+                let key = eval(k, env);
                 if key.as_ref().is_error() {
                     return new_error(format!("{:#?} is not a key type", key.as_ref()));
                 }
                 //key ==> String
-                let key_str: String = format!("{:#?}", key.as_ref());
+                let key_str: String = hash_key(key.as_ref());
 
-                let value = eval(v, &mut env);
+                let value = eval(v, env);
                 if value.as_ref().is_error() {
                     return new_error(format!("{:#?} is not a key type", value.as_ref()));
                 }
 
-                map.insert(key_str, value);
+                map.insert(key_str, (key, value));
             }
             return new_box!(Map(map));
         }
         _ => return theobject_null!(),
     }
-    theobject_null!()
 }
 
+// a call's named arguments
+pub(crate) type NamedArgs = [(Symbol, Box<object::TheObject>)];
+
 fn apply_function(
     function: &Box<object::TheObject>,
-    call_args: &Vec<Box<object::TheObject>>,
+    call_args: &[Box<object::TheObject>],
+    named: &NamedArgs,
 ) -> Box<object::TheObject> {
     match function.as_ref() {
-        object::TheObject::Func(ref params, ref body, ref env) => {
-            let mut extended_env = Environment::new();
-            extended_env.outer = Some(Box::new(env.clone()));
+        object::TheObject::Func(ref params, ref body, ref env) => call_function(
+            params,
+            body,
+            Environment::enclosed(env),
+            call_args,
+            named,
+            0,
+        ),
+        // the receiver is passed as the first parameter
+        object::TheObject::BoundMethod(ref receiver, ref method, ref owner) => {
+            let (params, body, env) = match method.as_ref() {
+                object::TheObject::Func(params, body, env) => (params, body, env),
+                other => return new_error(format!("not a function, is {}", other.type_of())),
+            };
+            let extended_env = Environment::enclosed(env);
+            if let Some(ref superclass) = owner.superclass {
+                extended_env.set(
+                    Symbol::from("super"),
+                    new_box!(TheObject::Super(superclass.clone(), receiver.clone())),
+                );
+            }
+            let mut args = vec![receiver.clone()];
+            args.extend_from_slice(call_args);
+            call_function(params, body, extended_env, &args, named, 1)
+        }
+        object::TheObject::Class(ref class) => instantiate(class, call_args, named),
+        object::TheObject::Builtin(name) => builtins::call(*name, call_args, named),
+        _ => new_error(format!(
+            "not a function, is {}",
+            function.as_ref().type_of()
+        )),
+    }
+}
 
-            // for (key, value) in call_args.iter().enumerate() {
-            //     extended_env.store.insert(params[key].as_ref().unwrap().as_ref().value.clone(), value.clone());//问题?
-            // }
+// `implicit` leading arguments, like a method's receiver, are not counted
+// in arity errors
+pub(crate) fn call_function(
+    params: &[ast::Parameter],
+    body: &ast::ASTNode,
+    mut extended_env: Environment,
+    call_args: &[Box<object::TheObject>],
+    named: &NamedArgs,
+    implicit: usize,
+) -> Box<object::TheObject> {
+    //函数调用参数加入环境
+    if let Err(err) = bind_params(params, call_args, named, &mut extended_env, implicit) {
+        return err;
+    }
 
-            //函数调用参数加入环境
-            call_args.iter().enumerate().for_each(|(index, value)| {
-                extended_env.store.insert(
-                    params[index].as_ref().unwrap().as_ref().value.clone(),
-                    value.clone(),
-                ); //问题?
-            });
+    //eval函数体
+    let evaled = eval(body, &mut extended_env);
 
-            //eval函数体
-            let evaled = eval(body, &mut extended_env);
+    match evaled.as_ref() {
+        object::TheObject::ReturnValue(ref value) => value.clone(),
+        object::TheObject::Break | object::TheObject::Continue => loop_signal_error(&evaled),
+        _ => evaled,
+    }
+}
+
+// positional arguments fill parameters in order, named ones fill them by
+// name, and defaults are evaluated for the rest, so they may use earlier
+// parameters
+fn bind_params(
+    params: &[ast::Parameter],
+    call_args: &[Box<object::TheObject>],
+    named: &NamedArgs,
+    env: &mut Environment,
+    implicit: usize,
+) -> Result<(), Box<object::TheObject>> {
+    let (fixed, rest) = match params.split_last() {
+        Some((last, fixed)) if last.rest => (fixed, Some(last)),
+        _ => (params, None),
+    };
+    let arity_error = || {
+        let required = fixed.iter().filter(|p| p.default.is_none()).count();
+        let required = required.saturating_sub(implicit);
+        let max = fixed.len().saturating_sub(implicit);
+        let expected = match rest {
+            Some(_) => format!("at least {}", required),
+            None if required == max => required.to_string(),
+            None => format!("{} to {}", required, max),
+        };
+        new_error(format!(
+            "wrong number of arguments: expected {}, got {}",
+            expected,
+            call_args.len() + named.len() - implicit
+        ))
+    };
+    if call_args.len() > fixed.len() && rest.is_none() {
+        return Err(arity_error());
+    }
 
-            match evaled.as_ref() {
-                object::TheObject::ReturnValue(ref value) => return value.clone(),
-                _ => return evaled,
+    let mut values: Vec<Option<Box<object::TheObject>>> = vec![None; fixed.len()];
+    for (slot, arg) in values.iter_mut().zip(call_args) {
+        *slot = Some(arg.clone());
+    }
+    for (name, arg) in named {
+        // a parameter renamed by quote keeps the name it was written as
+        let written = fixed
+            .iter()
+            .position(|p| macro_expansion::source_name(p.name.value) == name.as_str());
+        match written {
+            Some(i) if values[i].is_some() => {
+                return Err(new_error(format!(
+                    "got multiple values for argument {}",
+                    name
+                )))
             }
+            Some(i) => values[i] = Some(arg.clone()),
+            None => return Err(new_error(format!("unexpected named argument {}", name))),
         }
-        _ => new_error(format!(
-            "not a function, is {}",
-            function.as_ref().type_of()
+    }
+
+    for (param, value) in fixed.iter().zip(values) {
+        let value = match value {
+            Some(value) => value,
+            None if param.default.is_some() => {
+                let value = eval(&param.default, env);
+                if value.as_ref().is_error() {
+                    return Err(value);
+                }
+                value
+            }
+            None => return Err(arity_error()),
+        };
+        env.set(param.name.value, value);
+    }
+    if let Some(rest) = rest {
+        let extra = call_args.get(fixed.len()..).unwrap_or_default().to_vec();
+        env.set(rest.name.value, new_box!(TheObject::Array(extra)));
+    }
+    Ok(())
+}
+
+fn eval_class_statement(cs: &ast::ClassStatement, env: &mut Environment) -> Box<object::TheObject> {
+    let superclass = match cs.superclass {
+        Some(ref name) => {
+            let superclass = eval_identifier(name, env);
+            match superclass.as_ref() {
+                TheObject::Class(class) => Some(class.clone()),
+                TheObject::Errors(_) => return superclass,
+                other => {
+                    return new_error(format!(
+                        "superclass of {} must be a class, not {}",
+                        cs.name.value,
+                        other.type_of()
+                    ))
+                }
+            }
+        }
+        None => None,
+    };
+
+    let mut fields = vec![];
+    let mut methods = HashMap::new();
+    for member in &cs.members {
+        match member.as_ref() {
+            ast::ASTNode::LetStatement(field) => fields.push(field.clone()),
+            ast::ASTNode::FuncLiteral(ast::FuncLiteral {
+                name: Some(name),
+                params,
+                body,
+                ..
+            }) => {
+                let method = TheObject::Func(params.clone(), body.clone(), env.clone());
+                methods.insert(name.value, new_box!(method));
+            }
+            _ => {}
+        }
+    }
+
+    let class = new_box!(TheObject::Class(Rc::new(object::Class {
+        name: cs.name.value,
+        superclass,
+        fields,
+        methods,
+        env: env.clone(),
+    })));
+    env.set(cs.name.value, class.clone());
+    class
+}
+
+// Class(args): fills in the fields, then runs init if there is one
+fn instantiate(
+    class: &Rc<object::Class>,
+    call_args: &[Box<object::TheObject>],
+    named: &NamedArgs,
+) -> Box<object::TheObject> {
+    let instance = object::Instance::new(class.clone());
+    if let Err(err) = init_fields(class, &instance) {
+        return err;
+    }
+    let receiver = new_box!(TheObject::Instance(instance));
+
+    match class.find_method(Symbol::from("init")) {
+        Some((init, owner)) => {
+            let init = new_box!(TheObject::BoundMethod(receiver.clone(), init, owner));
+            let result = apply_function(&init, call_args, named);
+            if result.as_ref().is_error() {
+                return result;
+            }
+        }
+        None if !call_args.is_empty() || !named.is_empty() => {
+            return new_error(format!(
+                "{} has no init method but was given {} arguments",
+                class.name,
+                call_args.len() + named.len()
+            ))
+        }
+        None => {}
+    }
+    receiver
+}
+
+// ancestors' fields first, so a subclass can override their initial values
+fn init_fields(
+    class: &object::Class,
+    instance: &object::Instance,
+) -> Result<(), Box<object::TheObject>> {
+    if let Some(ref superclass) = class.superclass {
+        init_fields(superclass, instance)?;
+    }
+    let mut env = Environment::enclosed(&class.env);
+    for field in &class.fields {
+        let val = eval(&field.value, &mut env);
+        if val.as_ref().is_error() {
+            return Err(val);
+        }
+        env.set(field.name.value, val.clone());
+        instance.fields.borrow_mut().insert(field.name.value, val);
+    }
+    Ok(())
+}
+
+// fields shadow methods of the same name
+fn eval_member_expression(
+    me: &ast::MemberExpression,
+    env: &mut Environment,
+) -> Box<object::TheObject> {
+    let object = eval(&me.object, env);
+    if object.as_ref().is_error() {
+        return object;
+    }
+    let name = me.name.value;
+    match object.as_ref() {
+        TheObject::Instance(instance) => {
+            if let Some(field) = instance.fields.borrow().get(&name) {
+                return field.clone();
+            }
+            match instance.class.find_method(name) {
+                Some((method, owner)) => {
+                    new_box!(TheObject::BoundMethod(object.clone(), method, owner))
+                }
+                None => new_error(format!("{} has no member {}", instance.class.name, name)),
+            }
+        }
+        TheObject::Super(superclass, receiver) => match superclass.find_method(name) {
+            Some((method, owner)) => {
+                new_box!(TheObject::BoundMethod(receiver.clone(), method, owner))
+            }
+            None => new_error(format!("{} has no method {}", superclass.name, name)),
+        },
+        TheObject::Module(module) if name.as_str().starts_with('_') => {
+            new_error(format!("{} is private to module {:?}", name, module.path))
+        }
+        TheObject::Module(module) => module.env.get(name).unwrap_or_else(|| {
+            new_error(format!("module {:?} has no export {}", module.path, name))
+        }),
+        other => new_error(format!(
+            "cannot access member {} of {}",
+            name,
+            other.type_of()
         )),
     }
 }
 
+fn eval_import_statement(
+    is: &ast::ImportStatement,
+    env: &mut Environment,
+) -> Box<object::TheObject> {
+    let loader = env.loader();
+    let module = load_module(&loader.dir.join(&is.path), &is.path, &loader.modules);
+    if !module.is_error() {
+        env.set(is.alias.value, module.clone());
+    }
+    module
+}
+
+/// Evaluates the script at `path` as the main module, so that its
+/// imports resolve relative to it. Returns the module, or the first error.
+pub fn eval_file(path: &Path) -> Box<object::TheObject> {
+    let modules = Rc::new(RefCell::new(Modules::default()));
+    load_module(path, &path.to_string_lossy(), &modules)
+}
+
+// parses and evaluates a file in a scope of its own, once per program
+fn load_module(path: &Path, shown: &str, modules: &Rc<RefCell<Modules>>) -> Box<TheObject> {
+    let key = match path.canonicalize() {
+        Ok(key) => key,
+        Err(e) => return new_error(format!("cannot import {:?}: {}", shown, e)),
+    };
+    if let Some(module) = modules.borrow().loaded.get(&key) {
+        return module.clone();
+    }
+    if let Some(i) = modules.borrow().loading.iter().position(|(p, _)| *p == key) {
+        let cycle: Vec<String> = modules.borrow().loading[i..]
+            .iter()
+            .map(|(_, shown)| format!("{:?}", shown))
+            .chain(std::iter::once(format!("{:?}", shown)))
+            .collect();
+        return new_error(format!("import cycle: {}", cycle.join(" -> ")));
+    }
+
+    let source = match fs::read_to_string(&key) {
+        Ok(source) => source,
+        Err(e) => return new_error(format!("cannot import {:?}: {}", shown, e)),
+    };
+    let mut program = match Parser::new(Lexer::new(&source)).parse_program() {
+        Ok(program) => program,
+        Err(errors) => return new_error(format!("in {:?}: {}", shown, errors)),
+    };
+    let mut macros = Environment::new();
+    if let Err(message) = macro_expansion::expand_macros(&mut program, &mut macros) {
+        return new_error(format!("in {:?}: {}", shown, message));
+    }
+    if let Err(errors) = checker::check(&program) {
+        return new_error(format!("in {:?}: {}", shown, errors));
+    }
+
+    let dir = key.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut env = Environment::with_loader(Loader {
+        dir,
+        modules: modules.clone(),
+    });
+    modules
+        .borrow_mut()
+        .loading
+        .push((key.clone(), shown.to_string()));
+    let result = eval(&program, &mut env);
+    modules.borrow_mut().loading.pop();
+    if result.is_error() {
+        return result;
+    }
+
+    let module = new_box!(TheObject::Module(Rc::new(object::Module {
+        path: shown.to_string(),
+        env,
+    })));
+    modules.borrow_mut().loaded.insert(key, module.clone());
+    module
+}
+
 fn eval_expressions(
     exprs: &Vec<Box<ast::ASTNode>>,
-    mut env: &mut Environment,
+    env: &mut Environment,
 ) -> Vec<Box<object::TheObject>> {
     let mut res = vec![];
     for i in exprs {
-        let evaled = eval(&i, &mut env);
+        let evaled = eval(i, env);
 
         if evaled.as_ref().is_error() {
             return vec![evaled];
@@ -193,27 +580,412 @@ fn bool_obj(b: bool) -> Box<object::TheObject> {
 }
 
 fn eval_identifier(node: &ast::Identifier, env: &Environment) -> Box<object::TheObject> {
-    let err = new_error(format!(
-        "identifier name not found: {}, \n and the environment is {:?}",
-        node.value, env
-    ));
-
-    env.get(&node.value).unwrap_or(err)
+    env.get(node.value)
+        .or_else(|| builtins::lookup(node.value))
+        .unwrap_or_else(|| new_error(format!("identifier name not found: {}", node.value)))
 }
 
-fn eval_ifexpression(ie: &ast::IfExpression, mut env: &mut Environment) -> Box<object::TheObject> {
-    let condition = eval(&ie.condition, &mut env);
+fn eval_ifexpression(ie: &ast::IfExpression, env: &mut Environment) -> Box<object::TheObject> {
+    let condition = eval(&ie.condition, env);
     if condition.as_ref().is_error() {
         return condition;
     }
     if is_truthy(condition.as_ref()) {
-        return eval(&ie.consequence, &mut env);
+        return eval(&ie.consequence, env);
     } else if ie.alternative.as_ref().is_some() {
-        return eval(&ie.alternative, &mut env);
+        return eval(&ie.alternative, env);
     } else {
         return theobject_null!();
     }
 }
+// the first arm whose pattern fits and whose guard holds; its bindings are
+// only visible in the guard and body
+fn eval_match_expression(
+    me: &ast::MatchExpression,
+    env: &mut Environment,
+) -> Box<object::TheObject> {
+    let value = eval(&me.value, env);
+    if value.as_ref().is_error() {
+        return value;
+    }
+    for arm in &me.arms {
+        let mut arm_env = Environment::enclosed(env);
+        if match_pattern(&arm.pattern, &value, &mut arm_env).is_err() {
+            continue;
+        }
+        if arm.guard.is_some() {
+            let guard = eval(&arm.guard, &mut arm_env);
+            if guard.as_ref().is_error() {
+                return guard;
+            }
+            if !is_truthy(guard.as_ref()) {
+                continue;
+            }
+        }
+        return eval(&arm.body, &mut arm_env);
+    }
+    new_error(format!(
+        "no match arm for {} {}",
+        value.type_of(),
+        value.inspect()
+    ))
+}
+
+// checks `value` against `pattern`, binding its names in `env` only if the
+// whole pattern fits; the error says what did not fit
+fn match_pattern(
+    pattern: &ast::Pattern,
+    value: &Box<object::TheObject>,
+    env: &mut Environment,
+) -> Result<(), String> {
+    let mut bound = vec![];
+    match_bindings(pattern, value, env, &mut bound)?;
+    for (name, value) in bound {
+        env.set(name, value);
+    }
+    Ok(())
+}
+
+// checks `value` against `pattern`, collecting what its names bind to
+fn match_bindings(
+    pattern: &ast::Pattern,
+    value: &Box<object::TheObject>,
+    env: &mut Environment,
+    bound: &mut Vec<(Symbol, Box<object::TheObject>)>,
+) -> Result<(), String> {
+    use crate::ast::ast::Pattern;
+    match (pattern, value.as_ref()) {
+        (Pattern::Wildcard, _) => Ok(()),
+        (Pattern::Binding(id), _) => {
+            bound.push((id.value, value.clone()));
+            Ok(())
+        }
+        (Pattern::Literal(node), _) => {
+            let literal = eval(node, env);
+            // `==` makes 2 match 2.0; strings have no `==`, so compare those directly
+            let equal = match eval_infix_expr("==", &literal, value).as_ref() {
+                TheObject::Boolean(b) => *b,
+                _ => literal == *value,
+            };
+            if equal {
+                Ok(())
+            } else {
+                Err(format!("expected {}, found {}", node, value.inspect()))
+            }
+        }
+        (Pattern::Array(elements, rest), TheObject::Array(items)) => {
+            let fits = match rest {
+                Some(_) => items.len() >= elements.len(),
+                None => items.len() == elements.len(),
+            };
+            if !fits {
+                return Err(format!(
+                    "expected an array of {}{} elements, found {}",
+                    if rest.is_some() { "at least " } else { "" },
+                    elements.len(),
+                    items.len()
+                ));
+            }
+            for (element, item) in elements.iter().zip(items) {
+                match_bindings(element, item, env, bound)?;
+            }
+            match rest {
+                Some(rest) => {
+                    let rest_items = items[elements.len()..].to_vec();
+                    match_bindings(rest, &new_box!(TheObject::Array(rest_items)), env, bound)
+                }
+                None => Ok(()),
+            }
+        }
+        (Pattern::Hash(pairs), TheObject::Map(map)) => {
+            for (key, pattern) in pairs {
+                match map.get(&hash_key(&eval(key, env))) {
+                    Some((_, item)) => match_bindings(pattern, item, env, bound)?,
+                    None => return Err(format!("missing key {}", key)),
+                }
+            }
+            Ok(())
+        }
+        (Pattern::Array(_, _), other) => Err(format!(
+            "expected {}, found {}",
+            object::ARRAY_OBJ,
+            other.type_of()
+        )),
+        (Pattern::Hash(_), other) => Err(format!(
+            "expected {}, found {}",
+            object::MAP_OBJ,
+            other.type_of()
+        )),
+    }
+}
+
+// what a loop body's result means for the loop itself
+enum LoopFlow {
+    Next,
+    Stop,
+    Exit(Box<object::TheObject>), // return or error, passed on unchanged
+}
+
+fn loop_flow(result: Box<object::TheObject>) -> LoopFlow {
+    match result.as_ref() {
+        TheObject::Break => LoopFlow::Stop,
+        TheObject::ReturnValue(_) | TheObject::Errors(_) => LoopFlow::Exit(result),
+        _ => LoopFlow::Next,
+    }
+}
+
+fn eval_while_expression(
+    we: &ast::WhileExpression,
+    env: &mut Environment,
+) -> Box<object::TheObject> {
+    loop {
+        let condition = eval(&we.condition, env);
+        if condition.as_ref().is_error() {
+            return condition;
+        }
+        if !is_truthy(condition.as_ref()) {
+            break;
+        }
+        match loop_flow(eval(&we.body, env)) {
+            LoopFlow::Next => continue,
+            LoopFlow::Stop => break,
+            LoopFlow::Exit(result) => return result,
+        }
+    }
+    theobject_null!()
+}
+
+fn eval_for_expression(fe: &ast::ForExpression, env: &mut Environment) -> Box<object::TheObject> {
+    let iterable = eval(&fe.iterable, env);
+    let items: Box<dyn Iterator<Item = Box<TheObject>>> = match iterable.as_ref() {
+        TheObject::Array(items) => Box::new(items.clone().into_iter()),
+        TheObject::Stringobj(s) => Box::new(
+            s.chars()
+                .map(|c| new_box!(TheObject::Stringobj(c.to_string())))
+                .collect::<Vec<_>>()
+                .into_iter(),
+        ),
+        TheObject::Range(start, end, inclusive) => {
+            Box::new((*start..exclusive_end(*end, *inclusive)).map(|i| new_int!(i)))
+        }
+        TheObject::Errors(_) => return iterable,
+        other => return new_error(format!("cannot iterate over {}", other.type_of())),
+    };
+    for item in items {
+        env.set(fe.variable.value, item);
+        match loop_flow(eval(&fe.body, env)) {
+            LoopFlow::Next => continue,
+            LoopFlow::Stop => break,
+            LoopFlow::Exit(result) => return result,
+        }
+    }
+    theobject_null!()
+}
+
+// break and continue that reach a function or the program top level
+fn loop_signal_error(signal: &object::TheObject) -> Box<object::TheObject> {
+    new_error(format!("{} outside of a loop", signal.inspect()))
+}
+
+// where an assignment stores its value: a variable or an instance field,
+// indexed by keys when the target is `a[i][j]`
+enum Place {
+    Variable(Symbol),
+    Field(object::Instance, Symbol),
+}
+
+fn eval_assign_expression(
+    ae: &ast::AssignExpression,
+    env: &mut Environment,
+) -> Box<object::TheObject> {
+    use crate::token::token::TokenType::*;
+
+    // `target op= v` reads and writes the same place, so the parts of the
+    // target are evaluated once, before v
+    let compound = match (ae.token.ty, ae.value.as_ref()) {
+        (PlusASSIGN, ast::ASTNode::InfixExpression(infix))
+        | (MinusASSIGN, ast::ASTNode::InfixExpression(infix))
+        | (AsteriskASSIGN, ast::ASTNode::InfixExpression(infix))
+        | (SlashASSIGN, ast::ASTNode::InfixExpression(infix)) => Some(infix),
+        _ => None,
+    };
+    let val = match compound {
+        Some(_) => theobject_null!(),
+        None => eval(&ae.value, env),
+    };
+    if val.as_ref().is_error() {
+        return val;
+    }
+
+    let (place, keys) = match eval_place(&ae.target, env) {
+        Ok(place) => place,
+        Err(err) => return err,
+    };
+    let val = match compound {
+        Some(infix) => {
+            let current = read_place(&place, &keys, env);
+            if current.is_error() {
+                return current;
+            }
+            let right = eval(&infix.right, env);
+            if right.is_error() {
+                return right;
+            }
+            let val = eval_infix_expr(&infix.operator, &current, &right);
+            if val.is_error() {
+                return val;
+            }
+            val
+        }
+        None => val,
+    };
+
+    match place {
+        Place::Variable(name) => {
+            let mut root = match env.get(name) {
+                Some(root) => root,
+                None => return new_error(format!("cannot assign to undefined variable {}", name)),
+            };
+            if let Err(err) = set_index(&mut root, &keys, val.clone()) {
+                return err;
+            }
+            env.assign(name, root);
+        }
+        Place::Field(instance, name) => {
+            let field = instance.fields.borrow().get(&name).cloned();
+            let mut root = match field {
+                Some(root) => root,
+                None if keys.is_empty() => theobject_null!(),
+                None => return new_error(format!("{} has no field {}", instance.class.name, name)),
+            };
+            if let Err(err) = set_index(&mut root, &keys, val.clone()) {
+                return err;
+            }
+            instance.fields.borrow_mut().insert(name, root);
+        }
+    }
+    val
+}
+
+// a[i][j]: walks down to the variable or field, evaluating the object of
+// a field and the indices once each, left to right
+fn eval_place(
+    target: &ast::ASTNode,
+    env: &mut Environment,
+) -> Result<(Place, Vec<Box<object::TheObject>>), Box<object::TheObject>> {
+    let mut indices = vec![];
+    let mut target = target;
+    while let ast::ASTNode::IndexLiteral(_, left, index, _) = target {
+        indices.push(index);
+        target = left;
+    }
+
+    let place = match target {
+        ast::ASTNode::Identifier(id) => Place::Variable(id.value),
+        ast::ASTNode::MemberExpression(me) => {
+            let object = eval(&me.object, env);
+            match *object {
+                TheObject::Instance(instance) => Place::Field(instance, me.name.value),
+                TheObject::Errors(_) => return Err(object),
+                other => {
+                    return Err(new_error(format!(
+                        "cannot assign to member {} of {}",
+                        me.name.value,
+                        other.type_of()
+                    )))
+                }
+            }
+        }
+        other => return Err(new_error(format!("cannot assign to {}", other.kind()))),
+    };
+
+    let mut keys = vec![];
+    for index in indices.iter().rev() {
+        let key = eval(index, env);
+        if key.as_ref().is_error() {
+            return Err(key);
+        }
+        keys.push(key);
+    }
+    Ok((place, keys))
+}
+
+// the value at place[keys[0]][keys[1]]..
+fn read_place(
+    place: &Place,
+    keys: &[Box<object::TheObject>],
+    env: &Environment,
+) -> Box<object::TheObject> {
+    let root = match place {
+        Place::Variable(name) => env.get(*name),
+        Place::Field(instance, name) => instance.fields.borrow().get(name).cloned(),
+    };
+    let mut value = match (root, place) {
+        (Some(root), _) => root,
+        (None, Place::Variable(name)) => {
+            return new_error(format!("identifier name not found: {}", name))
+        }
+        (None, Place::Field(instance, name)) => {
+            return new_error(format!("{} has no field {}", instance.class.name, name))
+        }
+    };
+    for key in keys {
+        value = eval_index_expression(&value, key);
+        if value.is_error() {
+            break;
+        }
+    }
+    value
+}
+
+// container[keys[0]][keys[1]].. = value, or container = value when there
+// are no keys; arrays and maps are values, so the caller stores the updated
+// container back where it came from
+fn set_index(
+    container: &mut object::TheObject,
+    keys: &[Box<object::TheObject>],
+    value: Box<object::TheObject>,
+) -> Result<(), Box<object::TheObject>> {
+    let (key, rest) = match keys.split_first() {
+        Some(split) => split,
+        None => {
+            *container = *value;
+            return Ok(());
+        }
+    };
+    let slot = match (container, key.as_ref()) {
+        (TheObject::Array(items), TheObject::Integer(i)) => match resolve_index(*i, items.len()) {
+            Some(i) => &mut items[i],
+            None => {
+                return Err(new_error(format!(
+                    "index out of bounds length of array is {}, but have {}",
+                    items.len(),
+                    i
+                )))
+            }
+        },
+        (TheObject::Map(map), key) => {
+            let hash = hash_key(key);
+            if rest.is_empty() {
+                map.insert(hash, (Box::new(key.clone()), value));
+                return Ok(());
+            }
+            match map.get_mut(&hash) {
+                Some((_, slot)) => slot,
+                None => return Err(new_error(format!("key not found: {}", key.inspect()))),
+            }
+        }
+        (container, key) => {
+            return Err(new_error(format!(
+                "index assignment not supported: {}[{}]",
+                container.type_of(),
+                key.type_of()
+            )))
+        }
+    };
+    set_index(slot, rest, value)
+}
+
 fn is_truthy(obj: &object::TheObject) -> bool {
     match obj {
         TheObject::Boolean(b) => *b,
@@ -222,6 +994,26 @@ fn is_truthy(obj: &object::TheObject) -> bool {
     }
 }
 
+// && and || only evaluate the right side when the left does not decide
+fn eval_logical_expr(
+    infix: &ast::InfixExpression,
+    env: &mut Environment,
+) -> Box<object::TheObject> {
+    let left = eval(&infix.left, env);
+    if left.as_ref().is_error() {
+        return left;
+    }
+    let left = is_truthy(left.as_ref());
+    if (infix.operator == "&&") != left {
+        return bool_obj(left);
+    }
+    let right = eval(&infix.right, env);
+    if right.as_ref().is_error() {
+        return right;
+    }
+    bool_obj(is_truthy(right.as_ref()))
+}
+
 fn eval_infix_expr(
     operator: &str,
     left: &Box<object::TheObject>,
@@ -229,6 +1021,8 @@ fn eval_infix_expr(
 ) -> Box<object::TheObject> {
     if left.type_of() == object::INTEGER_OBJ && right.type_of() == object::INTEGER_OBJ {
         return eval_integer_infix_expression(operator, left, right);
+    } else if as_float(left).is_some() && as_float(right).is_some() {
+        return eval_float_infix_expression(operator, left, right);
     } else if right.type_of() == object::STRING_OBJ && left.type_of() == object::STRING_OBJ {
         return eval_string_infix_expression(operator, left, right);
     } else if left.as_ref().type_of() != right.as_ref().type_of() {
@@ -293,12 +1087,77 @@ fn eval_integer_infix_expression(
 ) -> Box<object::TheObject> {
     match (left.as_ref(), right.as_ref()) {
         (TheObject::Integer(a), TheObject::Integer(b)) => match operator {
-            "+" => new_int!(a + b),
-            "-" => new_int!(a - b),
-            "*" => new_int!(a * b),
-            "/" => new_int!(a / b),
+            "+" | "-" | "*" => {
+                let result = match operator {
+                    "+" => a.checked_add(*b),
+                    "-" => a.checked_sub(*b),
+                    _ => a.checked_mul(*b),
+                };
+                match result {
+                    Some(n) => new_int!(n),
+                    None => new_error(format!("integer overflow: {} {} {}", a, operator, b)),
+                }
+            }
+            "/" | "%" if *b == 0 => new_error("division by zero"),
+            // i64::MIN / -1 does not fit
+            "/" => match a.checked_div(*b) {
+                Some(q) => new_int!(q),
+                None => new_error(format!("integer overflow: {} / {}", a, b)),
+            },
+            "%" => match a.checked_rem(*b) {
+                Some(r) => new_int!(r),
+                None => new_error(format!("integer overflow: {} % {}", a, b)),
+            },
             "<" => bool_obj(a < b),
             ">" => bool_obj(a > b),
+            "<=" => bool_obj(a <= b),
+            ">=" => bool_obj(a >= b),
+            "==" => bool_obj(a == b),
+            "!=" => bool_obj(a != b),
+            ".." => new_box!(TheObject::Range(*a, *b, false)),
+            "..=" if *b == i64::MAX => new_error("range end overflows"),
+            "..=" => new_box!(TheObject::Range(*a, *b, true)),
+            _ => new_error(format!(
+                "unknown operator: {} {} {}",
+                left.as_ref().type_of(),
+                operator,
+                right.as_ref().type_of()
+            )),
+        },
+        _ => new_error(format!(
+            "unknown operator: {} {} {}",
+            left.as_ref().type_of(),
+            operator,
+            right.as_ref().type_of()
+        )),
+    }
+}
+
+// an integer mixed with a float is promoted to float
+fn as_float(obj: &object::TheObject) -> Option<f64> {
+    match obj {
+        TheObject::Integer(i) => Some(*i as f64),
+        TheObject::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+fn eval_float_infix_expression(
+    operator: &str,
+    left: &Box<object::TheObject>,
+    right: &Box<object::TheObject>,
+) -> Box<object::TheObject> {
+    match (as_float(left), as_float(right)) {
+        (Some(a), Some(b)) => match operator {
+            "+" => new_box!(TheObject::Float(a + b)),
+            "-" => new_box!(TheObject::Float(a - b)),
+            "*" => new_box!(TheObject::Float(a * b)),
+            "/" => new_box!(TheObject::Float(a / b)),
+            "%" => new_box!(TheObject::Float(a % b)),
+            "<" => bool_obj(a < b),
+            ">" => bool_obj(a > b),
+            "<=" => bool_obj(a <= b),
+            ">=" => bool_obj(a >= b),
             "==" => bool_obj(a == b),
             "!=" => bool_obj(a != b),
             _ => new_error(format!(
@@ -344,20 +1203,25 @@ fn eval_bang_op_expr(right: &Box<object::TheObject>) -> Box<object::TheObject> {
 
 fn eval_minus_op_expr(right: &Box<object::TheObject>) -> Box<object::TheObject> {
     match right.as_ref() {
-        TheObject::Integer(i) => new_int!(-(*i)),
+        TheObject::Integer(i) => match i.checked_neg() {
+            Some(n) => new_int!(n),
+            None => new_error(format!("integer overflow: -({})", i)),
+        },
+        TheObject::Float(f) => new_box!(TheObject::Float(-(*f))),
         _ => new_error(format!("unknown operator: -{}", right.as_ref().type_of())),
     }
 }
 
-fn eval_program(program: &ast::Program, mut env: &mut Environment) -> Box<object::TheObject> {
+fn eval_program(program: &ast::Program, env: &mut Environment) -> Box<object::TheObject> {
     use crate::object::object::TheObject::*;
     let mut result = Box::new(object::TheObject::default());
     for i in &program.statements {
-        result = eval(i, &mut env);
+        result = eval(i, env);
         //TODO
         match result.as_ref() {
             ReturnValue(ref value) => return value.clone(),
             Errors(ref value) => return new_error(value.clone()),
+            Break | Continue => return loop_signal_error(&result),
             _ => continue,
         }
     }
@@ -366,13 +1230,16 @@ fn eval_program(program: &ast::Program, mut env: &mut Environment) -> Box<object
 
 fn eval_statements(
     stmts: &Vec<Box<ast::ASTNode>>,
-    mut env: &mut Environment,
+    env: &mut Environment,
 ) -> Box<object::TheObject> {
     let mut result = Box::new(object::TheObject::default());
     for i in stmts {
-        result = eval(i, &mut env);
+        result = eval(i, env);
         match result.as_ref().type_of() {
-            object::RETURN_VALUE_OBJ | object::ERROR_OBJ => return result,
+            object::RETURN_VALUE_OBJ
+            | object::ERROR_OBJ
+            | object::BREAK_OBJ
+            | object::CONTINUE_OBJ => return result,
             _ => continue,
         }
     }
@@ -383,18 +1250,122 @@ fn eval_index_expression(
     left: &Box<object::TheObject>,
     index: &Box<object::TheObject>,
 ) -> Box<object::TheObject> {
-    if left.as_ref().type_of() == object::ARRAY_OBJ
-        && index.as_ref().type_of() == object::INTEGER_OBJ
-    {
-        eval_array_index_expression(left, index)
-    } else if left.as_ref().type_of() == object::MAP_OBJ {
-        eval_hash_index_expression(left, index)
+    match (left.as_ref(), index.as_ref()) {
+        (TheObject::Map(_), _) => eval_hash_index_expression(left, index),
+        (TheObject::Array(items), TheObject::Integer(i)) => match resolve_index(*i, items.len()) {
+            Some(i) => items[i].clone(),
+            None => new_error(format!(
+                "index out of bounds length of array is {}, but have {}",
+                items.len(),
+                i
+            )),
+        },
+        (TheObject::Stringobj(s), TheObject::Integer(i)) => {
+            let len = s.chars().count();
+            match resolve_index(*i, len) {
+                Some(i) => new_box!(TheObject::Stringobj(s.chars().nth(i).unwrap().to_string())),
+                None => new_error(format!(
+                    "index out of bounds length of string is {}, but have {}",
+                    len, i
+                )),
+            }
+        }
+        (_, TheObject::Range(start, end, inclusive)) => {
+            eval_slice(left, Some(*start), Some(exclusive_end(*end, *inclusive)))
+        }
+        (left, index) => new_error(format!(
+            "index operator not supported: {}[{}]",
+            left.type_of(),
+            index.type_of()
+        )),
+    }
+}
+
+// a negative index counts back from the end; None when out of range
+// the end of a range, excluded; `..=` never makes one ending at i64::MAX
+fn exclusive_end(end: i64, inclusive: bool) -> i64 {
+    if inclusive {
+        end + 1
     } else {
-        new_error(format!(
-            "error: left:{:#?}, index:{:#?}",
-            left.as_ref(),
-            index.as_ref()
-        ))
+        end
+    }
+}
+
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { index + len as i64 } else { index };
+    if index >= 0 && (index as usize) < len {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+fn eval_slice_expression(se: &ast::SliceExpression, env: &mut Environment) -> Box<TheObject> {
+    let left = eval(&se.left, env);
+    if left.is_error() {
+        return left;
+    }
+    let mut bounds = [None, None];
+    for (bound, node) in bounds.iter_mut().zip([&se.low, &se.high]) {
+        if node.is_none() {
+            continue;
+        }
+        let value = eval(node, env);
+        match value.as_ref() {
+            TheObject::Integer(i) => *bound = Some(*i),
+            v if v.is_error() => return value,
+            v => {
+                return new_error(format!(
+                    "slice bounds must be integers, found {}",
+                    v.type_of()
+                ))
+            }
+        }
+    }
+    eval_slice(&left, bounds[0], bounds[1])
+}
+
+// obj[low:high] for an array or string; a missing bound means the start or
+// the end
+fn eval_slice(obj: &TheObject, low: Option<i64>, high: Option<i64>) -> Box<TheObject> {
+    match obj {
+        TheObject::Array(items) => match slice_range(low, high, items.len()) {
+            Ok(range) => new_box!(TheObject::Array(items[range].to_vec())),
+            Err(err) => err,
+        },
+        TheObject::Stringobj(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            match slice_range(low, high, chars.len()) {
+                Ok(range) => new_box!(TheObject::Stringobj(chars[range].iter().collect())),
+                Err(err) => err,
+            }
+        }
+        other => new_error(format!("cannot slice {}", other.type_of())),
+    }
+}
+
+fn slice_range(
+    low: Option<i64>,
+    high: Option<i64>,
+    len: usize,
+) -> Result<std::ops::Range<usize>, Box<TheObject>> {
+    // unlike an index, a bound may be len itself
+    let bound = |b: Option<i64>, default: usize| match b {
+        None => Some(default),
+        Some(b) if b >= 0 && b as usize <= len => Some(b as usize),
+        Some(b) => resolve_index(b, len).filter(|_| b < 0),
+    };
+    match (bound(low, 0), bound(high, len)) {
+        (Some(start), Some(end)) if start <= end => Ok(start..end),
+        _ => {
+            let show = |b: Option<i64>| b.map(|b| b.to_string()).unwrap_or_default();
+            Err(new_error(format!(
+                "slice bounds out of range [{}:{}] with length {}",
+                show(low),
+                show(high),
+                len
+            )))
+        }
     }
 }
 
@@ -404,60 +1375,134 @@ fn eval_hash_index_expression(
 ) -> Box<object::TheObject> {
     use crate::object::object::TheObject::*;
     match map.as_ref() {
-        Map(ref map) => map
-            .get(&format!("{:#?}", index))
-            .unwrap_or(&Box::new(NULL))
-            .clone(),
-        _ => new_error(format!("not a map object")),
+        Map(ref map) => match map.get(&hash_key(index)) {
+            Some((_, value)) => value.clone(),
+            None => new_box!(NULL),
+        },
+        _ => new_error("not a map object".to_string()),
     }
 }
 
-fn eval_array_index_expression(
-    array: &Box<object::TheObject>,
-    index: &Box<object::TheObject>,
-) -> Box<object::TheObject> {
-    use crate::object::object::TheObject::*;
-    match (array.as_ref(), index.as_ref()) {
-        (Array(ref array), Integer(ref index)) => {
-            if *index < 0 || *index > array.len() as i64 {
-                return new_error(format!(
-                    "index out of bounds length of array is {}, but have {}",
-                    array.len(),
-                    *index
-                ));
-            }
-
-            array[*index as usize].clone()
-        }
-        _ => new_error(format!("there has some error ")),
-    }
+// maps are keyed by the debug rendering of the key object
+fn hash_key(key: &object::TheObject) -> String {
+    format!("{:#?}", key)
 }
 
-fn new_error<S: Into<String>>(s: S) -> Box<object::TheObject> {
+pub(crate) fn new_error<S: Into<String>>(s: S) -> Box<object::TheObject> {
     new_box!(object::TheObject::Errors(s.into()))
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct Environment {
-    pub store: HashMap<String, Box<object::TheObject>>,
-    pub outer: Option<Box<Environment>>,
+/// A scope of variables.
+///
+/// Cloning an `Environment` gives another handle to the same scope, so a
+/// closure sees, and can update, the variables of the scope it was made in.
+#[derive(Clone)]
+pub struct Environment(Rc<RefCell<Scope>>);
+
+#[derive(Default)]
+struct Scope {
+    store: HashMap<Symbol, Box<object::TheObject>>,
+    outer: Option<Environment>,
+    loader: Option<Rc<Loader>>, // set on top-level scopes only
+}
+
+// where a module's imports are looked up
+struct Loader {
+    dir: PathBuf,
+    modules: Rc<RefCell<Modules>>, // shared by all modules of a program
+}
+
+// files by canonical path: those evaluated, and those being evaluated
+// along with the path they were imported as
+#[derive(Default)]
+struct Modules {
+    loaded: HashMap<PathBuf, Box<TheObject>>,
+    loading: Vec<(PathBuf, String)>,
 }
 
 impl Environment {
+    /// A top-level scope whose imports resolve relative to the current
+    /// directory.
     pub fn new() -> Self {
-        Environment {
-            store: HashMap::new(),
-            outer: None,
+        Environment::in_dir(".")
+    }
+
+    /// A top-level scope whose imports resolve relative to `dir`.
+    pub fn in_dir<P: Into<PathBuf>>(dir: P) -> Self {
+        Environment::with_loader(Loader {
+            dir: dir.into(),
+            modules: Rc::new(RefCell::new(Modules::default())),
+        })
+    }
+
+    fn with_loader(loader: Loader) -> Self {
+        Environment(Rc::new(RefCell::new(Scope {
+            loader: Some(Rc::new(loader)),
+            ..Scope::default()
+        })))
+    }
+
+    /// A new scope nested inside `outer`.
+    pub fn enclosed(outer: &Environment) -> Self {
+        Environment(Rc::new(RefCell::new(Scope {
+            outer: Some(outer.clone()),
+            ..Scope::default()
+        })))
+    }
+
+    fn loader(&self) -> Rc<Loader> {
+        let scope = self.0.borrow();
+        match (&scope.loader, &scope.outer) {
+            (Some(loader), _) => loader.clone(),
+            (None, Some(outer)) => outer.loader(),
+            (None, None) => unreachable!("scopes are nested in a top-level one"),
         }
     }
-    pub fn get(&self, k: &String) -> Option<Box<object::TheObject>> {
-        if self.store.get(k).is_some() {
-            return Some(self.store.get(k).unwrap().clone());
-        } else {
-            if self.outer.is_some() {
-                return self.outer.clone().unwrap().as_ref().get(k);
-            }
-            return None;
+
+    pub fn get(&self, k: Symbol) -> Option<Box<object::TheObject>> {
+        let scope = self.0.borrow();
+        match scope.store.get(&k) {
+            Some(v) => Some(v.clone()),
+            None => scope.outer.as_ref()?.get(k),
+        }
+    }
+
+    /// Binds `k` in this scope, shadowing any outer binding.
+    pub fn set(&self, k: Symbol, v: Box<object::TheObject>) {
+        self.0.borrow_mut().store.insert(k, v);
+    }
+
+    /// Rebinds `k` in the innermost scope that defines it. Returns false
+    /// if no scope does.
+    pub fn assign(&self, k: Symbol, v: Box<object::TheObject>) -> bool {
+        let mut scope = self.0.borrow_mut();
+        if let Some(slot) = scope.store.get_mut(&k) {
+            *slot = v;
+            return true;
         }
+        match scope.outer {
+            Some(ref outer) => outer.assign(k, v),
+            None => false,
+        }
+    }
+}
+
+// two handles are equal when they share a scope
+impl PartialEq for Environment {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+// only names are printed: values may be closures holding this very scope
+impl std::fmt::Debug for Environment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scope = self.0.borrow();
+        let mut names: Vec<&str> = scope.store.keys().map(|k| k.as_str()).collect();
+        names.sort_unstable();
+        f.debug_struct("Environment")
+            .field("names", &names)
+            .field("outer", &scope.outer)
+            .finish()
     }
 }
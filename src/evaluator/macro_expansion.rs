@@ -0,0 +1,306 @@
+//! Macros.
+//!
+//! `let name = macro(params) { body };` at the top level of a program
+//! defines a macro. Before the program runs, every call `name(args)` is
+//! replaced by what `body` returns when its parameters are bound to the
+//! quoted, unevaluated arguments. The body builds that code with
+//! `quote(expr)`, splicing values back in with `unquote(expr)`:
+//!
+//! ```text
+//! let unless = macro(cond, then, otherwise) {
+//!     quote(if (!(unquote(cond))) { unquote(then) } else { unquote(otherwise) })
+//! };
+//! ```
+//!
+//! Quoting is hygienic: names bound inside quoted code are renamed to ones
+//! no source can spell, so an expansion cannot capture the caller's
+//! variables, nor shadow them.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::evaluator::{call_function, eval, new_error, Environment};
+use crate::ast::ast::{self, ASTNode};
+use crate::object::object::TheObject;
+use crate::token::token::{Symbol, Token};
+
+// expanding a macro may produce more macro calls, up to this deep
+const MAX_DEPTH: usize = 64;
+
+/// Removes the macro definitions from `program` into `env`, then expands
+/// every call to a macro in `env`. `env` may hold macros from earlier
+/// programs, as in the REPL.
+pub fn expand_macros(program: &mut ASTNode, env: &mut Environment) -> Result<(), String> {
+    define_macros(program, env);
+    expand(program, env, 0)
+}
+
+fn define_macros(program: &mut ASTNode, env: &mut Environment) {
+    let program = match program {
+        ASTNode::Program(program) => program,
+        _ => return,
+    };
+    program.statements.retain(|stmt| match stmt.as_ref() {
+        ASTNode::LetStatement(ast::LetStatement {
+            name,
+            pattern: None,
+            value,
+            ..
+        }) => match value.as_ref() {
+            ASTNode::MacroLiteral(m) => {
+                let body = m.body.clone();
+                env.set(
+                    name.value,
+                    Box::new(TheObject::Macro(m.params.clone(), body, env.clone())),
+                );
+                false
+            }
+            _ => true,
+        },
+        _ => true,
+    });
+}
+
+// innermost calls first, so a macro sees its arguments already expanded
+fn expand(node: &mut ASTNode, env: &Environment, depth: usize) -> Result<(), String> {
+    for child in node.children_mut() {
+        expand(child, env, depth)?;
+    }
+    let call = match node {
+        ASTNode::CallExpression(call) => call,
+        _ => return Ok(()),
+    };
+    let name = match call.func.as_ref() {
+        ASTNode::Identifier(id) => id.value,
+        _ => return Ok(()),
+    };
+    let (params, body, macro_env) = match env.get(name).as_deref() {
+        Some(TheObject::Macro(params, body, macro_env)) => {
+            (params.clone(), body.clone(), macro_env.clone())
+        }
+        _ => return Ok(()),
+    };
+    if depth == MAX_DEPTH {
+        return Err(format!(
+            "macro {} expands more than {} deep",
+            name, MAX_DEPTH
+        ));
+    }
+
+    let quoted = |arg: &ASTNode| Box::new(TheObject::Quote(arg.clone()));
+    let args: Vec<_> = call.args.iter().map(|arg| quoted(arg)).collect();
+    let named: Vec<_> = call
+        .named
+        .iter()
+        .map(|(param, arg)| (param.value, quoted(arg)))
+        .collect();
+    let expanded = call_function(
+        &params,
+        &body,
+        Environment::enclosed(&macro_env),
+        &args,
+        &named,
+        0,
+    );
+    match *expanded {
+        TheObject::Quote(code) => {
+            *node = code;
+            expand(node, env, depth + 1)
+        }
+        TheObject::Errors(message) => Err(format!("in macro {}: {}", name, message)),
+        other => Err(format!(
+            "macro {} must return a quote, found {}",
+            name,
+            other.type_of()
+        )),
+    }
+}
+
+/// Whether `call` is `quote(expr)`, which is evaluated by `quote`.
+pub fn is_quote(call: &ast::CallExpression) -> bool {
+    call_of(&call.func, "quote") && call.args.len() == 1 && call.named.is_empty()
+}
+
+/// The syntax of `node`, with each `unquote(e)` in it replaced by the
+/// syntax of `e`'s value in `env`.
+pub fn quote(node: &ASTNode, env: &mut Environment) -> Box<TheObject> {
+    let mut node = node.clone();
+    Renamer {
+        scopes: vec![HashMap::new()],
+    }
+    .rename(&mut node);
+    if let Err(err) = unquote(&mut node, env) {
+        return err;
+    }
+    Box::new(TheObject::Quote(node))
+}
+
+fn call_of(func: &ASTNode, name: &str) -> bool {
+    matches!(func, ASTNode::Identifier(id) if id.value == name)
+}
+
+// the argument of `unquote(arg)`
+fn unquoted(node: &ASTNode) -> Option<&ASTNode> {
+    match node {
+        ASTNode::CallExpression(call) if call_of(&call.func, "unquote") && call.args.len() == 1 => {
+            Some(&call.args[0])
+        }
+        _ => None,
+    }
+}
+
+fn unquote(node: &mut ASTNode, env: &mut Environment) -> Result<(), Box<TheObject>> {
+    let value = match unquoted(node) {
+        Some(arg) => eval(arg, env),
+        None => {
+            for child in node.children_mut() {
+                unquote(child, env)?;
+            }
+            return Ok(());
+        }
+    };
+    if value.is_error() {
+        return Err(value);
+    }
+    *node = to_syntax(&value)
+        .ok_or_else(|| new_error(format!("cannot unquote {}", value.type_of())))?;
+    Ok(())
+}
+
+// code that evaluates to `value`
+fn to_syntax(value: &TheObject) -> Option<ASTNode> {
+    let node = match value {
+        TheObject::Integer(i) => {
+            let mut lit = ast::IntegerLiteral::new(Token::default());
+            lit.value = *i;
+            ASTNode::IntegerLiteral(lit)
+        }
+        TheObject::Float(f) => {
+            let mut lit = ast::FloatLiteral::new(Token::default());
+            lit.value = *f;
+            ASTNode::FloatLiteral(lit)
+        }
+        TheObject::Boolean(b) => ASTNode::Boolean(ast::Boolean::new(Token::default(), *b)),
+        TheObject::Stringobj(s) => ASTNode::StringLiteral(None, s.clone()),
+        TheObject::Array(elements) => {
+            let elements: Option<Vec<_>> = elements
+                .iter()
+                .map(|e| to_syntax(e).map(Box::new))
+                .collect();
+            ASTNode::ArrayLiteral(None, elements?, None)
+        }
+        TheObject::Quote(node) => node.clone(),
+        _ => return None,
+    };
+    Some(node)
+}
+
+/// The name `name` was written as, before quoting renamed it. Named
+/// arguments find their parameters by it.
+pub(crate) fn source_name(name: Symbol) -> &'static str {
+    let name = name.as_str();
+    name.split('#').next().unwrap_or(name)
+}
+
+fn fresh_name(name: Symbol) -> Symbol {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    Symbol::from(format!("{}#{}", source_name(name), n))
+}
+
+// what each name bound in quoted code, outside of unquote(..), is renamed
+// to within its binder's scope: a name of the form `name#n`, which the
+// lexer never produces. Scopes follow the evaluator's environments, so
+// functions and match arms have their own, and a let or a for binds in
+// the one it is in. Fields and named-argument keys are left as written.
+struct Renamer {
+    scopes: Vec<HashMap<Symbol, Symbol>>,
+}
+
+impl Renamer {
+    fn bind(&mut self, id: &mut ast::Identifier) {
+        let fresh = fresh_name(id.value);
+        self.scopes.last_mut().unwrap().insert(id.value, fresh);
+        id.value = fresh;
+    }
+
+    fn lookup(&self, name: Symbol) -> Option<Symbol> {
+        self.scopes.iter().rev().find_map(|s| s.get(&name)).copied()
+    }
+
+    fn rename(&mut self, node: &mut ASTNode) {
+        if unquoted(node).is_some() {
+            return;
+        }
+        match node {
+            ASTNode::Identifier(id) => {
+                if let Some(fresh) = self.lookup(id.value) {
+                    id.value = fresh;
+                }
+            }
+            ASTNode::LetStatement(stmt) => match &mut stmt.pattern {
+                Some(pattern) => {
+                    self.rename(&mut stmt.value);
+                    for node in pattern.nodes_mut() {
+                        self.rename(node);
+                    }
+                    for id in pattern.bindings_mut() {
+                        self.bind(id);
+                    }
+                }
+                // bound first, so that a function can call itself
+                None if matches!(stmt.value.as_ref(), ASTNode::FuncLiteral(_)) => {
+                    self.bind(&mut stmt.name);
+                    self.rename(&mut stmt.value);
+                }
+                None => {
+                    self.rename(&mut stmt.value);
+                    self.bind(&mut stmt.name);
+                }
+            },
+            ASTNode::FuncLiteral(f) => {
+                self.scopes.push(HashMap::new());
+                for param in f.params.iter_mut() {
+                    self.rename(&mut param.default);
+                    self.bind(&mut param.name);
+                }
+                self.rename(&mut f.body);
+                self.scopes.pop();
+            }
+            ASTNode::ForExpression(f) => {
+                self.rename(&mut f.iterable);
+                self.bind(&mut f.variable);
+                self.rename(&mut f.body);
+            }
+            ASTNode::MatchExpression(m) => {
+                self.rename(&mut m.value);
+                for arm in m.arms.iter_mut() {
+                    self.scopes.push(HashMap::new());
+                    for node in arm.pattern.nodes_mut() {
+                        self.rename(node);
+                    }
+                    for id in arm.pattern.bindings_mut() {
+                        self.bind(id);
+                    }
+                    self.rename(&mut arm.guard);
+                    self.rename(&mut arm.body);
+                    self.scopes.pop();
+                }
+            }
+            // a field's name is not a variable, but its value may use some
+            ASTNode::ClassStatement(class) => {
+                for member in class.members.iter_mut() {
+                    match member.as_mut() {
+                        ASTNode::LetStatement(field) => self.rename(&mut field.value),
+                        method => self.rename(method),
+                    }
+                }
+            }
+            node => {
+                for child in node.children_mut() {
+                    self.rename(child);
+                }
+            }
+        }
+    }
+}
@@ -1,2 +1,5 @@
+pub mod builtins;
 pub mod evaluator;
+pub mod macro_expansion;
+#[cfg(test)]
 mod test;
@@ -1,7 +1,7 @@
-use std::collections::HashMap;
-
 use super::evaluator;
 use super::evaluator::eval;
+use super::macro_expansion::expand_macros;
+use crate::ast::ast;
 use crate::mylexer::lexer;
 use crate::object::object;
 use crate::parser::parser;
@@ -54,6 +54,37 @@ fn test_eval_int_expr() {
     }
 }
 
+#[test]
+fn test_eval_number_literals() {
+    let tests = vec![
+        ("0x1F", 31),
+        ("0o17", 15),
+        ("0b1010", 10),
+        ("1_000_000", 1000000),
+        ("0xff_ff + 1", 65536),
+    ];
+    for (input, expected) in tests {
+        test_int_obj(test_eval(input), expected);
+    }
+
+    let tests = vec![
+        ("2.75", 2.75),
+        ("3.14e-2", 0.0314),
+        ("-2.5 * 2", -5.0),
+        ("1 / 4.0", 0.25),
+        ("1_0.5e1", 105.0),
+    ];
+    for (input, expected) in tests {
+        match test_eval(input).as_ref() {
+            object::TheObject::Float(f) => assert_eq!(*f, expected, "{}", input),
+            obj => panic!("not Float ... but is {:#?}", obj),
+        }
+    }
+
+    test_bool_obj(test_eval("1.5 < 2"), true);
+    test_bool_obj(test_eval("2.0 == 2"), true);
+}
+
 #[test]
 fn test_eval_bool_expr() {
     let tests = vec![
@@ -87,6 +118,77 @@ fn test_eval_bool_expr() {
     }
 }
 
+#[test]
+fn test_eval_operators() {
+    let tests = vec![
+        ("7 % 3", 1),
+        ("-7 % 3", -1),
+        ("1 + 10 % 4 * 2", 5),
+        ("let x = 5; x += 3; x;", 8),
+        ("let x = 5; x -= 3 * 2; x;", -1),
+        ("let x = 5; x *= 2 + 1; x;", 15),
+        ("let x = 9; x /= 2; x;", 4),
+    ];
+    for (input, expected) in tests {
+        test_int_obj(test_eval(input), expected);
+    }
+
+    let tests = vec![
+        ("1 <= 1", true),
+        ("2 <= 1", false),
+        ("1 >= 1", true),
+        ("1 >= 2", false),
+        ("1.5 >= 1", true),
+        ("true && 1 < 2", true),
+        ("true && false", false),
+        ("false || 1 <= 2", true),
+        ("false || false", false),
+        ("1 < 2 || 2 < 1 && false", true),
+        // the right side is never evaluated, so the unbound name is fine
+        ("false && undefined", false),
+        ("true || undefined", true),
+    ];
+    for (input, expected) in tests {
+        test_bool_obj(test_eval(input), expected);
+    }
+
+    for input in ["true && undefined", "1 % 0", "1 / 0"] {
+        assert!(test_eval(input).is_error(), "{}", input);
+    }
+    test_error_obj(
+        test_eval("true && undefined"),
+        "identifier name not found: undefined",
+    );
+
+    let min = "(-9223372036854775807 - 1)";
+    test_error_obj(
+        test_eval(format!("{} % -1", min)),
+        "integer overflow: -9223372036854775808 % -1",
+    );
+    test_error_obj(
+        test_eval(format!("{} / -1", min)),
+        "integer overflow: -9223372036854775808 / -1",
+    );
+    let max = "9223372036854775807";
+    test_error_obj(
+        test_eval(format!("{} + 1", max)),
+        "integer overflow: 9223372036854775807 + 1",
+    );
+    test_error_obj(
+        test_eval(format!("{} - 1", min)),
+        "integer overflow: -9223372036854775808 - 1",
+    );
+    test_error_obj(
+        test_eval(format!("{} * 2", max)),
+        "integer overflow: 9223372036854775807 * 2",
+    );
+    test_error_obj(
+        test_eval(format!("-{}", min)),
+        "integer overflow: -(-9223372036854775808)",
+    );
+    test_int_obj(test_eval(format!("-{}", max)), -9223372036854775807);
+}
+
 #[test]
 fn test_ifelse() {
     let tests = vec![
@@ -103,6 +205,79 @@ fn test_ifelse() {
     }
 }
 
+#[test]
+fn test_string_literal() {
+    let tests = vec![
+        (r#""hello world""#, "hello world"),
+        (r#""tab\there""#, "tab\there"),
+        (r#""say \"hi\"" + "\u{21}""#, "say \"hi\"!"),
+    ];
+    for (input, expected) in tests {
+        match test_eval(input).as_ref() {
+            object::TheObject::Stringobj(s) => assert_eq!(s, expected),
+            obj => panic!("not Stringobj ... but is {:#?}", obj),
+        }
+    }
+}
+
+#[test]
+fn test_interpolated_string() {
+    let tests = vec![
+        (
+            r#"let user = {"name": "Ann"}; "Hello ${user["name"]}, you have ${1 + 2} new messages""#,
+            "Hello Ann, you have 3 new messages",
+        ),
+        (
+            r#"let user = {"name": "Ann"}; let items = [1, 2]; "Hello ${user["name"]}, you have ${len(items)} items""#,
+            "Hello Ann, you have 2 items",
+        ),
+        (r#""${"a" + "${"b"}"}${true}""#, "abtrue"),
+        (r#"let f = |x| "<${x}>"; f("${f(1.5)}")"#, "<<1.5>>"),
+        (r#""\${x} costs $5""#, "${x} costs $5"),
+        (
+            r#"let xs = [1, "a", [2.5]]; "xs = ${xs}""#,
+            r#"xs = [1, "a", [2.5]]"#,
+        ),
+        (
+            r#""${{"b": [true], "a": 1}} ${{}} ${[]}""#,
+            r#"{"a": 1, "b": [true]} {} []"#,
+        ),
+        (r#"let m = {}; m[2] = "two"; "${m}""#, r#"{2: "two"}"#),
+    ];
+    for (input, expected) in tests {
+        match test_eval(input).as_ref() {
+            object::TheObject::Stringobj(s) => assert_eq!(s, expected),
+            obj => panic!("not Stringobj ... but is {:#?}", obj),
+        }
+    }
+
+    test_error_obj(test_eval(r#""a ${-true} b""#), "unknown operator: -BOOLEAN");
+}
+
+#[test]
+fn test_builtins() {
+    let tests = vec![
+        (r#"len("")"#, 0),
+        (r#"len("héllo")"#, 5),
+        ("len([1, [2, 3]])", 2),
+        (r#"len({"a": 1, 2: 3})"#, 2),
+        ("let f = len; f([1])", 1),
+        ("let len = fn(x) { 7 }; len([])", 7),
+    ];
+    for (input, expected) in tests {
+        test_int_obj(test_eval(input), expected);
+    }
+
+    test_error_obj(
+        test_eval("len(1)"),
+        "argument to len not supported, got INTEGER",
+    );
+    test_error_obj(
+        test_eval(r#"len("a", "b")"#),
+        "wrong number of arguments: expected 1, got 2",
+    );
+}
+
 // #[test]
 // fn test_error_handler() { //改了报错内容
 //     let tests = vec![
@@ -167,9 +342,9 @@ fn test_func_obj() {
 
     let evaled = test_eval(input);
     match evaled.as_ref() {
-        object::TheObject::Func(ref params, ref body, ref env) => {
+        object::TheObject::Func(ref params, _body, _env) => {
             assert_eq!(1, params.len());
-            assert_eq!("x".to_string(), params[0].clone().unwrap().as_ref().value);
+            assert_eq!("x", params[0].name.value);
             // panic!("body = {:#?}", body);
         }
         _ => panic!("not Func {:#?}", evaled.as_ref()),
@@ -200,3 +375,689 @@ fn test_func_apply() {
         test_int_obj(evaled, expected);
     }
 }
+
+fn test_error_obj(obj: Box<object::TheObject>, expected: &str) {
+    match obj.as_ref().type_of() {
+        object::ERROR_OBJ => assert_eq!(obj.as_ref().inspect(), expected),
+        _ => panic!("not errors, {:?}", obj.as_ref()),
+    }
+}
+
+#[test]
+fn test_loops() {
+    let tests = vec![
+        (
+            "let i = 0; let s = 0; while (i < 5) { i = i + 1; s = s + i; } s",
+            15,
+        ),
+        ("let s = 0; for (x in [1, 2, 3, 4]) { s = s + x; } s", 10),
+        (
+            "let s = 0; for (x in [1, 2, 3, 4]) { if (x == 3) { continue; } s = s + x; } s",
+            7,
+        ),
+        (
+            "let s = 0; for (x in [1, 2, 3, 4]) { if (x > 2) { break; } s = s + x; } s",
+            3,
+        ),
+        (
+            "let n = 0; for (a in [1, 2, 3]) { for (b in [1, 2, 3]) { if (b > a) { break; } n = n + 1; } } n",
+            6,
+        ),
+        (
+            "let f = fn() { let i = 0; while (true) { i = i + 1; if (i == 3) { return i * 10; } } }; f()",
+            30,
+        ),
+        ("let i = 0; while (i < 3) { i += 1; continue; i = 100; } i", 3),
+    ];
+    for (input, expected) in tests {
+        test_int_obj(test_eval(input), expected);
+    }
+
+    match test_eval("let s = \"\"; for (c in \"abc\") { s = c + s; } s").as_ref() {
+        object::TheObject::Stringobj(s) => assert_eq!(s, "cba"),
+        obj => panic!("not Stringobj ... but is {:#?}", obj),
+    }
+    assert_eq!(test_eval("while (false) { 1 }").type_of(), object::NULL_OBJ);
+}
+
+#[test]
+fn test_ranges_and_slices() {
+    let tests = vec![
+        ("[1, 2, 3][-1]", 3),
+        ("[1, 2, 3][-3]", 1),
+        ("let s = 0; for (i in 1..5) { s += i; } s", 10),
+        ("let s = 0; for (i in 1..=5) { s += i; } s", 15),
+        ("let n = 0; for (i in 3..1) { n += 1; } n", 0),
+        ("[10, 20, 30, 40][1..3][1]", 30),
+        ("[10, 20, 30, 40][1:][0]", 20),
+        ("[10, 20, 30, 40][:-1][-1]", 30),
+        ("let a = [1, 2, 3]; a[-1] = 9; a[2]", 9),
+    ];
+    for (input, expected) in tests {
+        test_int_obj(test_eval(input), expected);
+    }
+
+    let tests = vec![
+        (r#""hello"[1:3]"#, "el"),
+        (r#""hello"[2:]"#, "llo"),
+        (r#""hello"[:-2]"#, "hel"),
+        (r#""hello"[-1]"#, "o"),
+        (r#""héllo"[1]"#, "é"),
+        (r#""hello"[5:]"#, ""),
+        (r#""hello"[1..=2]"#, "el"),
+    ];
+    for (input, expected) in tests {
+        match test_eval(input).as_ref() {
+            object::TheObject::Stringobj(s) => assert_eq!(s, expected, "{}", input),
+            obj => panic!("not Stringobj ... but is {:#?}", obj),
+        }
+    }
+    assert_eq!(test_eval("1..=3").inspect(), "1..=3");
+    assert_eq!(test_eval("1..3").inspect(), "1..3");
+    assert_eq!(
+        test_eval("[1, 2, 3][:]").inspect(),
+        test_eval("[1, 2, 3]").inspect()
+    );
+
+    let tests = vec![
+        (
+            "[1, 2, 3][3]",
+            "index out of bounds length of array is 3, but have 3",
+        ),
+        (
+            "[1, 2, 3][-4]",
+            "index out of bounds length of array is 3, but have -4",
+        ),
+        (
+            r#""abc"[3]"#,
+            "index out of bounds length of string is 3, but have 3",
+        ),
+        (
+            "[1, 2, 3][1:4]",
+            "slice bounds out of range [1:4] with length 3",
+        ),
+        (
+            "[1, 2, 3][2:1]",
+            "slice bounds out of range [2:1] with length 3",
+        ),
+        (
+            r#""abc"[-4:]"#,
+            "slice bounds out of range [-4:] with length 3",
+        ),
+        (
+            "[1, 2, 3][0..5]",
+            "slice bounds out of range [0:5] with length 3",
+        ),
+        ("[1][true:]", "slice bounds must be integers, found BOOLEAN"),
+        ("5[1:]", "cannot slice INTEGER"),
+        ("5[0]", "index operator not supported: INTEGER[INTEGER]"),
+        ("1.5..2", "unknown operator: FLOAT .. INTEGER"),
+        ("0..=9223372036854775807", "range end overflows"),
+    ];
+    for (input, expected) in tests {
+        test_error_obj(test_eval(input), expected);
+    }
+}
+
+#[test]
+fn test_loop_errors() {
+    let tests = vec![
+        ("break;", "break outside of a loop"),
+        (
+            "let f = fn() { continue; }; f()",
+            "continue outside of a loop",
+        ),
+        (
+            "while (true) { let f = fn() { break; }; f(); }",
+            "break outside of a loop",
+        ),
+        ("for (x in 5) { x }", "cannot iterate over INTEGER"),
+        ("while (-true) { 1 }", "unknown operator: -BOOLEAN"),
+    ];
+    for (input, expected) in tests {
+        test_error_obj(test_eval(input), expected);
+    }
+}
+
+#[test]
+fn test_assignment() {
+    let tests = vec![
+        ("let a = [1, [2, 3]]; a[1][0] = 30; a[0] + a[1][0] + 1", 32),
+        (
+            "let conf = {\"port\": 8080}; conf[\"port\"] = conf[\"port\"] + 1; conf[\"port\"]",
+            8081,
+        ),
+        ("let a = [1, 2]; let b = a; b[0] = 5; a[0]", 1),
+        (
+            "let m = {\"a\": [1, 2]}; m[\"a\"][1] += 10; m[\"a\"][1]",
+            12,
+        ),
+        ("let m = {}; m[1] = 2; m[1]", 2),
+        // the target's index and object are evaluated once
+        (
+            "let calls = 0; let a = [1, 2]; let f = fn() { calls += 1; 1 }; a[f()] += 5; a[1] * 10 + calls",
+            71,
+        ),
+        (
+            "class C { let n = 1; } let calls = 0; let c = C(); let get = fn() { calls += 1; c };
+             get().n *= 3; c.n * 10 + calls",
+            31,
+        ),
+        (
+            "let count = 0; let inc = fn() { count = count + 1; }; inc(); inc(); count",
+            2,
+        ),
+        (
+            "let fact = fn(n) { if (n < 2) { return 1; } n * fact(n - 1) }; fact(5)",
+            120,
+        ),
+        (
+            "let x = 1; let f = fn() { let x = 2; x = 4; x }; f() + x - 1",
+            4,
+        ),
+    ];
+    for (input, expected) in tests {
+        test_int_obj(test_eval(input), expected);
+    }
+
+    let tests = vec![
+        ("y = 1", "cannot assign to undefined variable y"),
+        (
+            "let a = [1]; a[1] = 2",
+            "index out of bounds length of array is 1, but have 1",
+        ),
+        (
+            "let n = 1; n[0] = 2",
+            "index assignment not supported: INTEGER[INTEGER]",
+        ),
+        ("let m = {}; m[1][2] = 3", "key not found: 1"),
+    ];
+    for (input, expected) in tests {
+        test_error_obj(test_eval(input), expected);
+    }
+}
+
+#[test]
+fn test_classes() {
+    let tests = vec![
+        (
+            "class Point { let x = 1; let y = 2; } let p = Point(); p.x + p.y",
+            3,
+        ),
+        (
+            "class Point { let x = 0; let y = 0;
+                fn init(self, x, y) { self.x = x; self.y = y; }
+                fn norm(self) { self.x * self.x + self.y * self.y }
+            }
+            Point(3, 4).norm()",
+            25,
+        ),
+        (
+            "class Counter { let n = 0; fn inc(self) { self.n += 1; self } }
+            let c = Counter(); let d = c; c.inc().inc(); d.inc(); c.n",
+            3,
+        ),
+        (
+            "class Bag { let items = [1, 2]; } let b = Bag(); b.items[1] = 5; b.items[1]",
+            5,
+        ),
+        (
+            "class A { let a = 1; fn get(self) { self.a } fn twice(self) { self.get() * 2 } }
+            class B < A { let a = 10; fn get(self) { super.get() + 1 } }
+            B().twice()",
+            22,
+        ),
+        (
+            "class A { fn init(self, v) { self.v = v; } }
+            class B < A { fn init(self, v) { super.init(v * 2); } }
+            class C < B {}
+            C(4).v",
+            8,
+        ),
+        (
+            "class A { fn g(self) { 1 } }
+            class B < A { fn g(self) { 2 } fn f(self) { let h = fn() { super.g() }; h() } }
+            B().f()",
+            1,
+        ),
+    ];
+    for (input, expected) in tests {
+        test_int_obj(test_eval(input), expected);
+    }
+
+    match test_eval("class P { let x = 1; fn init(self) { self.y = [2]; } } P()").as_ref() {
+        obj @ object::TheObject::Instance(_) => assert_eq!(
+            obj.inspect(),
+            "P { x: 1, y: [\n    Integer(\n        2,\n    ),\n] }"
+        ),
+        obj => panic!("not Instance ... but is {:#?}", obj),
+    }
+    test_bool_obj(test_eval("class P {} let p = P(); p == p"), true);
+
+    let tests = vec![
+        ("class P {} P().x", "P has no member x"),
+        (
+            "class P {} P(1)",
+            "P has no init method but was given 1 arguments",
+        ),
+        (
+            "let n = 1; class P < n {}",
+            "superclass of P must be a class, not INTEGER",
+        ),
+        (
+            "class A {} class B < A { fn f(self) { super.f() } } B().f()",
+            "A has no method f",
+        ),
+        (
+            "class A { fn f(self) { super.f() } } A().f()",
+            "super outside of a subclass method",
+        ),
+        ("let n = 1; n.x", "cannot access member x of INTEGER"),
+        ("let n = 1; n.x = 2", "cannot assign to member x of INTEGER"),
+        ("class P {} P().xs[0] = 1", "P has no field xs"),
+    ];
+    for (input, expected) in tests {
+        test_error_obj(test_eval(input), expected);
+    }
+}
+
+#[test]
+fn test_match() {
+    let tests = vec![
+        ("match 2 { 1 => 10, 2 => 20, _ => 30 }", 20),
+        ("match 7 { 1 => 10, n => n * 2 }", 14),
+        ("match -1 { -1 => 5, _ => 0 }", 5),
+        ("match 2.0 { 2 => 1, _ => 0 }", 1),
+        ("match \"b\" { \"a\" => 1, \"b\" => 2 }", 2),
+        ("match 5 { n if n > 10 => 1, n if n > 3 => 2, _ => 3 }", 2),
+        ("match [1, 2, 3] { [a, b] => 0, [a, b, c] => a + b + c }", 6),
+        ("match [1, 2, 3, 4] { [first, ...rest] => first + rest[2] }", 5),
+        ("match [] { [x, ...] => x, [...] => 9 }", 9),
+        ("match [1, [2, 3]] { [1, [_, y]] => y }", 3),
+        (
+            "match {\"kind\": \"circle\", \"r\": 2} { {\"kind\": \"square\", \"side\": s} => s, {\"kind\": \"circle\", \"r\": r} => 3 * r * r }",
+            12,
+        ),
+        ("match {1: true} { {1: false} => 0, {1: true} => 1 }", 1),
+        ("let x = 1; match 5 { x => x }; x", 1),
+        (
+            "let total = 0; for (v in [1, \"a\", [2, 3]]) { total += match v { 1 => 1, [a, b] => { a * b } _ => 100 } } total",
+            107,
+        ),
+    ];
+    for (input, expected) in tests {
+        test_int_obj(test_eval(input), expected);
+    }
+
+    let tests = vec![
+        ("match 3 { 1 => 1, 2 => 2 }", "no match arm for INTEGER 3"),
+        (
+            "match [1] { [a, b] => a }",
+            "no match arm for ARRAY [\n    Integer(\n        1,\n    ),\n]",
+        ),
+        ("match 1 { n if -true => n }", "unknown operator: -BOOLEAN"),
+    ];
+    for (input, expected) in tests {
+        test_error_obj(test_eval(input), expected);
+    }
+}
+
+#[test]
+fn test_destructuring_let() {
+    let tests = vec![
+        ("let [a, b] = [1, 2]; a * 10 + b", 12),
+        (
+            "let [head, second, ...tail] = [1, 2, 3, 4]; head + second + tail[1]",
+            7,
+        ),
+        ("let [x, ...rest] = [5]; x", 5),
+        ("let [_, [y, _]] = [1, [2, 3]]; y", 2),
+        (
+            "let person = {\"name\": \"ann\", \"age\": 41}; let {\"age\": a} = person; a + 1",
+            42,
+        ),
+        ("let {1: [p, q]} = {1: [3, 4], 2: 0}; p * q", 12),
+        (
+            "let f = fn(pair) { let [l, r] = pair; l - r }; f([9, 4])",
+            5,
+        ),
+    ];
+    for (input, expected) in tests {
+        test_int_obj(test_eval(input), expected);
+    }
+
+    let tests =
+        vec![
+        (
+            "let [a, b] = [1];",
+            "cannot destructure [a, b]: expected an array of 2 elements, found 1",
+        ),
+        (
+            "let [a, b, ...rest] = [1];",
+            "cannot destructure [a, b, ...rest]: expected an array of at least 2 elements, found 1",
+        ),
+        (
+            "let {\"name\": n} = {\"age\": 1};",
+            "cannot destructure {\"name\": n}: missing key \"name\"",
+        ),
+        ("let [a] = 1;", "cannot destructure [a]: expected ARRAY, found INTEGER"),
+        ("let {1: a} = [1];", "cannot destructure {1: a}: expected MAP, found ARRAY"),
+        ("let [0, x] = [1, 2];", "cannot destructure [0, x]: expected 0, found 1"),
+    ];
+    for (input, expected) in tests {
+        test_error_obj(test_eval(input), expected);
+    }
+
+    // a pattern that does not fit binds none of its names
+    let mut env = evaluator::Environment::new();
+    let mut run = |input: &str| {
+        let program = parser::Parser::new(lexer::Lexer::new(input))
+            .parse_program()
+            .unwrap();
+        eval(&program, &mut env)
+    };
+    run("let x = 1;");
+    assert!(run("let [x, y, 2] = [5, 6, 3];").is_error());
+    test_int_obj(run("x"), 1);
+    assert!(run("y").is_error());
+}
+
+#[test]
+fn test_parameters() {
+    let tests = vec![
+        ("let f = fn(x, y = 10) { x + y }; f(1)", 11),
+        ("let f = fn(x, y = 10) { x + y }; f(1, 2)", 3),
+        ("let f = fn(x, y = x * 2) { x + y }; f(3)", 9),
+        ("let n = 5; let f = fn(x = n) { x }; let n = 7; f()", 7),
+        ("let f = fn(first, ...rest) { first + rest[1] }; f(1, 2, 3)", 4),
+        (
+            "let f = fn(...rest) { let [a, b] = rest; a - b }; f(5, 3)",
+            2,
+        ),
+        ("let f = fn(x, ...rest) { match rest { [] => x } }; f(1)", 1),
+        ("let f = fn(a, b) { a - b }; f(b: 1, a: 5)", 4),
+        ("let f = fn(a, b = 2, c = 3) { a * 100 + b * 10 + c }; f(1, c: 9)", 129),
+        (
+            "class P { fn init(self, x = 1, y = 2) { self.x = x; self.y = y; } } let p = P(y: 5); p.x + p.y",
+            6,
+        ),
+    ];
+    for (input, expected) in tests {
+        test_int_obj(test_eval(input), expected);
+    }
+
+    let tests = vec![
+        (
+            "fn(x) { x }(1, 2)",
+            "wrong number of arguments: expected 1, got 2",
+        ),
+        (
+            "fn(x, y) { x }(1)",
+            "wrong number of arguments: expected 2, got 1",
+        ),
+        (
+            "fn(x, y = 1, z = 2) { x }()",
+            "wrong number of arguments: expected 1 to 3, got 0",
+        ),
+        (
+            "fn(x, ...rest) { x }()",
+            "wrong number of arguments: expected at least 1, got 0",
+        ),
+        (
+            "class P { fn m(self, a) { a } } P().m()",
+            "wrong number of arguments: expected 1, got 0",
+        ),
+        ("fn(x) { x }(1, x: 2)", "got multiple values for argument x"),
+        ("fn(x) { x }(y: 2)", "unexpected named argument y"),
+        ("fn(...xs) { xs }(xs: 2)", "unexpected named argument xs"),
+        ("fn(x = -true) { x }()", "unknown operator: -BOOLEAN"),
+    ];
+    for (input, expected) in tests {
+        test_error_obj(test_eval(input), expected);
+    }
+}
+
+#[test]
+fn test_short_lambdas() {
+    let tests = vec![
+        ("let double = |x| x * 2; double(21)", 42),
+        ("let double = x => x * 2; double(4)", 8),
+        ("let add = |a, b = 10| a + b; add(1)", 11),
+        ("let f = || 7; f()", 7),
+        ("let apply = fn(f, v) { f(v) }; apply(x => x + 1, 1)", 2),
+        ("let adder = x => y => x + y; adder(2)(3)", 5),
+        ("let n = 1; let inc = || n = n + 1; inc(); inc(); n", 3),
+        ("let k = 10; let f = |x| x + k; let k = 20; f(1)", 21),
+        ("match 5 { n if n > 1 => (x => x * n)(2) }", 10),
+    ];
+    for (input, expected) in tests {
+        test_int_obj(test_eval(input), expected);
+    }
+}
+
+// writes `files` under a fresh directory and evaluates `input` as if it
+// were a script in it
+fn test_eval_in(dir: &str, files: &[(&str, &str)], input: &str) -> Box<object::TheObject> {
+    let dir = std::env::temp_dir().join(format!("monkey-{}-{}", std::process::id(), dir));
+    for (path, source) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, source).unwrap();
+    }
+    let mut env = evaluator::Environment::in_dir(&dir);
+    let program = parser::Parser::new(lexer::Lexer::new(input))
+        .parse_program()
+        .unwrap();
+    eval(&program, &mut env)
+}
+
+#[test]
+fn test_imports() {
+    let files = [
+        (
+            "lib/math.mk",
+            "import \"util.mk\" as util; let square = fn(x) { util.twice(x, fn(y) { y * y }) }; let _unit = 1; let pi = 3;",
+        ),
+        ("lib/util.mk", "let twice = fn(x, f) { f(x) / x * x };"),
+        ("counter.mk", "class Counter { let n = 0; } let counter = Counter();"),
+    ];
+    let tests = vec![
+        ("import \"lib/math.mk\" as math; math.square(4)", 16),
+        ("import \"lib/math.mk\" as math; math.pi", 3),
+        ("import \"lib/util.mk\" as u; u.twice(3, fn(y) { y })", 3),
+        (
+            "import \"counter.mk\" as a; import \"./counter.mk\" as b; a.counter.n = 5; b.counter.n",
+            5,
+        ),
+        ("let f = fn() { import \"lib/math.mk\" as m; m.pi }; f()", 3),
+    ];
+    for (input, expected) in tests {
+        test_int_obj(test_eval_in("imports", &files, input), expected);
+    }
+}
+
+#[test]
+fn test_import_errors() {
+    let files = [
+        ("a.mk", "import \"b.mk\" as b; let x = 1;"),
+        ("b.mk", "import \"a.mk\" as a;"),
+        ("lib.mk", "let _hidden = 1; let shown = 2;"),
+        ("bad.mk", "let = 1;"),
+        ("fails.mk", "let x = 1 / 0;"),
+        ("ill-typed.mk", "let n: int = \"five\";"),
+    ];
+    let tests = vec![
+        (
+            "import \"a.mk\" as a;",
+            "import cycle: \"a.mk\" -> \"b.mk\" -> \"a.mk\"",
+        ),
+        (
+            "import \"lib.mk\" as l; l._hidden",
+            "_hidden is private to module \"lib.mk\"",
+        ),
+        (
+            "import \"lib.mk\" as l; l.nope",
+            "module \"lib.mk\" has no export nope",
+        ),
+        (
+            "import \"lib.mk\" as l; l.shown = 3",
+            "cannot assign to member shown of MODULE",
+        ),
+        ("import \"fails.mk\" as f;", "division by zero"),
+        (
+            "import \"ill-typed.mk\" as m;",
+            "in \"ill-typed.mk\": 1:14: expected int, found str",
+        ),
+    ];
+    for (input, expected) in tests {
+        test_error_obj(test_eval_in("import-errors", &files, input), expected);
+    }
+    for input in ["import \"missing.mk\" as m;", "import \"bad.mk\" as m;"] {
+        let evaled = test_eval_in("import-errors", &files, input);
+        assert!(evaled.is_error(), "{:?}", evaled);
+    }
+}
+
+#[test]
+fn test_eval_file() {
+    let dir = std::env::temp_dir().join(format!("monkey-{}-eval-file", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let main = dir.join("main.mk");
+
+    std::fs::write(&main, "let n = 5; n * 2;").unwrap();
+    assert!(!evaluator::eval_file(&main).is_error());
+
+    // the main file is type checked before any of it runs
+    std::fs::write(&main, "let n: int = 5; n = \"ten\";").unwrap();
+    test_error_obj(
+        evaluator::eval_file(&main),
+        &format!(
+            "in {:?}: 1:21: expected int, found str",
+            main.to_string_lossy()
+        ),
+    );
+}
+
+fn test_expand(input: &str) -> Result<ast::ASTNode, String> {
+    let mut program = parser::Parser::new(lexer::Lexer::new(input))
+        .parse_program()
+        .unwrap();
+    let mut macros = evaluator::Environment::new();
+    expand_macros(&mut program, &mut macros)?;
+    Ok(program)
+}
+
+fn test_eval_expanded(input: &str) -> Box<object::TheObject> {
+    let program = test_expand(input).unwrap();
+    eval(&program, &mut evaluator::Environment::new())
+}
+
+#[test]
+fn test_quote_unquote() {
+    let tests = vec![
+        ("quote(5)", "QUOTE(5)"),
+        ("quote(foobar + barfoo)", "QUOTE(foobar + barfoo)"),
+        ("quote(unquote(4 + 4))", "QUOTE(8)"),
+        ("quote(8 + unquote(4 + 4))", "QUOTE(8 + 8)"),
+        (
+            "let q = quote(4 + 4); quote(unquote(4 + 4) * unquote(q))",
+            "QUOTE(8 * (4 + 4))",
+        ),
+        (
+            "quote(unquote(true) == unquote(1.5 < 1))",
+            "QUOTE(true == false)",
+        ),
+        ("quote(unquote(\"a\" + \"b\")[0])", "QUOTE(\"ab\"[0])"),
+        ("quote(unquote([1, [quote(x)]]))", "QUOTE([1, [x]])"),
+        ("let x = 2; quote(unquote(x) + x)", "QUOTE(2 + x)"),
+    ];
+    for (input, expected) in tests {
+        assert_eq!(test_eval(input).inspect(), expected, "{}", input);
+    }
+    test_error_obj(
+        test_eval("quote(unquote(fn() {}))"),
+        "cannot unquote FUNCTION",
+    );
+    test_error_obj(
+        test_eval("quote(unquote(-true))"),
+        "unknown operator: -BOOLEAN",
+    );
+}
+
+#[test]
+fn test_macros() {
+    let unless = "let unless = macro(cond, then, otherwise) {
+        quote(if (!(unquote(cond))) { unquote(then) } else { unquote(otherwise) })
+    };";
+    let expanded = test_expand(&format!("{} unless(10 > 5, a, b);", unless)).unwrap();
+    assert_eq!(expanded.to_string(), "if (!(10 > 5)) { a; } else { b; };");
+
+    let tests = vec![
+        (format!("{} unless(10 > 5, 1, 2)", unless), 2),
+        (format!("unless(false, 1, 2); {}", unless), 1),
+        (
+            "let inc = macro(e) { quote(unquote(e) + 1) };
+            let inc2 = macro(e) { quote(inc(inc(unquote(e)))) };
+            inc2(inc(1))"
+                .to_string(),
+            4,
+        ),
+        (
+            "let sq = macro(e, by = quote(2)) { quote(unquote(e) * unquote(by)) }; sq(3) + sq(3, by: 3)"
+                .to_string(),
+            15,
+        ),
+        // the macro's `x` cannot capture the caller's
+        (
+            "let add1 = macro(e) { quote(fn(x) { unquote(e) + x }(1)) }; let x = 100; add1(x)"
+                .to_string(),
+            101,
+        ),
+        (
+            "let m = macro(e) { quote(match unquote(e) { [n] => n, n => n }) }; let n = 7; m([n])"
+                .to_string(),
+            7,
+        ),
+        // a name is renamed only within its binder's scope
+        (
+            "let m = macro(a) { quote((fn(x) { x + unquote(a) })(x)) }; let x = 10; m(x)"
+                .to_string(),
+            20,
+        ),
+        (
+            "let m = macro(e) { quote(fn(x, y = x) { x * 10 + y }(unquote(e), y: 2)) }; m(3)"
+                .to_string(),
+            32,
+        ),
+        (
+            "let m = macro() { quote(fn() { class C { let n = 1; fn get(self) { self.n } } let n = C(); n.n + n.get() }()) }; let n = 5; m() + n"
+                .to_string(),
+            7,
+        ),
+    ];
+    for (input, expected) in tests {
+        test_int_obj(test_eval_expanded(&input), expected);
+    }
+
+    let errors = vec![
+        (
+            "let m = macro() { 1 }; m()",
+            "macro m must return a quote, found INTEGER",
+        ),
+        (
+            "let m = macro(a) { quote(a) }; m()",
+            "in macro m: wrong number of arguments: expected 1, got 0",
+        ),
+        (
+            "let m = macro() { quote(m()) }; m()",
+            "macro m expands more than 64 deep",
+        ),
+    ];
+    for (input, expected) in errors {
+        assert_eq!(test_expand(input).unwrap_err(), expected, "{}", input);
+    }
+    test_error_obj(
+        test_eval_expanded("let f = fn() { macro() { 1 } }; f()"),
+        "a macro must be bound by a top-level let",
+    );
+}
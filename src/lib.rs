@@ -1,42 +1,38 @@
+#![allow(
+    clippy::module_inception,
+    clippy::borrowed_box,
+    clippy::vec_box,
+    clippy::needless_return,
+    clippy::redundant_field_names,
+    clippy::new_without_default,
+    clippy::should_implement_trait,
+    clippy::result_large_err
+)]
 #[macro_use]
 extern crate lazy_static;
 pub mod ast;
+pub mod checker;
+pub mod cst;
 pub mod evaluator;
 pub mod mylexer;
 pub mod object;
 pub mod parser;
 pub mod repl;
 pub mod token;
+pub mod typeck;
 
-use std::{error, io};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum FullError {
     #[error("parse int error")]
     IntErr(#[from] std::num::ParseIntError),
-    #[error("parse group error")]
-    GroupErr,
-    #[error("parse if error")]
-    IfErr,
-    #[error("parse func error")]
-    FuncErr,
-    #[error("parse Index error")]
-    IndexErr,
-    #[error("parse Hash error")]
-    HashErr,
-    #[error("parse Assign error")]
-    AssignErr,
-    #[error("parse let error")]
-    LetErr,
-    #[error("parse return error")]
-    RetErr,
-    #[error("parse expr error")]
-    ExpressionErr,
-    #[error("Unexpected EOF")]
-    EOF,
-    #[error("Miss ;")]
-    MissSem,
+    #[error("parse float error")]
+    FloatErr(#[from] std::num::ParseFloatError),
+    #[error("{0}")]
+    Syntax(#[from] parser::diagnostic::ParseErrors),
+    #[error("{0}")]
+    Type(#[from] checker::checker::TypeErrors),
     #[error("unknown data store error")]
     Unknown,
 }
@@ -1,87 +1,198 @@
-use crate::token::token::{lookup_ident, Token, TokenType::*};
+use crate::token::token::{lookup_ident, Position, Span, Symbol, Token, TokenType, TokenType::*};
 use std::fmt::Write;
 use std::iter::Iterator;
 use std::iter::Peekable;
 use std::str::CharIndices;
+use unicode_xid::UnicodeXID;
 
 const CHAR0: char = 0 as char;
 
 #[derive(Debug)]
 pub struct Lexer<'a> {
+    source: &'a str,
     input: Peekable<CharIndices<'a>>,
     position: usize,
     ch: char,
+    // position of the next unread character
+    cursor: Position,
+    doc_comments: bool,
+    trivia: bool,
+    // for each open ${ in a string, the number of { inside it not yet closed
+    interpolations: Vec<usize>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new<'b>(input: &'b str) -> Lexer<'b> {
         Lexer {
+            source: input,
             input: input.char_indices().peekable(),
             position: 0,
             ch: CHAR0,
+            cursor: Position::start(),
+            doc_comments: false,
+            trivia: false,
+            interpolations: vec![],
         }
     }
 
-    pub fn next_token(&mut self) -> Option<Token> {
-        let mut tok: Token;
+    /// Emit `///` comments as `DocComment` tokens instead of skipping them.
+    pub fn with_doc_comments(mut self) -> Self {
+        self.doc_comments = true;
+        self
+    }
 
-        self.skip_whitespace();
+    /// Emit whitespace and comments as `WHITESPACE` and `COMMENT` tokens,
+    /// so that the tokens cover every byte of the input.
+    pub fn with_trivia(mut self) -> Self {
+        self.trivia = true;
+        self
+    }
 
-        match self.next_char()? {
-            '=' => {
-                if self.peek_char()? == '=' {
-                    self.next_char();
-                    tok = Token::new(EQ, "==");
-                } else {
-                    tok = Token::new(ASSIGN, self.ch)
-                }
+    pub fn next_token(&mut self) -> Option<Token> {
+        loop {
+            let start = self.cursor;
+            self.skip_whitespace();
+            if self.trivia && self.cursor != start {
+                return Some(self.trivia_token(WHITESPACE, start));
             }
-            '!' => {
-                if self.peek_char()? == '=' {
-                    self.next_char();
-                    tok = Token::new(NotEQ, "!=");
-                } else {
-                    tok = Token::new(BANG, self.ch)
+
+            let start = self.cursor;
+            let tok = match self.next_char()? {
+                '=' => match self.peek_char() {
+                    Some('>') => self.either('>', ARROW, ASSIGN),
+                    _ => self.either('=', EQ, ASSIGN),
+                },
+                '!' => {
+                    if self.peek_char() == Some('=') {
+                        self.next_char();
+                        Token::new(NotEQ, "!=")
+                    } else {
+                        Token::new(BANG, self.ch)
+                    }
                 }
-            }
-            '(' => tok = Token::new(LPAREN, self.ch),
-            ')' => tok = Token::new(RPAREN, self.ch),
-            '{' => tok = Token::new(LBRACE, self.ch),
-            '}' => tok = Token::new(RBRACE, self.ch),
-            '+' => tok = Token::new(PLUS, self.ch),
-            '-' => tok = Token::new(MINUS, self.ch),
-            '*' => tok = Token::new(ASTERISK, self.ch),
-            '/' => tok = Token::new(SLASH, self.ch),
-            '<' => tok = Token::new(LT, self.ch),
-            '>' => tok = Token::new(GT, self.ch),
-            ',' => tok = Token::new(COMMA, self.ch),
-            ';' => tok = Token::new(SEMICOLON, self.ch),
-            '"' => tok = Token::new(Str, self.read_string(None)),
-            '[' => tok = Token::new(LBRACKET, self.ch),
-            ']' => tok = Token::new(RBRACKET, self.ch),
-            ':' => tok = Token::new(COLON, self.ch),
-            ch if is_letter(ch) => {
-                let lit = self.read_identifier(Some(ch));
-                tok = Token::new(lookup_ident(&lit), lit);
-            }
-            ch if is_digit(ch) => {
-                tok = Token::new(INT, self.read_number(Some(ch)));
-            }
-            CHAR0 => tok = Token::new(EOF, CHAR0),
-            _ => {
-                tok = Token::new(ILLEGAL, self.ch);
-            }
+                '(' => Token::new(LPAREN, self.ch),
+                ')' => Token::new(RPAREN, self.ch),
+                '{' => {
+                    if let Some(depth) = self.interpolations.last_mut() {
+                        *depth += 1;
+                    }
+                    Token::new(LBRACE, self.ch)
+                }
+                '}' => match self.interpolations.last_mut() {
+                    Some(0) => self.string_token(true),
+                    Some(depth) => {
+                        *depth -= 1;
+                        Token::new(RBRACE, self.ch)
+                    }
+                    None => Token::new(RBRACE, self.ch),
+                },
+                '+' => self.either('=', PlusASSIGN, PLUS),
+                '-' => match self.peek_char() {
+                    Some('>') => self.either('>', RARROW, MINUS),
+                    _ => self.either('=', MinusASSIGN, MINUS),
+                },
+                '*' => self.either('=', AsteriskASSIGN, ASTERISK),
+                '%' => Token::new(PERCENT, self.ch),
+                '&' => self.either('&', AND, ILLEGAL),
+                '|' => self.either('|', OR, PIPE),
+                '/' => match self.peek_char() {
+                    Some('/') => {
+                        self.next_char();
+                        // `///` is a doc comment, but `////` is a plain one
+                        let is_doc = self.peek_char() == Some('/') && self.peek_nth(1) != Some('/');
+                        if self.doc_comments && is_doc {
+                            self.next_char();
+                            let text = self.read_line_comment();
+                            Token::new(DocComment, text.strip_prefix(' ').unwrap_or(&text))
+                        } else {
+                            self.read_line_comment();
+                            if self.trivia {
+                                return Some(self.trivia_token(COMMENT, start));
+                            }
+                            continue;
+                        }
+                    }
+                    Some('*') => {
+                        self.next_char();
+                        if self.skip_block_comment() {
+                            if self.trivia {
+                                return Some(self.trivia_token(COMMENT, start));
+                            }
+                            continue;
+                        }
+                        Token::new(ILLEGAL, "unterminated block comment")
+                    }
+                    _ => self.either('=', SlashASSIGN, SLASH),
+                },
+                '<' => self.either('=', LtEQ, LT),
+                '>' => self.either('=', GtEQ, GT),
+                ',' => Token::new(COMMA, self.ch),
+                ';' => Token::new(SEMICOLON, self.ch),
+                '"' => self.string_token(false),
+                '[' => Token::new(LBRACKET, self.ch),
+                ']' => Token::new(RBRACKET, self.ch),
+                ':' => Token::new(COLON, self.ch),
+                '.' => match (self.peek_char(), self.peek_nth(1)) {
+                    (Some('.'), Some('.')) => {
+                        self.next_char();
+                        self.next_char();
+                        Token::new(ELLIPSIS, "...")
+                    }
+                    (Some('.'), Some('=')) => {
+                        self.next_char();
+                        self.next_char();
+                        Token::new(DOTDOTEQ, "..=")
+                    }
+                    _ => self.either('.', DOTDOT, DOT),
+                },
+                ch if is_letter(ch) => {
+                    let lit = Symbol::intern(&self.read_identifier(Some(ch)));
+                    Token::new(lookup_ident(lit), lit)
+                }
+                ch if is_digit(ch) => self.read_number(ch),
+                CHAR0 => Token::new(EOF, CHAR0),
+                _ => Token::new(ILLEGAL, self.ch),
+            };
+            return Some(tok.with_span(Span::new(start, self.cursor)));
+        }
+    }
+
+    // trivia tokens keep their source text as the literal
+    fn trivia_token(&self, ty: TokenType, start: Position) -> Token {
+        let span = Span::new(start, self.cursor);
+        Token::new(ty, span.slice(self.source)).with_span(span)
+    }
+
+    // a two-character token if `second` follows the current character,
+    // otherwise the single-character one
+    fn either(&mut self, second: char, double: TokenType, single: TokenType) -> Token {
+        if self.peek_char() == Some(second) {
+            let first = self.ch;
+            self.next_char();
+            Token::new(double, format!("{}{}", first, second))
+        } else {
+            Token::new(single, self.ch)
         }
-        Some(tok)
     }
 
     fn next_char(&mut self) -> Option<char> {
         (self.position, self.ch) = self.input.next()?;
+        self.cursor.offset = self.position + self.ch.len_utf8();
+        if self.ch == '\n' {
+            self.cursor.line += 1;
+            self.cursor.column = 1;
+        } else {
+            self.cursor.column += 1;
+        }
         Some(self.ch)
     }
     fn peek_char(&mut self) -> Option<char> {
         self.input.peek().map(|(_, c)| *c)
     }
+    // peek_nth(0) is peek_char
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.input.clone().nth(n).map(|(_, c)| c)
+    }
 
     fn skip_whitespace(&mut self) {
         while let Some(ch) = self.peek_char() {
@@ -110,16 +221,166 @@ impl<'a> Lexer<'a> {
         id
     }
 
+    // the comment text up to, but not including, the end of the line
+    fn read_line_comment(&mut self) -> String {
+        self.read_(None, |c| c != '\n')
+    }
+
+    // the opening /* has been consumed; block comments nest, and false is
+    // returned if the input ends before the comment is closed
+    fn skip_block_comment(&mut self) -> bool {
+        let mut depth = 1;
+        while let Some(ch) = self.next_char() {
+            match ch {
+                '*' if self.peek_char() == Some('/') => {
+                    self.next_char();
+                    depth -= 1;
+                    if depth == 0 {
+                        return true;
+                    }
+                }
+                '/' if self.peek_char() == Some('*') => {
+                    self.next_char();
+                    depth += 1;
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
     fn read_identifier(&mut self, first_char: Option<char>) -> String {
         self.read_(first_char, is_var_name)
     }
 
-    fn read_string(&mut self, first_char: Option<char>) -> String {
-        self.read_(first_char, |c| c == '"')
+    // "a ${x} b" is StrHead("a "), the tokens of x, then StrTail(" b");
+    // `resumed` is true after the } that closes an interpolation
+    fn string_token(&mut self, resumed: bool) -> Token {
+        let (lit, interpolates) = self.read_string();
+        let ty = match (resumed, interpolates) {
+            (false, false) => Str,
+            (false, true) => StrHead,
+            (true, true) => StrMid,
+            (true, false) => StrTail,
+        };
+        if !resumed && interpolates {
+            self.interpolations.push(0);
+        } else if resumed && !interpolates {
+            self.interpolations.pop();
+        }
+        match lit {
+            Ok(lit) => Token::new(ty, lit),
+            Err(msg) => Token::new(ILLEGAL, msg),
+        }
     }
 
-    fn read_number(&mut self, first_char: Option<char>) -> String {
-        self.read_(first_char, is_digit)
+    // the opening " has been consumed; reads up to the closing " or the
+    // next ${ and returns the unescaped text, or a diagnostic for an
+    // unterminated string or the first bad escape, along with whether an
+    // interpolation starts
+    fn read_string(&mut self) -> (Result<String, String>, bool) {
+        let mut lit = String::with_capacity(8);
+        let mut bad_escape = None;
+        let interpolates = loop {
+            match self.next_char() {
+                None => return (Err("unterminated string".to_string()), false),
+                Some('"') => break false,
+                Some('$') if self.peek_char() == Some('{') => {
+                    self.next_char();
+                    break true;
+                }
+                Some('\\') => match self.read_escape() {
+                    Ok(ch) => lit.push(ch),
+                    Err(msg) => {
+                        bad_escape.get_or_insert(msg);
+                    }
+                },
+                Some(ch) => lit.push(ch),
+            }
+        };
+        match bad_escape {
+            Some(msg) => (Err(msg), interpolates),
+            None => (Ok(lit), interpolates),
+        }
+    }
+
+    // the \ has been consumed
+    fn read_escape(&mut self) -> Result<char, String> {
+        match self.next_char() {
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('r') => Ok('\r'),
+            Some('\\') => Ok('\\'),
+            Some('"') => Ok('"'),
+            Some('$') => Ok('$'),
+            Some('u') => self.read_unicode_escape(),
+            Some(ch) => Err(format!("unknown escape \\{}", ch)),
+            None => Err("unterminated string".to_string()),
+        }
+    }
+
+    // \u{1F600}: one to six hex digits naming a unicode scalar value
+    fn read_unicode_escape(&mut self) -> Result<char, String> {
+        if self.peek_char() != Some('{') {
+            return Err("expected { after \\u".to_string());
+        }
+        self.next_char();
+        let digits = self.read_(None, |c| c.is_ascii_hexdigit());
+        if self.peek_char() != Some('}') {
+            return Err("expected } to close \\u{...}".to_string());
+        }
+        self.next_char();
+        if digits.is_empty() || digits.len() > 6 {
+            return Err(format!("invalid unicode escape \\u{{{}}}", digits));
+        }
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("invalid unicode escape \\u{{{}}}", digits))
+    }
+
+    // 0x1F, 0o17, 0b1010, 1_000, 3.14, 2e10, 3.14e-2
+    // the literal keeps its prefix and `_` separators, the parser strips them
+    fn read_number(&mut self, first_char: char) -> Token {
+        let radix = match (first_char, self.peek_char()) {
+            ('0', Some('x')) => 16,
+            ('0', Some('o')) => 8,
+            ('0', Some('b')) => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            let prefix = self.next_char().unwrap_or_default();
+            let digits = self.read_(None, is_var_name);
+            let literal = format!("0{}{}", prefix, digits);
+            if digits.chars().all(|c| c == '_') {
+                return Token::new(ILLEGAL, format!("missing digits after 0{}", prefix));
+            }
+            return match digits.chars().find(|c| *c != '_' && !c.is_digit(radix)) {
+                Some(c) => Token::new(ILLEGAL, format!("invalid digit {:?} in {}", c, literal)),
+                None => Token::new(INT, literal),
+            };
+        }
+
+        let mut ty = INT;
+        let mut literal = self.read_(Some(first_char), is_digit_or_separator);
+        // `1.` and `1.foo` are not floats, a digit must follow the dot
+        if self.peek_char() == Some('.') && self.peek_nth(1).is_some_and(is_digit) {
+            ty = FLOAT;
+            literal.push(self.next_char().unwrap_or_default());
+            literal += &self.read_(None, is_digit_or_separator);
+        }
+        if let Some('e') | Some('E') = self.peek_char() {
+            let signed = matches!(self.peek_nth(1), Some('+') | Some('-'));
+            let digit_at = if signed { 2 } else { 1 };
+            if self.peek_nth(digit_at).is_some_and(is_digit) {
+                ty = FLOAT;
+                for _ in 0..digit_at {
+                    literal.push(self.next_char().unwrap_or_default());
+                }
+                literal += &self.read_(None, is_digit_or_separator);
+            }
+        }
+        Token::new(ty, literal)
     }
 }
 
@@ -130,27 +391,26 @@ impl<'a> Iterator for Lexer<'a> {
     }
 }
 
+// identifiers follow Unicode UAX #31: XID_Start (or `_`) then XID_Continue
 fn is_letter(ch: char) -> bool {
-    match ch {
-        'a'..='z' | 'A'..='Z' | '_' => true,
-        _ => false,
-    }
+    ch == '_' || UnicodeXID::is_xid_start(ch)
 }
 
 fn is_digit(ch: char) -> bool {
-    '0' <= ch && ch <= '9'
+    ch.is_ascii_digit()
+}
+
+fn is_digit_or_separator(ch: char) -> bool {
+    is_digit(ch) || ch == '_'
 }
 
 fn is_var_name(ch: char) -> bool {
-    is_letter(ch) || is_digit(ch)
+    UnicodeXID::is_xid_continue(ch)
 }
 
 #[cfg(test)]
 mod test_lexer {
-    use crate::{
-        mylexer::lexer,
-        token::token::{Token, TokenType},
-    };
+    use crate::token::token::{Token, TokenType};
 
     use super::Lexer;
 
@@ -160,16 +420,16 @@ mod test_lexer {
         let s = "let a = 10";
         let mut lexer = Lexer::new(s);
 
-        let mut a = Token::new(Let, "let");
+        let a = Token::new(Let, "let");
         assert_eq!(Some(a), lexer.next_token());
 
-        let mut a = Token::new(IDENT, 'a');
+        let a = Token::new(IDENT, 'a');
         assert_eq!(Some(a), lexer.next_token());
 
-        let mut a = Token::new(ASSIGN, '=');
+        let a = Token::new(ASSIGN, '=');
         assert_eq!(Some(a), lexer.next_token());
 
-        let mut a = Token::new(INT, "10");
+        let a = Token::new(INT, "10");
 
         assert_eq!(Some(a), lexer.next_token());
 
@@ -180,7 +440,7 @@ mod test_lexer {
     fn test_peek() {
         use TokenType::*;
         let s = "let a = 10;";
-        let mut lexer = Lexer::new(s);
+        let lexer = Lexer::new(s);
         let mut lexer = lexer.peekable();
 
         let a = Token::new(Let, "let");
@@ -198,4 +458,254 @@ mod test_lexer {
         lexer.next();
         assert_eq!(None, lexer.peek());
     }
+
+    #[test]
+    fn test_span() {
+        use crate::token::token::{Position, Span};
+        let s = "let a\n  == 10;";
+        let tokens: Vec<Token> = Lexer::new(s).collect();
+
+        assert_eq!(
+            tokens[0].span,
+            Span::new(Position::new(0, 1, 1), Position::new(3, 1, 4))
+        );
+        assert_eq!(
+            tokens[2].span,
+            Span::new(Position::new(8, 2, 3), Position::new(10, 2, 5))
+        );
+        for tok in &tokens {
+            assert_eq!(tok.span.slice(s), tok.literal);
+        }
+    }
+
+    #[test]
+    fn test_literal_interning() {
+        use crate::token::token::Literal;
+        // only names are interned
+        let tokens: Vec<Token> = Lexer::new("let x = \"text\" + 12;").collect();
+        let interned: Vec<bool> = tokens
+            .iter()
+            .map(|t| matches!(t.literal, Literal::Name(_)))
+            .collect();
+        assert_eq!(interned, [true, true, false, false, false, false, false]);
+    }
+
+    #[test]
+    fn test_string() {
+        use TokenType::*;
+        let tests = vec![
+            (r#""hello""#, Token::new(Str, "hello")),
+            (r#""""#, Token::new(Str, "")),
+            (r#""a\tb\nc""#, Token::new(Str, "a\tb\nc")),
+            (r#""\\ \"q\"""#, Token::new(Str, "\\ \"q\"")),
+            (r#""\u{48}\u{1F600}""#, Token::new(Str, "H\u{1F600}")),
+            ("\"two\nlines\"", Token::new(Str, "two\nlines")),
+            (r#""open"#, Token::new(ILLEGAL, "unterminated string")),
+            (r#""bad\q""#, Token::new(ILLEGAL, "unknown escape \\q")),
+            (
+                r#""\u{110000}""#,
+                Token::new(ILLEGAL, "invalid unicode escape \\u{110000}"),
+            ),
+            (
+                r#""\u{}""#,
+                Token::new(ILLEGAL, "invalid unicode escape \\u{}"),
+            ),
+            (r#""\u41""#, Token::new(ILLEGAL, "expected { after \\u")),
+            (
+                r#""\u{41""#,
+                Token::new(ILLEGAL, "expected } to close \\u{...}"),
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(Some(expected), Lexer::new(input).next_token(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_interpolation() {
+        use TokenType::*;
+        let s = r#""a ${x + "b ${y}"} c ${ {1: 2}[1] }\${d}""#;
+        let tokens: Vec<Token> = Lexer::new(s).collect();
+        let found: Vec<_> = tokens.iter().map(|t| (t.ty, t.literal.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![
+                (StrHead, "a "),
+                (IDENT, "x"),
+                (PLUS, "+"),
+                (StrHead, "b "),
+                (IDENT, "y"),
+                (StrTail, ""),
+                (StrMid, " c "),
+                (LBRACE, "{"),
+                (INT, "1"),
+                (COLON, ":"),
+                (INT, "2"),
+                (RBRACE, "}"),
+                (LBRACKET, "["),
+                (INT, "1"),
+                (RBRACKET, "]"),
+                (StrTail, "${d}"),
+            ]
+        );
+        assert_eq!(tokens[6].span.slice(s), "} c ${");
+
+        let types: Vec<_> = Lexer::new("\"${x").map(|t| t.ty).collect();
+        assert_eq!(types, vec![StrHead, IDENT]);
+    }
+
+    #[test]
+    fn test_string_resync() {
+        use TokenType::*;
+        let s = "\"a\\qb\" + \"c\nd\";";
+        let tokens: Vec<Token> = Lexer::new(s).collect();
+        let types: Vec<_> = tokens.iter().map(|t| t.ty).collect();
+        assert_eq!(types, vec![ILLEGAL, PLUS, Str, SEMICOLON]);
+        assert_eq!(tokens[0].span.slice(s), "\"a\\qb\"");
+        assert_eq!(tokens[2].span.end.line, 2);
+    }
+
+    #[test]
+    fn test_comments() {
+        use TokenType::*;
+        let s = "let a = 1; // trailing\n/* block /* nested */ still */ a / 2 //// four\n";
+        let types: Vec<_> = Lexer::new(s).map(|t| t.ty).collect();
+        assert_eq!(
+            types,
+            vec![Let, IDENT, ASSIGN, INT, SEMICOLON, IDENT, SLASH, INT]
+        );
+
+        let tokens: Vec<Token> = Lexer::new("1 /* open /* */").collect();
+        assert_eq!(tokens[1], Token::new(ILLEGAL, "unterminated block comment"));
+        assert_eq!(tokens[1].span.slice("1 /* open /* */"), "/* open /* */");
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        use TokenType::*;
+        let s = "/// adds one\n///\n//// not doc\nlet inc";
+
+        let types: Vec<_> = Lexer::new(s).map(|t| t.ty).collect();
+        assert_eq!(types, vec![Let, IDENT]);
+
+        let tokens: Vec<Token> = Lexer::new(s).with_doc_comments().collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(DocComment, "adds one"),
+                Token::new(DocComment, ""),
+                Token::new(Let, "let"),
+                Token::new(IDENT, "inc"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_numbers() {
+        use TokenType::*;
+        let tests = vec![
+            ("0", Token::new(INT, "0")),
+            ("1_000_000", Token::new(INT, "1_000_000")),
+            ("0x1F", Token::new(INT, "0x1F")),
+            ("0o17", Token::new(INT, "0o17")),
+            ("0b1010_0101", Token::new(INT, "0b1010_0101")),
+            ("3.14", Token::new(FLOAT, "3.14")),
+            ("3.14e-2", Token::new(FLOAT, "3.14e-2")),
+            ("2E10", Token::new(FLOAT, "2E10")),
+            ("1e+3", Token::new(FLOAT, "1e+3")),
+            ("0x", Token::new(ILLEGAL, "missing digits after 0x")),
+            ("0b102", Token::new(ILLEGAL, "invalid digit '2' in 0b102")),
+            ("0o8", Token::new(ILLEGAL, "invalid digit '8' in 0o8")),
+        ];
+        for (input, expected) in tests {
+            let tokens: Vec<Token> = Lexer::new(input).collect();
+            assert_eq!(vec![expected], tokens, "{}", input);
+        }
+
+        // a dot or exponent without digits after it is not part of the number
+        let types: Vec<_> = Lexer::new("1.x 2e").map(|t| t.ty).collect();
+        assert_eq!(types, vec![INT, DOT, IDENT, INT, IDENT]);
+        let types: Vec<_> = Lexer::new("1..2 1.5..=-2").map(|t| t.ty).collect();
+        assert_eq!(types, vec![INT, DOTDOT, INT, FLOAT, DOTDOTEQ, MINUS, INT]);
+    }
+
+    #[test]
+    fn test_operators() {
+        use TokenType::*;
+        let s = "<= >= < > % && || += -= *= /= / & | . .. ... ..= => = == ->";
+        let tokens: Vec<Token> = Lexer::new(s).collect();
+        let types: Vec<_> = tokens.iter().map(|t| t.ty).collect();
+        assert_eq!(
+            types,
+            vec![
+                LtEQ,
+                GtEQ,
+                LT,
+                GT,
+                PERCENT,
+                AND,
+                OR,
+                PlusASSIGN,
+                MinusASSIGN,
+                AsteriskASSIGN,
+                SlashASSIGN,
+                SLASH,
+                ILLEGAL,
+                PIPE,
+                DOT,
+                DOTDOT,
+                ELLIPSIS,
+                DOTDOTEQ,
+                ARROW,
+                ASSIGN,
+                EQ,
+                RARROW
+            ]
+        );
+        for tok in &tokens {
+            assert_eq!(tok.span.slice(s), tok.literal);
+        }
+    }
+
+    #[test]
+    fn test_trivia() {
+        use TokenType::*;
+        let s = "let a = 1; // one\n  /* two */ a\n";
+        let tokens: Vec<Token> = Lexer::new(s).with_trivia().collect();
+        let types: Vec<_> = tokens.iter().map(|t| t.ty).collect();
+        assert_eq!(
+            types,
+            vec![
+                Let, WHITESPACE, IDENT, WHITESPACE, ASSIGN, WHITESPACE, INT, SEMICOLON, WHITESPACE,
+                COMMENT, WHITESPACE, COMMENT, WHITESPACE, IDENT, WHITESPACE
+            ]
+        );
+        let text: String = tokens.iter().map(|t| t.span.slice(s)).collect();
+        assert_eq!(text, s);
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        use TokenType::*;
+        let s = "let 变量 = café_2;\n  naïve + λ ¬";
+        let tokens: Vec<Token> = Lexer::new(s).collect();
+        let expected = vec![
+            (Let, "let", (1, 1), (1, 4)),
+            (IDENT, "变量", (1, 5), (1, 7)),
+            (ASSIGN, "=", (1, 8), (1, 9)),
+            (IDENT, "café_2", (1, 10), (1, 16)),
+            (SEMICOLON, ";", (1, 16), (1, 17)),
+            (IDENT, "naïve", (2, 3), (2, 8)),
+            (PLUS, "+", (2, 9), (2, 10)),
+            (IDENT, "λ", (2, 11), (2, 12)),
+            (ILLEGAL, "¬", (2, 13), (2, 14)),
+        ];
+        assert_eq!(tokens.len(), expected.len());
+        for (tok, (ty, lit, start, end)) in tokens.iter().zip(expected) {
+            assert_eq!((tok.ty, tok.literal.as_str()), (ty, lit));
+            assert_eq!((tok.span.start.line, tok.span.start.column), start);
+            assert_eq!((tok.span.end.line, tok.span.end.column), end);
+            assert_eq!(tok.span.slice(s), lit);
+        }
+    }
 }
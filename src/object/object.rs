@@ -1,37 +1,127 @@
 use crate::ast::ast;
 use crate::evaluator::evaluator::Environment;
+use crate::token::token::Symbol;
 
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 
 type ObjectType = &'static str;
 
 pub const INTEGER_OBJ: ObjectType = "INTEGER";
+pub const FLOAT_OBJ: ObjectType = "FLOAT";
 pub const BOOLEAN_OBJ: ObjectType = "BOOLEAN";
 pub const NULL_OBJ: ObjectType = "NULL";
 pub const RETURN_VALUE_OBJ: ObjectType = "RETURN_VALUE";
+pub const BREAK_OBJ: ObjectType = "BREAK";
+pub const CONTINUE_OBJ: ObjectType = "CONTINUE";
 pub const ERROR_OBJ: ObjectType = "ERROR";
 pub const FUNCTION_OBJ: ObjectType = "FUNCTION";
 pub const STRING_OBJ: ObjectType = "STRING";
 pub const ARRAY_OBJ: ObjectType = "ARRAY";
 pub const MAP_OBJ: ObjectType = "MAP";
+pub const RANGE_OBJ: ObjectType = "RANGE";
+pub const CLASS_OBJ: ObjectType = "CLASS";
+pub const INSTANCE_OBJ: ObjectType = "INSTANCE";
+pub const SUPER_OBJ: ObjectType = "SUPER";
+pub const MODULE_OBJ: ObjectType = "MODULE";
+pub const QUOTE_OBJ: ObjectType = "QUOTE";
+pub const MACRO_OBJ: ObjectType = "MACRO";
+pub const BUILTIN_OBJ: ObjectType = "BUILTIN";
 
+// Quote holds a whole node; objects are boxed anyway
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum TheObject {
     Integer(i64),
+    Float(f64),
     Boolean(bool),
     ReturnValue(Box<TheObject>),
+    Break,    // unwinds to the innermost loop
+    Continue, // like Break, then starts the next iteration
     Errors(String),
-    Func(
-        Vec<Option<Box<ast::Identifier>>>,
-        Box<ast::ASTNode>,
-        Environment,
-    ),
+    Func(Vec<ast::Parameter>, Box<ast::ASTNode>, Environment),
     Stringobj(String),
     Array(Vec<Box<TheObject>>),
-    Map(HashMap<String, Box<TheObject>>),
+    Map(HashMap<String, (Box<TheObject>, Box<TheObject>)>), // hash key => (key, value)
+    Range(i64, i64, bool), // start..end, or start..=end when inclusive
+    Class(Rc<Class>),
+    Instance(Instance),
+    // a method looked up on an instance: (receiver, Func, class defining it)
+    BoundMethod(Box<TheObject>, Box<TheObject>, Rc<Class>),
+    // what `super` means inside a method: (superclass, receiver)
+    Super(Rc<Class>, Box<TheObject>),
+    Module(Rc<Module>),
+    Quote(ast::ASTNode), // unevaluated syntax, made by quote(..)
+    Macro(Vec<ast::Parameter>, Box<ast::ASTNode>, Environment),
+    Builtin(Symbol), // a function of the interpreter's, such as len
     NULL,
 }
 
+/// A class declaration, evaluated.
+#[derive(Debug, PartialEq)]
+pub struct Class {
+    pub name: Symbol,
+    pub superclass: Option<Rc<Class>>,
+    pub fields: Vec<ast::LetStatement>, // evaluated in `env` for each instance
+    pub methods: HashMap<Symbol, Box<TheObject>>,
+    pub env: Environment,
+}
+
+impl Class {
+    /// The method `name` of this class or its nearest ancestor that has
+    /// one, along with that class.
+    pub fn find_method(self: &Rc<Self>, name: Symbol) -> Option<(Box<TheObject>, Rc<Class>)> {
+        match self.methods.get(&name) {
+            Some(method) => Some((method.clone(), self.clone())),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+}
+
+/// An imported file. Its top-level bindings not starting with `_` are
+/// its exports.
+#[derive(Debug, PartialEq)]
+pub struct Module {
+    pub path: String, // as written in the import
+    pub env: Environment,
+}
+
+/// An object made by calling a class. Clones share their fields.
+#[derive(Clone)]
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: Rc<RefCell<HashMap<Symbol, Box<TheObject>>>>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Self {
+        Instance {
+            class,
+            fields: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+}
+
+// instances are equal only to themselves
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.fields, &other.fields)
+    }
+}
+
+// the address stands in for the fields, which may refer back to the instance
+impl std::fmt::Debug for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} instance at {:p}",
+            self.class.name,
+            Rc::as_ptr(&self.fields)
+        )
+    }
+}
+
 impl TheObject {
     pub fn default() -> Self {
         TheObject::NULL
@@ -40,13 +130,25 @@ impl TheObject {
         use self::TheObject::*;
         match self {
             Integer(_) => INTEGER_OBJ,
+            Float(_) => FLOAT_OBJ,
             Boolean(_) => BOOLEAN_OBJ,
             ReturnValue(_) => RETURN_VALUE_OBJ,
+            Break => BREAK_OBJ,
+            Continue => CONTINUE_OBJ,
             Errors(_) => ERROR_OBJ,
             Func(_, _, _) => FUNCTION_OBJ,
             Stringobj(_) => STRING_OBJ,
             Array(_) => ARRAY_OBJ,
             Map(_) => MAP_OBJ,
+            Range(_, _, _) => RANGE_OBJ,
+            Class(_) => CLASS_OBJ,
+            Instance(_) => INSTANCE_OBJ,
+            BoundMethod(_, _, _) => FUNCTION_OBJ,
+            Super(_, _) => SUPER_OBJ,
+            Module(_) => MODULE_OBJ,
+            Quote(_) => QUOTE_OBJ,
+            Macro(_, _, _) => MACRO_OBJ,
+            Builtin(_) => BUILTIN_OBJ,
             NULL => NULL_OBJ,
         }
     }
@@ -55,14 +157,71 @@ impl TheObject {
         use self::TheObject::*;
         match self {
             Integer(i) => format!("{}", *i),
+            Float(f) => format!("{:?}", *f),
             Boolean(i) => format!("{}", *i),
-            ReturnValue(i) => format!("{}", i.as_ref().inspect()),
+            ReturnValue(i) => i.as_ref().inspect().to_string(),
+            Break => "break".to_string(),
+            Continue => "continue".to_string(),
             Errors(i) => i.clone(),
             Func(_, _, _) => "".to_string(), //format!("env = {:#?} fn({:#?}){}\n{:#?}\n{} ",env,ident, "{",block,"}"),
             Stringobj(ref s) => s.clone(),
             Array(ref s) => format!("{:#?}", s),
             Map(ref s) => format!("{:#?}", s),
-            NULL => format!("()"),
+            Range(start, end, false) => format!("{}..{}", start, end),
+            Range(start, end, true) => format!("{}..={}", start, end),
+            Class(ref c) => format!("class {}", c.name),
+            Instance(ref i) => {
+                let fields = i.fields.borrow();
+                let mut names: Vec<&Symbol> = fields.keys().collect();
+                names.sort_by_key(|name| name.as_str());
+                let fields: Vec<String> = names
+                    .iter()
+                    .map(|name| format!("{}: {}", name, fields[*name].inspect()))
+                    .collect();
+                format!("{} {{ {} }}", i.class.name, fields.join(", "))
+            }
+            BoundMethod(_, _, _) => "".to_string(),
+            Super(ref c, _) => format!("super {}", c.name),
+            Module(ref m) => format!("module {:?}", m.path),
+            Quote(ref node) => format!("QUOTE({})", node),
+            Macro(_, _, _) => "macro".to_string(),
+            Builtin(name) => format!("builtin {}", name),
+            NULL => "()".to_string(),
+        }
+    }
+
+    /// The object as a user sees it, as in string interpolation: a string
+    /// is its text, and arrays and maps are written like their literals.
+    pub fn display(&self) -> String {
+        match self {
+            TheObject::Stringobj(s) => s.clone(),
+            other => other.literal(),
+        }
+    }
+
+    // like display, but with strings quoted, for the elements of an array
+    // or a map
+    fn literal(&self) -> String {
+        use self::TheObject::*;
+        match self {
+            Stringobj(s) => format!("{:?}", s),
+            Array(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.literal()).collect();
+                format!("[{}]", items.join(", "))
+            }
+            Map(map) => {
+                let mut pairs: Vec<(String, String)> = map
+                    .values()
+                    .map(|(key, value)| (key.literal(), value.literal()))
+                    .collect();
+                pairs.sort();
+                let pairs: Vec<String> = pairs
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect();
+                format!("{{{}}}", pairs.join(", "))
+            }
+            other => other.inspect(),
         }
     }
 
@@ -0,0 +1,112 @@
+use crate::ast::ast::ASTNode;
+use crate::token::token::{Span, Token, TokenType};
+
+/// One syntax error: what the parser wanted, what it got and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub expected: Vec<TokenType>, // empty when no single token would have helped
+    pub found: Token,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn new<S: Into<String>>(message: S, found: Token) -> Self {
+        Diagnostic {
+            message: message.into(),
+            expected: vec![],
+            span: found.span,
+            found,
+        }
+    }
+
+    /// `found` is not one of the `expected` tokens.
+    pub fn unexpected(expected: &[TokenType], found: Token) -> Self {
+        let wanted: Vec<String> = expected.iter().map(|ty| format!("`{}`", ty)).collect();
+        let wanted = match wanted.len() {
+            1 => wanted[0].clone(),
+            _ => format!("one of {}", wanted.join(", ")),
+        };
+        let mut diagnostic = Diagnostic::new(
+            format!("expected {}, found {}", wanted, describe(&found)),
+            found,
+        );
+        diagnostic.expected = expected.to_vec();
+        diagnostic.lexer_message();
+        diagnostic
+    }
+
+    /// `found` cannot start an expression.
+    pub fn expected_expression(expected: Vec<TokenType>, found: Token) -> Self {
+        let mut diagnostic = Diagnostic::new(
+            format!("expected expression, found {}", describe(&found)),
+            found,
+        );
+        diagnostic.expected = expected;
+        diagnostic.lexer_message();
+        diagnostic
+    }
+
+    /// `found` cannot start a pattern.
+    pub fn expected_pattern(found: Token) -> Self {
+        let mut diagnostic = Diagnostic::new(
+            format!("expected pattern, found {}", describe(&found)),
+            found,
+        );
+        diagnostic.lexer_message();
+        diagnostic
+    }
+
+    /// `found` cannot start a type.
+    pub fn expected_type(found: Token) -> Self {
+        let mut diagnostic =
+            Diagnostic::new(format!("expected type, found {}", describe(&found)), found);
+        diagnostic.lexer_message();
+        diagnostic
+    }
+
+    // an ILLEGAL token already says what is wrong with it
+    fn lexer_message(&mut self) {
+        if self.found.is_ty(TokenType::ILLEGAL) {
+            self.message = self.found.literal.to_string();
+        }
+    }
+}
+
+fn describe(token: &Token) -> String {
+    use TokenType::*;
+    match token.ty {
+        EOF => "end of input".to_string(),
+        IDENT | INT | FLOAT => format!("`{}`", token.literal),
+        Str => format!("{:?}", token.literal.as_str()),
+        ty => format!("`{}`", ty),
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.span.start, self.message)
+    }
+}
+
+/// Every syntax error in a program, along with the statements that did
+/// parse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseErrors {
+    pub program: Box<ASTNode>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl std::fmt::Display for ParseErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseErrors {}
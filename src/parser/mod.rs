@@ -1,2 +1,4 @@
+pub mod diagnostic;
 pub mod parser;
-pub mod test;
+#[cfg(test)]
+mod test;
@@ -1,23 +1,11 @@
 use crate::ast::ast;
 use crate::ast::ast::ASTNode;
 use crate::mylexer::lexer;
-use crate::token::token::{Token, TokenType, TokenType::*};
-use crate::FullError::*;
-use crate::{FullError, Result};
+use crate::parser::diagnostic::{Diagnostic, ParseErrors};
+use crate::token::precedence::{get_precedence, Pri};
+use crate::token::token::{Span, Token, TokenType, TokenType::*};
 use std::collections::HashMap;
 use std::iter::Peekable;
-#[repr(u8)]
-pub enum Pri {
-    LOWEST,
-    ASSIGN,      // 赋值
-    EQUALS,      // ==, !=
-    LESSGREATER, // < ,>
-    SUM,         //+,-
-    PRODUCT,     //*,/
-    PREFIX,      // !,-
-    CALL,        // func()
-    INDEX,       // array[0], map[0]
-}
 
 macro_rules! get_pri {
     ($x:ident) => {
@@ -25,36 +13,37 @@ macro_rules! get_pri {
     };
 }
 
-lazy_static! {
-    static ref HASHMAP: HashMap<TokenType, u8> = {
-        let mut mmap = HashMap::new();
-        mmap.insert(EQ, get_pri!(EQUALS));
-        mmap.insert(NotEQ, get_pri!(EQUALS));
-        mmap.insert(LT, get_pri!(LESSGREATER));
-        mmap.insert(GT, get_pri!(LESSGREATER));
-        mmap.insert(PLUS, get_pri!(SUM));
-        mmap.insert(MINUS, get_pri!(LESSGREATER));
-        mmap.insert(SLASH, get_pri!(PRODUCT));
-        mmap.insert(ASTERISK, get_pri!(PRODUCT));
-        mmap.insert(LPAREN, get_pri!(CALL));
-        mmap.insert(LBRACKET, get_pri!(INDEX));
-        mmap.insert(ASSIGN, get_pri!(ASSIGN));
-        mmap
-    };
-}
+type Result<T> = std::result::Result<T, Diagnostic>;
+// a call's positional and named arguments
+type CallArguments = (Vec<Box<ASTNode>>, Vec<(ast::Identifier, Box<ASTNode>)>);
 
-fn get_precedence(t: TokenType) -> u8 {
-    HASHMAP.get(&t).map(|t| *t).unwrap_or(get_pri!(LOWEST))
-}
-
-#[derive(Debug)]
 pub struct Parser<'a> {
-    l: Peekable<lexer::Lexer<'a>>,
+    l: Peekable<Box<dyn Iterator<Item = Token> + 'a>>,
     cur_token: Token,
+    // a token handed back by error recovery, read before `l`
+    pending: Option<Token>,
+    // doc comments seen since the last statement started
+    doc: Vec<String>,
+    errors: Vec<Diagnostic>,
+    // set while parsing a match guard, where `x =>` ends the guard instead
+    // of starting a function; cleared again inside brackets, which the
+    // guard's `=>` cannot be in
+    in_guard: bool,
+}
+
+impl std::fmt::Debug for Parser<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Parser")
+            .field("cur_token", &self.cur_token)
+            .field("doc", &self.doc)
+            .field("errors", &self.errors)
+            .finish()
+    }
 }
 
 type PrefixFn = fn(&mut Parser) -> Result<ast::ASTNode>;
 type InfixFn = fn(&mut Parser, ast::ASTNode) -> Result<ast::ASTNode>;
+#[derive(Default)]
 pub struct FuncParser {
     prefix_parser_fns: HashMap<TokenType, PrefixFn>,
     infix_parser_fns: HashMap<TokenType, InfixFn>,
@@ -69,39 +58,52 @@ impl FuncParser {
     }
 }
 
-impl Default for FuncParser {
-    fn default() -> Self {
-        Self {
-            prefix_parser_fns: HashMap::new(),
-            infix_parser_fns: HashMap::new(),
-        }
-    }
-}
-
 fn parse_identifier(this: &mut Parser) -> Result<ast::ASTNode> {
-    Ok(ASTNode::Identifier(ast::Identifier {
-        token: this.cur_token.clone(),
-        value: this.cur_token.literal.clone(),
-    }))
+    if this.expect_peek(ARROW) && !this.in_guard {
+        return parse_arrow_lambda(this);
+    }
+    Ok(ASTNode::Identifier(ast::Identifier::new(
+        this.cur_token.clone(),
+        this.cur_token.literal.to_symbol(),
+    )))
 }
 
 fn parse_integer_literal(this: &mut Parser) -> Result<ast::ASTNode> {
-    let cur_token = this.next_token().ok_or(FullError::EOF)?;
-    println!("cur token={:?}", cur_token);
-    let literal = cur_token.literal.parse::<i64>();
-    let mut lit = ast::IntegerLiteral::new(cur_token);
+    let cur_token = this.cur_token.clone();
+    let digits = cur_token.literal.replace('_', "");
+    let literal = match digits.get(..2) {
+        Some("0x") => i64::from_str_radix(&digits[2..], 16),
+        Some("0o") => i64::from_str_radix(&digits[2..], 8),
+        Some("0b") => i64::from_str_radix(&digits[2..], 2),
+        _ => digits.parse::<i64>(),
+    };
+    let mut lit = ast::IntegerLiteral::new(cur_token.clone());
     lit.value = match literal {
         Ok(v) => v,
         Err(e) => {
-            return Err(e.into());
+            return Err(Diagnostic::new(
+                format!("invalid integer literal: {}", e),
+                cur_token,
+            ));
         }
     };
-    println!("here?");
     Ok(ASTNode::IntegerLiteral(lit))
 }
+fn parse_float_literal(this: &mut Parser) -> Result<ast::ASTNode> {
+    let mut lit = ast::FloatLiteral::new(this.cur_token.clone());
+    lit.value = match this.cur_token.literal.replace('_', "").parse::<f64>() {
+        Ok(v) => v,
+        Err(e) => {
+            let msg = format!("invalid float literal: {}", e);
+            return Err(Diagnostic::new(msg, this.cur_token.clone()));
+        }
+    };
+    Ok(ASTNode::FloatLiteral(lit))
+}
+
 fn parse_prefix_expression(this: &mut Parser) -> Result<ast::ASTNode> {
     let mut expression =
-        ast::PrefixExpression::new(this.cur_token.clone(), this.cur_token.literal.clone());
+        ast::PrefixExpression::new(this.cur_token.clone(), this.cur_token.literal.to_string());
 
     this.next_token();
 
@@ -111,7 +113,7 @@ fn parse_prefix_expression(this: &mut Parser) -> Result<ast::ASTNode> {
 }
 fn parse_infix_expression(this: &mut Parser, left: ast::ASTNode) -> Result<ast::ASTNode> {
     let mut expression =
-        ast::InfixExpression::new(this.cur_token.clone(), this.cur_token.literal.clone());
+        ast::InfixExpression::new(this.cur_token.clone(), this.cur_token.literal.to_string());
     expression.left = Box::new(left);
     let p = this.cur_precedence();
     this.next_token();
@@ -128,141 +130,349 @@ fn parse_boolean(this: &mut Parser) -> Result<ast::ASTNode> {
 
 fn parse_group_expression(this: &mut Parser) -> Result<ast::ASTNode> {
     this.next_token();
-    let exp = this.parse_expression(get_pri!(LOWEST))?;
-    if !this.expect_peek(RPAREN) {
-        return Err(crate::FullError::GroupErr);
-    }
+    let exp = this.bracketed(|this| this.parse_expression(get_pri!(LOWEST)))?;
+    this.expect(RPAREN)?;
     Ok(exp)
 }
 
 fn parse_if_expression(this: &mut Parser) -> Result<ast::ASTNode> {
     let mut exp = ast::IfExpression::new(this.cur_token.clone());
 
-    if !this.expect_peek(LPAREN) {
-        return Err(crate::FullError::IfErr);
-    }
-
-    this.next_token();
+    this.expect(LPAREN)?;
+    this.advance()?;
     exp.condition = this.parse_expression(get_pri!(LOWEST))?.into();
 
-    if !this.expect_peek(RPAREN) {
-        return Err(crate::FullError::IfErr);
-    }
-    if !this.expect_peek(LBRACE) {
-        return Err(crate::FullError::IfErr);
-    }
+    this.expect(RPAREN)?;
+    this.expect(LBRACE)?;
     exp.consequence = this.parse_block_statement()?.into();
 
     if this.expect_peek(Else) {
         this.next_token();
-
-        if !this.expect_peek(LBRACE) {
-            return Err(crate::FullError::IfErr);
-        }
+        this.expect(LBRACE)?;
         exp.alternative = this.parse_block_statement()?.into();
     }
     Ok(ASTNode::IfExpression(exp))
 }
 
+// match value { pattern => body, pattern if guard => body }
+// an arm body starting with `{` is a block, and needs no comma after it
+fn parse_match_expression(this: &mut Parser) -> Result<ast::ASTNode> {
+    let mut exp = ast::MatchExpression::new(this.cur_token.clone());
+
+    this.advance()?;
+    exp.value = this.parse_expression(get_pri!(LOWEST))?.into();
+    this.expect(LBRACE)?;
+
+    while !this.expect_peek(RBRACE) {
+        this.advance()?;
+        let pattern = this.parse_pattern()?;
+        let mut guard = ASTNode::None;
+        if this.expect_peek(If) {
+            this.next_token();
+            this.advance()?;
+            let in_guard = std::mem::replace(&mut this.in_guard, true);
+            let parsed = this.parse_expression(get_pri!(LOWEST));
+            this.in_guard = in_guard;
+            guard = parsed?;
+        }
+        this.expect(ARROW)?;
+        let body = match this.advance()?.ty {
+            LBRACE => this.parse_block_statement()?,
+            _ => this.parse_expression(get_pri!(LOWEST))?,
+        };
+        let is_block = matches!(body, ASTNode::BlockStatement(_));
+        exp.arms.push(ast::MatchArm {
+            pattern,
+            guard: Box::new(guard),
+            body: Box::new(body),
+        });
+
+        if this.expect_peek(COMMA) {
+            this.next_token();
+        } else if !is_block && !this.expect_peek(RBRACE) {
+            return Err(this.unexpected(&[COMMA, RBRACE]));
+        }
+    }
+    exp.end = this.advance()?;
+    Ok(ASTNode::MatchExpression(exp))
+}
+
+fn parse_while_expression(this: &mut Parser) -> Result<ast::ASTNode> {
+    let mut exp = ast::WhileExpression::new(this.cur_token.clone());
+
+    this.expect(LPAREN)?;
+    this.advance()?;
+    exp.condition = this.parse_expression(get_pri!(LOWEST))?.into();
+
+    this.expect(RPAREN)?;
+    this.expect(LBRACE)?;
+    exp.body = this.parse_block_statement()?.into();
+    Ok(ASTNode::WhileExpression(exp))
+}
+
+fn parse_for_expression(this: &mut Parser) -> Result<ast::ASTNode> {
+    let mut exp = ast::ForExpression::new(this.cur_token.clone());
+
+    this.expect(LPAREN)?;
+    let id = this.expect(IDENT)?;
+    exp.variable = ast::Identifier::new(id.clone(), id.literal.to_symbol());
+    this.expect(In)?;
+    this.advance()?;
+    exp.iterable = this.parse_expression(get_pri!(LOWEST))?.into();
+
+    this.expect(RPAREN)?;
+    this.expect(LBRACE)?;
+    exp.body = this.parse_block_statement()?.into();
+    Ok(ASTNode::ForExpression(exp))
+}
+
 fn parse_func_literal(this: &mut Parser) -> Result<ast::ASTNode> {
     let mut lit = ast::FuncLiteral::new(this.cur_token.clone());
-    if !this.expect_peek(LPAREN) {
-        return Err(crate::FullError::FuncErr);
-    }
-    lit.params = this.parse_func_params();
+    this.expect(LPAREN)?;
+    lit.params = this.parse_func_params(RPAREN)?;
+    lit.ret = this.parse_return_type()?;
 
-    if !this.expect_peek(LBRACE) {
-        return Err(crate::FullError::FuncErr);
-    }
+    this.expect(LBRACE)?;
     lit.body = Box::new(this.parse_block_statement()?);
     Ok(ASTNode::FuncLiteral(lit))
 }
 
+fn parse_macro_literal(this: &mut Parser) -> Result<ast::ASTNode> {
+    let mut lit = ast::FuncLiteral::new(this.cur_token.clone());
+    this.expect(LPAREN)?;
+    lit.params = this.parse_func_params(RPAREN)?;
+    this.expect(LBRACE)?;
+    lit.body = Box::new(this.parse_block_statement()?);
+    Ok(ASTNode::MacroLiteral(lit))
+}
+
+// x => body
+fn parse_arrow_lambda(this: &mut Parser) -> Result<ast::ASTNode> {
+    let token = this.cur_token.clone();
+    let param = ast::Parameter::new(ast::Identifier::new(
+        token.clone(),
+        token.literal.to_symbol(),
+    ));
+    this.next_token();
+    this.advance()?;
+    parse_lambda_body(this, token, vec![param])
+}
+
+// |a, b| body, and || body for no parameters
+fn parse_pipe_lambda(this: &mut Parser) -> Result<ast::ASTNode> {
+    let token = this.cur_token.clone();
+    let params = match token.ty {
+        OR => vec![],
+        _ => this.parse_func_params(PIPE)?,
+    };
+    this.advance()?;
+    parse_lambda_body(this, token, params)
+}
+
+// a short lambda is a FuncLiteral whose block holds just the body
+// expression, so calling it returns that expression's value
+// cur_token is the first token of the body
+fn parse_lambda_body(
+    this: &mut Parser,
+    token: Token,
+    params: Vec<ast::Parameter>,
+) -> Result<ast::ASTNode> {
+    let mut stmt = ast::ExpressionStatement::new(this.cur_token.clone());
+    stmt.expression = Box::new(this.parse_expression(get_pri!(LOWEST))?);
+    let span = stmt.expression.span();
+
+    let open = Token::new(LBRACE, "{").with_span(Span::new(span.start, span.start));
+    let mut body = ast::BlockStatement::new(open);
+    body.end = Token::new(RBRACE, "}").with_span(Span::new(span.end, span.end));
+    body.statements
+        .push(Box::new(ASTNode::ExpressionStatement(stmt)));
+
+    let mut lit = ast::FuncLiteral::new(Token::new(Function, "fn").with_span(token.span));
+    lit.params = params;
+    lit.body = Box::new(ASTNode::BlockStatement(body));
+    Ok(ASTNode::FuncLiteral(lit))
+}
+
+// object.name
+fn parse_member_expression(this: &mut Parser, object: ast::ASTNode) -> Result<ast::ASTNode> {
+    let mut member = ast::MemberExpression::new(this.cur_token.clone());
+    member.object = Box::new(object);
+    let id = this.expect(IDENT)?;
+    member.name = ast::Identifier::new(id.clone(), id.literal.to_symbol());
+    Ok(ASTNode::MemberExpression(member))
+}
+
+// super only makes sense as super.method
+fn parse_super(this: &mut Parser) -> Result<ast::ASTNode> {
+    if !this.expect_peek(DOT) {
+        return Err(this.unexpected(&[DOT]));
+    }
+    Ok(ASTNode::Super(Some(this.cur_token.clone())))
+}
+
 fn parse_call_expression(this: &mut Parser, func: ast::ASTNode) -> Result<ast::ASTNode> {
     let mut call_expression = ast::CallExpression::new(this.cur_token.clone());
     call_expression.func = Box::new(func);
-    call_expression.args = this.parse_expression_list(RPAREN)?;
+    (call_expression.args, call_expression.named) =
+        this.bracketed(|this| this.parse_call_arguments())?;
+    call_expression.end = this.cur_token.clone();
     Ok(ASTNode::CallExpression(call_expression))
 }
 
 fn parse_string_literal(this: &mut Parser) -> Result<ast::ASTNode> {
     Ok(ast::ASTNode::StringLiteral(
-        this.cur_token.clone(),
-        this.cur_token.literal.clone(),
+        Some(this.cur_token.clone()),
+        this.cur_token.literal.to_string(),
     ))
 }
 
+// "a ${x} b": a StrHead, then each expression followed by a StrMid or
+// the closing StrTail
+fn parse_interpolated_string(this: &mut Parser) -> Result<ast::ASTNode> {
+    let mut parts = vec![Box::new(parse_string_literal(this)?)];
+    loop {
+        this.advance()?;
+        let part = this.bracketed(|this| this.parse_expression(get_pri!(LOWEST)))?;
+        parts.push(Box::new(part));
+        let end = this.expect_peek(StrTail);
+        if !end && !this.expect_peek(StrMid) {
+            return Err(this.unexpected(&[RBRACE]));
+        }
+        this.advance()?;
+        parts.push(Box::new(parse_string_literal(this)?));
+        if end {
+            return Ok(ASTNode::InterpolatedString(parts));
+        }
+    }
+}
+
+// [a, b, c]
 fn parse_array_literal(this: &mut Parser) -> Result<ast::ASTNode> {
+    let cur_token = this.cur_token.clone();
+    let elements = this.bracketed(|this| this.parse_expression_list(RBRACKET))?;
     Ok(ast::ASTNode::ArrayLiteral(
-        this.cur_token.clone(),
-        this.parse_expression_list(RBRACKET)?,
+        Some(cur_token),
+        elements,
+        Some(this.cur_token.clone()),
     ))
 }
 
+// left[index], or the slice left[low:high] where either bound may be left out
 fn parse_index_expression(this: &mut Parser, left: ast::ASTNode) -> Result<ast::ASTNode> {
-    let cur_token = this.cur_token.clone();
+    this.bracketed(|this| parse_index_or_slice(this, left))
+}
 
-    this.next_token();
-    let index = this.parse_expression(get_pri!(LOWEST))?;
+fn parse_index_or_slice(this: &mut Parser, left: ast::ASTNode) -> Result<ast::ASTNode> {
+    let token = this.cur_token.clone();
+    let mut slice = ast::SliceExpression::new(token.clone());
+    slice.left = Box::new(left);
 
+    this.advance()?;
+    if this.cur_token.ty != COLON {
+        let index = this.parse_expression(get_pri!(LOWEST))?;
+        if !this.expect_peek(COLON) {
+            this.expect(RBRACKET)?;
+            return Ok(ast::ASTNode::IndexLiteral(
+                Some(token),
+                slice.left,
+                Box::new(index),
+                Some(this.cur_token.clone()),
+            ));
+        }
+        this.advance()?;
+        slice.low = Box::new(index);
+    }
     if !this.expect_peek(RBRACKET) {
-        return Err(crate::FullError::IndexErr);
+        this.advance()?;
+        slice.high = Box::new(this.parse_expression(get_pri!(LOWEST))?);
     }
-    Ok(ast::ASTNode::IndexLiteral(
-        cur_token,
-        Box::new(left),
-        Box::new(index),
-    ))
+    slice.end = this.expect(RBRACKET)?;
+    Ok(ast::ASTNode::SliceExpression(slice))
 }
 
 fn parse_hash_helper(this: &mut Parser) -> Result<Vec<(Box<ast::ASTNode>, Box<ast::ASTNode>)>> {
     let mut hash = vec![];
     while !this.expect_peek(RBRACE) {
-        this.next_token();
+        this.advance()?;
         let key = this.parse_expression(get_pri!(LOWEST))?;
 
-        if !this.expect_peek(COLON) {
-            return Err(crate::FullError::HashErr);
-        }
-
-        this.next_token();
+        this.expect(COLON)?;
+        this.advance()?;
         let value = this.parse_expression(get_pri!(LOWEST))?;
 
         hash.push((Box::new(key), Box::new(value)));
 
-        if !this.expect_peek(COMMA) && !this.expect_peek(RBRACE) {
-            return Err(crate::FullError::HashErr);
+        // pairs are separated by commas, and a trailing one is allowed
+        if this.expect_peek(COMMA) {
+            this.next_token();
+        } else if !this.expect_peek(RBRACE) {
+            return Err(this.unexpected(&[COMMA, RBRACE]));
         }
     }
-    if !this.expect_peek(RBRACE) {
-        return Err(crate::FullError::HashErr);
-    }
+    this.expect(RBRACE)?;
     Ok(hash)
 }
 
 fn parse_hash_literal(this: &mut Parser) -> Result<ast::ASTNode> {
     let cur_token = this.cur_token.clone();
-    let hash = parse_hash_helper(this)?;
-    Ok(ast::ASTNode::HashLiteral(cur_token, hash))
+    let hash = this.bracketed(parse_hash_helper)?;
+    Ok(ast::ASTNode::HashLiteral(
+        Some(cur_token),
+        hash,
+        Some(this.cur_token.clone()),
+    ))
 }
 
+// x = v, a[i][j] = v
 fn parse_assign_expression(this: &mut Parser, left: ast::ASTNode) -> Result<ast::ASTNode> {
-    let mut letstmt = ast::LetStatement::new();
-    letstmt.token = this.cur_token.clone();
+    let mut assign = ast::AssignExpression::new(this.cur_token.clone());
 
-    match left {
-        ast::ASTNode::Identifier(value) => letstmt.name = value,
-        _ => return Err(crate::FullError::AssignErr),
+    // the innermost left side of an index chain must be a variable or a field
+    let mut root = &left;
+    while let ast::ASTNode::IndexLiteral(_, inner, _, _) = root {
+        root = inner;
+    }
+    if !matches!(
+        root,
+        ast::ASTNode::Identifier(_) | ast::ASTNode::MemberExpression(_)
+    ) {
+        let mut diagnostic = Diagnostic::new(
+            format!("cannot assign to {}", root.kind()),
+            this.cur_token.clone(),
+        );
+        diagnostic.span = left.span().to(this.cur_token.span);
+        return Err(diagnostic);
     }
+    assign.target = Box::new(left);
 
-    this.next_token();
-    letstmt.value = Box::new(this.parse_expression(get_pri!(LOWEST))?);
+    this.advance()?;
+    assign.value = Box::new(this.parse_expression(get_pri!(LOWEST))?);
 
-    if !this.expect_peek(SEMICOLON) {
-        return Err(crate::FullError::AssignErr);
-    }
+    Ok(ast::ASTNode::AssignExpression(assign))
+}
 
-    Ok(ast::ASTNode::LetStatement(letstmt))
+// x += e is x = x + e
+fn parse_compound_assign_expression(this: &mut Parser, left: ast::ASTNode) -> Result<ast::ASTNode> {
+    let op = this.cur_token.clone();
+    let (ty, operator) = match op.ty {
+        PlusASSIGN => (PLUS, "+"),
+        MinusASSIGN => (MINUS, "-"),
+        AsteriskASSIGN => (ASTERISK, "*"),
+        _ => (SLASH, "/"),
+    };
+    let target = left.clone();
+
+    match parse_assign_expression(this, left)? {
+        ast::ASTNode::AssignExpression(mut assign) => {
+            let mut infix =
+                ast::InfixExpression::new(Token::new(ty, operator).with_span(op.span), operator);
+            infix.left = Box::new(target);
+            infix.right = assign.value;
+            assign.value = Box::new(ast::ASTNode::InfixExpression(infix));
+            Ok(ast::ASTNode::AssignExpression(assign))
+        }
+        node => Ok(node),
+    }
 }
 
 impl FuncParser {
@@ -270,15 +480,25 @@ impl FuncParser {
         let mut fp = Self::default();
         fp.insert_prefix(IDENT, parse_identifier);
         fp.insert_prefix(INT, parse_integer_literal);
+        fp.insert_prefix(FLOAT, parse_float_literal);
         fp.insert_prefix(BANG, parse_prefix_expression);
         fp.insert_prefix(MINUS, parse_prefix_expression);
         fp.insert_prefix(True, parse_boolean);
         fp.insert_prefix(False, parse_boolean);
         fp.insert_prefix(LPAREN, parse_group_expression);
         fp.insert_prefix(If, parse_if_expression);
+        fp.insert_prefix(Match, parse_match_expression);
+        fp.insert_prefix(While, parse_while_expression);
+        fp.insert_prefix(For, parse_for_expression);
         fp.insert_prefix(Function, parse_func_literal);
+        fp.insert_prefix(Macro, parse_macro_literal);
+        fp.insert_prefix(PIPE, parse_pipe_lambda);
+        fp.insert_prefix(OR, parse_pipe_lambda);
         fp.insert_prefix(Str, parse_string_literal);
+        fp.insert_prefix(StrHead, parse_interpolated_string);
         fp.insert_prefix(LBRACE, parse_hash_literal);
+        fp.insert_prefix(LBRACKET, parse_array_literal);
+        fp.insert_prefix(Super, parse_super);
 
         fp.insert_infix(PLUS, parse_infix_expression);
         fp.insert_infix(MINUS, parse_infix_expression);
@@ -288,8 +508,20 @@ impl FuncParser {
         fp.insert_infix(NotEQ, parse_infix_expression);
         fp.insert_infix(LT, parse_infix_expression);
         fp.insert_infix(GT, parse_infix_expression);
-        fp.insert_infix(LBRACKET, parse_call_expression);
+        fp.insert_infix(LtEQ, parse_infix_expression);
+        fp.insert_infix(GtEQ, parse_infix_expression);
+        fp.insert_infix(PERCENT, parse_infix_expression);
+        fp.insert_infix(DOTDOT, parse_infix_expression);
+        fp.insert_infix(DOTDOTEQ, parse_infix_expression);
+        fp.insert_infix(AND, parse_infix_expression);
+        fp.insert_infix(OR, parse_infix_expression);
+        fp.insert_infix(LBRACKET, parse_index_expression);
+        fp.insert_infix(DOT, parse_member_expression);
         fp.insert_infix(ASSIGN, parse_assign_expression);
+        fp.insert_infix(PlusASSIGN, parse_compound_assign_expression);
+        fp.insert_infix(MinusASSIGN, parse_compound_assign_expression);
+        fp.insert_infix(AsteriskASSIGN, parse_compound_assign_expression);
+        fp.insert_infix(SlashASSIGN, parse_compound_assign_expression);
         fp.insert_infix(LPAREN, parse_call_expression);
 
         fp
@@ -302,41 +534,198 @@ lazy_static! {
 
 impl<'a> Parser<'a> {
     fn next_token(&mut self) -> Option<Token> {
-        self.l.next()
+        let token = match self.pending.take() {
+            Some(token) => token,
+            None => {
+                self.skip_trivia();
+                self.l.next()?
+            }
+        };
+        self.cur_token = token.clone();
+        Some(token)
     }
     fn peek_token(&mut self) -> Option<&Token> {
+        if self.pending.is_some() {
+            return self.pending.as_ref();
+        }
+        self.skip_trivia();
         self.l.peek()
     }
 
+    // like next_token, but running out of input is an error
+    fn advance(&mut self) -> Result<Token> {
+        match self.next_token() {
+            Some(token) => Ok(token),
+            None => Err(Diagnostic::new("unexpected end of input", self.eof_token())),
+        }
+    }
+
+    // consumes the next token if it is `ty`
+    fn expect(&mut self, ty: TokenType) -> Result<Token> {
+        if !self.expect_peek(ty) {
+            return Err(self.unexpected(&[ty]));
+        }
+        self.advance()
+    }
+
+    // the peeked token is none of `expected`
+    fn unexpected(&mut self, expected: &[TokenType]) -> Diagnostic {
+        let found = match self.peek_token() {
+            Some(token) => token.clone(),
+            None => self.eof_token(),
+        };
+        Diagnostic::unexpected(expected, found)
+    }
+
+    fn eof_token(&self) -> Token {
+        let end = self.cur_token.span.end;
+        Token::new(EOF, "").with_span(Span::new(end, end))
+    }
+
+    // runs `parse` on what is inside brackets
+    fn bracketed<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let in_guard = std::mem::replace(&mut self.in_guard, false);
+        let parsed = parse(self);
+        self.in_guard = in_guard;
+        parsed
+    }
+
+    // trivia and doc comments are kept out of the token stream the grammar sees
+    fn skip_trivia(&mut self) {
+        while let Some(token) = self.l.next_if(|t| t.is_trivia() || t.is_ty(DocComment)) {
+            if token.is_ty(DocComment) {
+                self.doc.push(token.literal.to_string());
+            }
+        }
+    }
+
     pub fn new(l: lexer::Lexer) -> Parser {
+        Parser::from_tokens(l)
+    }
+
+    /// A parser over an already lexed token stream.
+    pub fn from_tokens<I>(tokens: I) -> Parser<'a>
+    where
+        I: IntoIterator<Item = Token>,
+        I::IntoIter: 'a,
+    {
+        let tokens: Box<dyn Iterator<Item = Token> + 'a> = Box::new(tokens.into_iter());
         Parser {
-            l: l.peekable(),
+            l: tokens.peekable(),
             cur_token: Token::default(),
+            pending: None,
+            doc: vec![],
+            errors: vec![],
+            in_guard: false,
         }
     }
 
     //入口函数
-    pub fn parse_program(&mut self) -> Result<ast::ASTNode> {
+    /// Parses the whole input. On syntax errors the parser skips to the
+    /// next `;` or `}` and keeps going, so the error holds every diagnostic
+    /// plus the statements that did parse.
+    pub fn parse_program(&mut self) -> std::result::Result<ast::ASTNode, ParseErrors> {
         let mut program = ast::Program::new();
 
         while let Some(token) = self.next_token() {
-            let stmt = self.parse_statement(token)?;
-            program.statements.push(Box::new(stmt));
+            if token.is_ty(RBRACE) {
+                self.errors.push(Diagnostic::new("unexpected `}`", token));
+                continue;
+            }
+            match self.parse_statement(token) {
+                Ok(stmt) => program.statements.push(Box::new(stmt)),
+                Err(diagnostic) => self.recover(diagnostic),
+            }
+        }
+
+        let program = ast::ASTNode::Program(program);
+        if self.errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(ParseErrors {
+                program: Box::new(program),
+                diagnostics: std::mem::take(&mut self.errors),
+            })
         }
+    }
 
-        Ok(ast::ASTNode::Program(program))
+    // records the error and skips to the end of the broken statement: past
+    // the next `;`, or up to the `}` closing the enclosing block
+    fn recover(&mut self, diagnostic: Diagnostic) {
+        let at_cur = diagnostic.span == self.cur_token.span;
+        self.errors.push(diagnostic);
+        if at_cur {
+            match self.cur_token.ty {
+                SEMICOLON => return,
+                RBRACE => {
+                    self.pending = Some(self.cur_token.clone());
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        // a failing `{` has opened a block, and the statement ends with the
+        // `}` that closes it
+        let opened = self.cur_token.ty == LBRACE;
+        let mut depth = opened as usize;
+        while let Some(peek) = self.peek_token() {
+            match peek.ty {
+                SEMICOLON if depth == 0 => {
+                    self.next_token();
+                    return;
+                }
+                RBRACE if depth == 0 => return,
+                RBRACE if depth == 1 && opened => {
+                    self.next_token();
+                    if self.peek_token().map(|t| t.ty) == Some(SEMICOLON) {
+                        self.next_token();
+                    }
+                    return;
+                }
+                RBRACE => depth -= 1,
+                LBRACE => depth += 1,
+                _ => {}
+            }
+            self.next_token();
+        }
     }
 
     fn parse_statement(&mut self, cur_token: Token) -> Result<ast::ASTNode> {
+        let doc = std::mem::take(&mut self.doc);
         match cur_token.ty {
             Let => {
-                let letstmt = self.parse_letstatement(cur_token)?;
+                let mut letstmt = self.parse_letstatement(cur_token)?;
+                if !doc.is_empty() {
+                    letstmt.doc = Some(doc.join("\n"));
+                }
                 Ok(ASTNode::LetStatement(letstmt))
             }
+            Class => {
+                let class = self.parse_class_statement(cur_token)?;
+                if self.expect_peek(SEMICOLON) {
+                    self.next_token();
+                }
+                Ok(ASTNode::ClassStatement(class))
+            }
+            Import => {
+                let import = self.parse_import_statement(cur_token)?;
+                Ok(ASTNode::ImportStatement(import))
+            }
             Return => {
                 let ret = self.parse_returnstatement(cur_token)?;
                 Ok(ASTNode::ReturnStatement(ret))
             }
+            Break | Continue => {
+                if self.expect_peek(SEMICOLON) {
+                    self.next_token();
+                }
+                if cur_token.is_ty(Break) {
+                    Ok(ASTNode::BreakStatement(Some(cur_token)))
+                } else {
+                    Ok(ASTNode::ContinueStatement(Some(cur_token)))
+                }
+            }
             _ => self.parse_expression_statement(),
         }
     }
@@ -352,28 +741,112 @@ impl<'a> Parser<'a> {
         Ok(ast::ASTNode::ExpressionStatement(stmt))
     }
 
+    // cur_token is the first token of the expression
     fn parse_expression(&mut self, precedence: u8) -> Result<ast::ASTNode> {
-        let first_peek = self.peek_token().ok_or(FullError::EOF)?;
-        if let Some(prefix) = FUNCPARSER.prefix_parser_fns.get(&first_peek.ty) {
+        if let Some(prefix) = FUNCPARSER.prefix_parser_fns.get(&self.cur_token.ty) {
             let mut left_expr = prefix(self)?;
-            println!("not end {:?}", self.l.peek());
             while let Some(peek) = self.peek_token() {
-                if peek.ty == SEMICOLON {
+                if peek.ty == SEMICOLON || precedence >= get_precedence(peek.ty) {
                     break;
                 }
-                let p = peek.ty != SEMICOLON && precedence < get_precedence(peek.ty);
-                if p {
-                    if let Some(infix) = FUNCPARSER.infix_parser_fns.get(&peek.ty) {
-                        left_expr = infix(self, left_expr)?;
-                    } else {
-                        return Ok(left_expr);
-                    }
-                }
+                let infix = match FUNCPARSER.infix_parser_fns.get(&peek.ty) {
+                    Some(infix) => infix,
+                    None => break,
+                };
+                self.next_token();
+                left_expr = infix(self, left_expr)?;
             }
-            return Ok(left_expr);
+            Ok(left_expr)
         } else {
-            return Err(crate::FullError::ExpressionErr);
+            let mut expected: Vec<_> = FUNCPARSER.prefix_parser_fns.keys().copied().collect();
+            expected.sort_by_key(|ty| *ty as u8);
+            Err(Diagnostic::expected_expression(
+                expected,
+                self.cur_token.clone(),
+            ))
+        }
+    }
+
+    // cur_token is the first token of the pattern
+    fn parse_pattern(&mut self) -> Result<ast::Pattern> {
+        let token = self.cur_token.clone();
+        match token.ty {
+            IDENT if token.literal == "_" => Ok(ast::Pattern::Wildcard),
+            IDENT => Ok(ast::Pattern::Binding(ast::Identifier::new(
+                token.clone(),
+                token.literal.to_symbol(),
+            ))),
+            INT | FLOAT | Str | True | False => {
+                Ok(ast::Pattern::Literal(Box::new(self.parse_literal()?)))
+            }
+            MINUS if self.expect_peek(INT) || self.expect_peek(FLOAT) => {
+                let mut negative = ast::PrefixExpression::new(token, "-");
+                self.next_token();
+                negative.right = Box::new(self.parse_literal()?);
+                Ok(ast::Pattern::Literal(Box::new(ASTNode::PrefixExpression(
+                    negative,
+                ))))
+            }
+            LBRACKET => self.parse_array_pattern(),
+            LBRACE => self.parse_hash_pattern(),
+            _ => Err(Diagnostic::expected_pattern(token)),
+        }
+    }
+
+    // a number, string or boolean token on its own
+    fn parse_literal(&mut self) -> Result<ast::ASTNode> {
+        let prefix = FUNCPARSER.prefix_parser_fns[&self.cur_token.ty];
+        prefix(self)
+    }
+
+    // [a, b, ...rest]
+    fn parse_array_pattern(&mut self) -> Result<ast::Pattern> {
+        let mut elements = vec![];
+        let mut rest = None;
+        while !self.expect_peek(RBRACKET) {
+            if self.advance()?.is_ty(ELLIPSIS) {
+                let rest_pattern = if self.expect_peek(IDENT) {
+                    self.next_token();
+                    self.parse_pattern()?
+                } else {
+                    ast::Pattern::Wildcard
+                };
+                rest = Some(Box::new(rest_pattern));
+                break;
+            }
+            elements.push(self.parse_pattern()?);
+
+            if self.expect_peek(COMMA) {
+                self.next_token();
+            } else if !self.expect_peek(RBRACKET) {
+                return Err(self.unexpected(&[COMMA, RBRACKET]));
+            }
         }
+        self.expect(RBRACKET)?;
+        Ok(ast::Pattern::Array(elements, rest))
+    }
+
+    // {"key": pattern}
+    fn parse_hash_pattern(&mut self) -> Result<ast::Pattern> {
+        let mut pairs = vec![];
+        while !self.expect_peek(RBRACE) {
+            let token = self.advance()?;
+            let key = match token.ty {
+                INT | Str | True | False => self.parse_literal()?,
+                _ => return Err(Diagnostic::unexpected(&[Str, INT, True, False], token)),
+            };
+            self.expect(COLON)?;
+            self.advance()?;
+            pairs.push((Box::new(key), self.parse_pattern()?));
+
+            if self.expect_peek(COMMA) {
+                self.next_token();
+            } else if !self.expect_peek(RBRACE) {
+                return Err(self.unexpected(&[COMMA, RBRACE]));
+            }
+        }
+        self.expect(RBRACE)?;
+        Ok(ast::Pattern::Hash(pairs))
     }
 
     fn parse_expression_list(&mut self, end: TokenType) -> Result<Vec<Box<ast::ASTNode>>> {
@@ -383,20 +856,21 @@ impl<'a> Parser<'a> {
             return Ok(list);
         }
 
-        self.next_token();
+        self.advance()?;
         let expr = self.parse_expression(get_pri!(LOWEST))?;
         list.push(Box::new(expr));
 
         while self.expect_peek(COMMA) {
             self.next_token();
-            self.next_token();
+            self.advance()?;
             let expr = self.parse_expression(get_pri!(LOWEST))?;
             list.push(Box::new(expr));
         }
 
         if !self.expect_peek(end) {
-            return Err(crate::FullError::ExpressionErr);
+            return Err(self.unexpected(&[COMMA, end]));
         }
+        self.next_token();
         Ok(list)
     }
     // fn parse_call_args(&mut self)->Vec<Box<ast::ASTNode>>{
@@ -419,81 +893,253 @@ impl<'a> Parser<'a> {
     //     args
     // }
 
-    fn parse_func_params(&mut self) -> Vec<Option<Box<ast::Identifier>>> {
-        let mut ids = vec![];
-        if self.expect_peek(RPAREN) {
-            self.next_token();
-            return ids;
-        }
-        self.next_token();
+    // (a, b = default, ...rest), or |a, b| with `end` as PIPE
+    // cur_token is the opening token, and the closing `end` is consumed
+    fn parse_func_params(&mut self, end: TokenType) -> Result<Vec<ast::Parameter>> {
+        let mut params = vec![];
+        while !self.expect_peek(end) {
+            let token = self.advance()?;
+            let rest = token.is_ty(ELLIPSIS);
+            let id = match token.ty {
+                ELLIPSIS => self.expect(IDENT)?,
+                IDENT => token,
+                _ => return Err(Diagnostic::unexpected(&[IDENT, ELLIPSIS], token)),
+            };
+            let mut param =
+                ast::Parameter::new(ast::Identifier::new(id.clone(), id.literal.to_symbol()));
+            param.rest = rest;
+            param.ty = self.parse_annotation()?;
+            if !rest && self.expect_peek(ASSIGN) {
+                self.next_token();
+                self.advance()?;
+                param.default = Box::new(self.parse_expression(get_pri!(LOWEST))?);
+            }
+            params.push(param);
 
-        let ident = ast::Identifier::new(self.cur_token.clone(), self.cur_token.literal.clone());
+            // nothing may follow a rest parameter
+            if rest {
+                break;
+            }
+            if self.expect_peek(COMMA) {
+                self.next_token();
+            } else if !self.expect_peek(end) {
+                return Err(self.unexpected(&[COMMA, end]));
+            }
+        }
+        self.expect(end)?;
+        Ok(params)
+    }
 
-        ids.push(Some(Box::new(ident)));
+    // an optional `: type` after a name
+    fn parse_annotation(&mut self) -> Result<Option<ast::Type>> {
+        if !self.expect_peek(COLON) {
+            return Ok(None);
+        }
+        self.next_token();
+        self.advance()?;
+        Ok(Some(self.parse_type()?))
+    }
 
-        while self.expect_peek(COMMA) {
-            self.next_token();
-            self.next_token();
+    // an optional `-> type` after a function's parameters
+    fn parse_return_type(&mut self) -> Result<Option<ast::Type>> {
+        if !self.expect_peek(RARROW) {
+            return Ok(None);
+        }
+        self.next_token();
+        self.advance()?;
+        Ok(Some(self.parse_type()?))
+    }
 
-            let ident =
-                ast::Identifier::new(self.cur_token.clone(), self.cur_token.literal.clone());
-            ids.push(Some(Box::new(ident)));
+    // int, [T], {K: V} or fn(T, U) -> R, where `-> R` may be left out
+    // cur_token is the type's first token
+    fn parse_type(&mut self) -> Result<ast::Type> {
+        let token = self.cur_token.clone();
+        match token.ty {
+            IDENT => match token.literal.as_str() {
+                "int" => Ok(ast::Type::Int),
+                "float" => Ok(ast::Type::Float),
+                "bool" => Ok(ast::Type::Bool),
+                "str" => Ok(ast::Type::Str),
+                "range" => Ok(ast::Type::Range),
+                "any" => Ok(ast::Type::Any),
+                name => Err(Diagnostic::new(format!("unknown type `{}`", name), token)),
+            },
+            LBRACKET => {
+                self.advance()?;
+                let element = self.parse_type()?;
+                self.expect(RBRACKET)?;
+                Ok(ast::Type::Array(Box::new(element)))
+            }
+            LBRACE => {
+                self.advance()?;
+                let key = self.parse_type()?;
+                self.expect(COLON)?;
+                self.advance()?;
+                let value = self.parse_type()?;
+                self.expect(RBRACE)?;
+                Ok(ast::Type::Map(Box::new(key), Box::new(value)))
+            }
+            Function => {
+                self.expect(LPAREN)?;
+                let mut params = vec![];
+                while !self.expect_peek(RPAREN) {
+                    self.advance()?;
+                    params.push(self.parse_type()?);
+                    if self.expect_peek(COMMA) {
+                        self.next_token();
+                    } else if !self.expect_peek(RPAREN) {
+                        return Err(self.unexpected(&[COMMA, RPAREN]));
+                    }
+                }
+                self.next_token();
+                let ret = self.parse_return_type()?.unwrap_or(ast::Type::Any);
+                Ok(ast::Type::Func(params, Box::new(ret)))
+            }
+            _ => Err(Diagnostic::expected_type(token)),
         }
+    }
 
-        if !self.expect_peek(RPAREN) {
-            return vec![];
+    // (a, b, name: c)
+    // cur_token is (, and the closing ) is consumed
+    fn parse_call_arguments(&mut self) -> Result<CallArguments> {
+        let mut args = vec![];
+        let mut named = vec![];
+        while !self.expect_peek(RPAREN) {
+            let token = self.advance()?;
+            if token.is_ty(IDENT) && self.expect_peek(COLON) {
+                self.next_token();
+                self.advance()?;
+                let value = self.parse_expression(get_pri!(LOWEST))?;
+                named.push((
+                    ast::Identifier::new(token.clone(), token.literal.to_symbol()),
+                    Box::new(value),
+                ));
+            } else if !named.is_empty() {
+                return Err(Diagnostic::new(
+                    "positional argument after named arguments",
+                    token,
+                ));
+            } else {
+                args.push(Box::new(self.parse_expression(get_pri!(LOWEST))?));
+            }
+
+            if self.expect_peek(COMMA) {
+                self.next_token();
+            } else if !self.expect_peek(RPAREN) {
+                return Err(self.unexpected(&[COMMA, RPAREN]));
+            }
         }
-        ids
+        self.expect(RPAREN)?;
+        Ok((args, named))
     }
 
     // { }
+    // cur_token is {, and the closing } is consumed
     fn parse_block_statement(&mut self) -> Result<ast::ASTNode> {
+        self.bracketed(Parser::parse_block_statements)
+    }
+
+    fn parse_block_statements(&mut self) -> Result<ast::ASTNode> {
         let mut block = ast::BlockStatement::new(self.cur_token.clone());
 
         while let Some(token) = self.next_token() {
-            if !token.is_ty(RBRACE) {
-                break;
+            if token.is_ty(RBRACE) {
+                block.end = token;
+                return Ok(ast::ASTNode::BlockStatement(block));
+            }
+            match self.parse_statement(token) {
+                Ok(val) => block.statements.push(Box::new(val)),
+                Err(diagnostic) => self.recover(diagnostic),
             }
-            let val = self.parse_statement(token)?;
-
-            block.statements.push(Box::new(val));
         }
 
-        Ok(ast::ASTNode::BlockStatement(block))
+        Err(self.unexpected(&[RBRACE]))
     }
 
     // let
     // let name = expr;
+    // let [a, ...rest] = expr; let {"key": name} = expr;
     // cur_token is let
     fn parse_letstatement(&mut self, cur_token: Token) -> Result<ast::LetStatement> {
         let mut stmt = ast::LetStatement::new();
-        if !self.expect_peek(IDENT) {
-            return Err(crate::FullError::LetErr);
-        }
-        let id = self.next_token().ok_or(FullError::EOF)?;
-        let val = id.literal.clone();
-        stmt.name = ast::Identifier {
-            token: id,
-            value: val,
-        };
-
-        if !self.expect_peek(ASSIGN) {
-            return Err(crate::FullError::AssignErr);
+        stmt.token = Some(cur_token);
+        if self.expect_peek(LBRACKET) || self.expect_peek(LBRACE) {
+            self.next_token();
+            stmt.pattern = Some(self.parse_pattern()?);
+        } else {
+            let id = self.expect(IDENT)?;
+            let val = id.literal.to_symbol();
+            stmt.name = ast::Identifier::new(id, val);
+            stmt.ty = self.parse_annotation()?;
         }
-        let _assign = self.next_token();
+
+        self.expect(ASSIGN)?;
+        self.advance()?;
 
         stmt.value = Box::new(self.parse_expression(get_pri!(LOWEST))?);
 
-        if self.expect_peek(SEMICOLON) {
+        self.expect(SEMICOLON)?;
+        Ok(stmt)
+    }
+
+    // class Name < Superclass { let field = value; fn method(self) { body } }
+    // cur_token is class, and the closing } is consumed
+    fn parse_class_statement(&mut self, cur_token: Token) -> Result<ast::ClassStatement> {
+        let mut class = ast::ClassStatement::new(cur_token);
+        let id = self.expect(IDENT)?;
+        class.name = ast::Identifier::new(id.clone(), id.literal.to_symbol());
+        if self.expect_peek(LT) {
             self.next_token();
-            Ok(stmt)
-        } else {
-            Err(crate::FullError::MissSem)
+            let id = self.expect(IDENT)?;
+            class.superclass = Some(ast::Identifier::new(id.clone(), id.literal.to_symbol()));
         }
+        self.expect(LBRACE)?;
+
+        while !self.expect_peek(RBRACE) {
+            let token = self.advance()?;
+            let member = match token.ty {
+                Let if self.expect_peek(IDENT) => {
+                    ASTNode::LetStatement(self.parse_letstatement(token)?)
+                }
+                Let => return Err(self.unexpected(&[IDENT])),
+                Function => {
+                    let mut method = ast::FuncLiteral::new(token);
+                    let id = self.expect(IDENT)?;
+                    method.name = Some(ast::Identifier::new(id.clone(), id.literal.to_symbol()));
+                    self.expect(LPAREN)?;
+                    method.params = self.parse_func_params(RPAREN)?;
+                    if method.params.is_empty() {
+                        return Err(Diagnostic::new(
+                            format!("method {} needs a parameter for the instance", id.literal),
+                            id,
+                        ));
+                    }
+                    method.ret = self.parse_return_type()?;
+                    self.expect(LBRACE)?;
+                    method.body = Box::new(self.parse_block_statement()?);
+                    ASTNode::FuncLiteral(method)
+                }
+                _ => return Err(Diagnostic::unexpected(&[Let, Function, RBRACE], token)),
+            };
+            class.members.push(Box::new(member));
+        }
+        class.end = self.advance()?;
+        Ok(class)
+    }
+
+    fn parse_import_statement(&mut self, cur_token: Token) -> Result<ast::ImportStatement> {
+        let mut import = ast::ImportStatement::new(cur_token);
+        import.path = self.expect(Str)?.literal.to_string();
+        self.expect(As)?;
+        let id = self.expect(IDENT)?;
+        import.alias = ast::Identifier::new(id.clone(), id.literal.to_symbol());
+        import.end = self.expect(SEMICOLON)?;
+        Ok(import)
     }
 
     fn parse_returnstatement(&mut self, cur_token: Token) -> Result<ast::ReturnStatement> {
         let mut restmt = ast::ReturnStatement::new(cur_token);
+        self.advance()?;
 
         restmt.return_value = Box::new(self.parse_expression(get_pri!(LOWEST))?);
 
@@ -515,9 +1161,9 @@ impl<'a> Parser<'a> {
 #[cfg(test)]
 mod parser {
     use crate::{
-        ast::ast::{ASTNode, Identifier, IntegerLiteral, LetStatement},
-        mylexer::lexer::{self, Lexer},
-        token::token::Token,
+        ast::ast::{ASTNode, Identifier, IntegerLiteral, LetStatement, Program},
+        mylexer::lexer::Lexer,
+        token::token::{Position, Span, Token, TokenType},
     };
 
     use super::Parser;
@@ -530,14 +1176,39 @@ mod parser {
         let ast = p.parse_program().unwrap();
 
         let mut letstat = LetStatement::new();
-        let mut id = Identifier::default();
-        id.value = "a".to_string();
-        letstat.name = id;
+        letstat.token = Some(Token::new(TokenType::Let, "let"));
+        letstat.name = Identifier::new(Token::new(TokenType::IDENT, "a"), "a".into());
 
-        let mut num = IntegerLiteral::new(Token::default());
+        let mut num = IntegerLiteral::new(Token::new(TokenType::INT, "10"));
         num.value = 10;
-        letstat.value = Box::new(crate::ast::ast::ASTNode::IntegerLiteral(num));
+        letstat.value = Box::new(ASTNode::IntegerLiteral(num));
+
+        let mut program = Program::new();
+        program
+            .statements
+            .push(Box::new(ASTNode::LetStatement(letstat)));
+        assert_eq!(ast, ASTNode::Program(program));
+    }
+
+    #[test]
+    fn test_span() {
+        let input = "let a = 10;\nadd(a,\n  neg(2));";
+        let mut p = Parser::new(Lexer::new(input));
+        let program = match p.parse_program().unwrap() {
+            ASTNode::Program(program) => program,
+            node => panic!("not a program: {:?}", node),
+        };
+
+        let span = program.statements[0].span();
+        assert_eq!(span.start, Position::new(0, 1, 1));
+        assert_eq!(span.end, Position::new(10, 1, 11));
+        assert_eq!(span.slice(input), "let a = 10");
+
+        let call = program.statements[1].span();
+        assert_eq!(call.slice(input), "add(a,\n  neg(2))");
+        assert_eq!(call.end, Position::new(28, 3, 10));
 
-        assert_eq!(ast, ASTNode::LetStatement(letstat));
+        let whole = ASTNode::Program(program).span();
+        assert_eq!(whole, Span::new(span.start, call.end));
     }
 }
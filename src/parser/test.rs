@@ -1,20 +1,18 @@
 use crate::ast::ast;
 use crate::mylexer::lexer;
-use crate::token::token;
-use std::any::Any;
 // use crate::ast::ast::Node;
 use super::parser;
 
 #[test]
 fn test_let_statment() {
-    let tests = vec![
+    let tests = [
         ("let x =10;", "x"),
         ("let y =10;", "y"),
         ("let foobar =10;", "foobar"),
     ];
 
-    for (i, (input, expected)) in tests.iter().enumerate() {
-        let l = lexer::Lexer::new(*input);
+    for (input, expected) in tests.iter() {
+        let l = lexer::Lexer::new(input);
         let mut p = parser::Parser::new(l);
         let program = p.parse_program().unwrap();
 
@@ -33,6 +31,44 @@ fn test_let_statment() {
     }
 }
 
+#[test]
+fn test_type_annotations() {
+    let statements = parse(
+        "let n: int = 5; fn(a: int, c: {str: fn(int) -> bool} = {}, ...b: [str]) -> [float] { a }",
+    );
+    match statements[0].as_ref() {
+        ast::ASTNode::LetStatement(stmt) => assert_eq!(stmt.ty, Some(ast::Type::Int)),
+        other => panic!("not ast::LetStatement, has {:#?}", other),
+    }
+    match expression(&statements[1]) {
+        ast::ASTNode::FuncLiteral(func) => {
+            let types: Vec<String> = func
+                .params
+                .iter()
+                .map(|p| p.ty.as_ref().unwrap().to_string())
+                .collect();
+            assert_eq!(types, ["int", "{str: fn(int) -> bool}", "[str]"]);
+            assert_eq!(func.ret.as_ref().unwrap().to_string(), "[float]");
+        }
+        other => panic!("not ast::FuncLiteral, has {:#?}", other),
+    }
+
+    let tests = [
+        ("let n: number = 1;", "1:8: unknown type `number`"),
+        ("let n: 5 = 1;", "1:8: expected type, found `5`"),
+        (
+            "fn(f: fn(int int)) {}",
+            "1:14: expected one of `,`, `)`, found `int`",
+        ),
+        ("fn() -> {}", "1:10: expected type, found `}`"),
+    ];
+    for (input, expected) in tests.iter() {
+        let mut p = parser::Parser::new(lexer::Lexer::new(input));
+        let errors = p.parse_program().unwrap_err();
+        assert_eq!(errors.diagnostics[0].to_string(), *expected, "{}", input);
+    }
+}
+
 #[test]
 fn test_function() {
     let input = "fn(x,y,z){z+y+x+2;}; ";
@@ -45,7 +81,7 @@ fn test_function() {
             match program.statements[0].as_ref() {
                 ast::ASTNode::ExpressionStatement(ref stmt) => {
                     match stmt.expression.as_ref() {
-                        ast::ASTNode::FuncLiteral(ref func) => {
+                        ast::ASTNode::FuncLiteral(_func) => {
                             // panic!("func = {:#?}", func);
                         }
                         _ => panic!("please give me funcliteral, but i recive {:?}", stmt),
@@ -62,332 +98,700 @@ fn test_function() {
     // panic!(" {:#?}", program);
 }
 
-// #[test]
-// pub fn test_return_stmt(){
-//     let input = "return 5;
-//         return 10;
-//         return 99999;
-//     ";
-//     let l = lexer::Lexer::new(input);
-//     let mut p = parser::Parser::new(l);
-//     let program = p.parse_program().unwrap();
-
-//     check_parse_error(&p);
-
-//     assert_eq!(3, program.statements.len());
-
-//     for i in program.statements{
-//         match i{
-//             Some(stmt)=>{
-//                 assert_eq!(stmt.token_literal(),"return");
-//             },
-//             _=> eprintln!("stmt not returnStatment, got={:?}",i),
-//         }
-//     }
-// }
-
-// #[test]
-// fn test_id_expr() {
-//     let input = "foobar;";
-//     let l = lexer::Lexer::new(input);
-//     let mut p = parser::Parser::new(l);
-//     let program = p.parse_program().unwrap();
-
-//     check_parse_error(&p);
-//     if program.statements.len() != 1 {
-//         panic!("program = {:?}",program.statements);
-//     }
-//     match &program.statements[0] {
-//         Some(stmt)=>{
-//             match stmt.as_any().downcast_ref::<ast::ExpressionStatement>(){
-//                 Some(expr_stmt)=>{
-//                     match &expr_stmt.expression{
-//                         Some(ls)=> {
-//                             match ls.as_any().downcast_ref::<ast::Identifier>(){
-//                                 Some(b)=>{
-//                                     assert_eq!(b.value, "foobar");
-//                                     assert_eq!(b.token_literal(), "foobar");
-//                                 },
-//                                 _=>panic!("not letstmt, has {:#?}",program.statements),
-//                             }
-//                         },
-//                         _=>panic!("expression is none"),
-//                     }
-
-//                 },
-//                 _=>panic!("not ast::ExpressionStatement"),
-//             }
-//         },
-//         _=> panic!("program.statment[0] is not ast::Expr"),
-//     }
-// }
-
-// #[test]
-// fn test_int_literal_expression() {
-//     let input = "5;";
-//     let l = lexer::Lexer::new(input);
-//     let mut p = parser::Parser::new(l);
-//     let program = p.parse_program().unwrap();
-
-//     check_parse_error(&p);
-//     if program.statements.len() != 1 {
-//         panic!("program = {:?}",program.statements);
-//     }
-
-//     match &program.statements[0] {
-//         Some(stmt)=>{
-//             match stmt.as_any().downcast_ref::<ast::ExpressionStatement>(){
-//                 Some(expr_stmt)=>{
-//                     match &expr_stmt.expression{
-//                         Some(ls)=> {
-//                             match ls.as_any().downcast_ref::<ast::IntegerLiteral>(){
-//                                 Some(b)=>{
-//                                     assert_eq!(b.value, 5);
-//                                     assert_eq!(b.token_literal(), "5");
-//                                 },
-//                                 _=>panic!("not letstmt, has {:#?}",program.statements),
-//                             }
-//                         },
-//                         _=>panic!("expression is none"),
-//                     }
-
-//                 },
-//                 _=>panic!("not ast::ExpressionStatement"),
-//             }
-//         },
-//         _=> panic!("program.statment[0] is not ast::Expr"),
-//     }
-// }
-
-// #[derive(Debug)]
-// struct TestPrefix{
-//     input: String,
-//     operator:String,
-//     intValue:i64,
-// }
-// impl TestPrefix{
-//     fn new<S: Into<String>>(input: S,operator:S,intValue:i64)->Self{
-//         TestPrefix{
-//             input: input.into(),
-//             operator:operator.into(),
-//             intValue,
-//         }
-//     }
-// }
-// #[test]
-// fn test_prefix_expr(){
-//     let pretests = vec![
-//         TestPrefix::new("!5", "!", 5),
-//         TestPrefix::new("-15", "-", 15),
-//     ];
-//     for i in pretests{
-//         let l = lexer::Lexer::new(i.input);
-//         let mut p = parser::Parser::new(l);
-//         let program = p.parse_program().unwrap();
-
-//         check_parse_error(&p);
-//         if program.statements.len() != 1 {
-//             panic!("program = {:?}",program.statements);
-//         }
-//         match &program.statements[0] {
-//             Some(stmt)=>{
-//                 match stmt.as_any().downcast_ref::<ast::ExpressionStatement>(){
-//                     Some(expr_stmt)=>{
-//                         match &expr_stmt.expression{
-//                             Some(ls)=> {
-//                                 match ls.as_any().downcast_ref::<ast::PrefixExpression>(){
-//                                     Some(b)=>{
-
-//                                         assert_eq!(b.operator, i.operator);
-//                                         test_int_literal(b.right.as_ref().unwrap(), i.intValue);
-//                                     },
-//                                     _=>panic!("not letstmt, has {:#?}",program.statements),
-//                                 }
-//                             },
-//                             _=>panic!("expression is none"),
-//                         }
-//                     },
-//                     _=>panic!("not ast::ExpressionStatement"),
-//                 }
-//             },
-//             _=> panic!("program.statment[0] is not ast::Expr"),
-//         }
-//     }
-// }
-
-// fn test_int_literal(il:& Box<dyn ast::Node>, value:i64) {
-//     match il.as_any().downcast_ref::<ast::IntegerLiteral>(){
-//         Some(b)=>{
-
-//             assert_eq!(b.value, value);
-//             assert_eq!(b.token_literal(), format!("{}",value));
-//         },
-//         _=>panic!("not ast::IntegerLiteral, has {:#?}",il),
-//     }
-// }
-
-// #[derive(Debug)]
-// struct TestInfix{
-//     input: String,
-//     operator:String,
-//     left_val:i64,
-//     right_val:i64,
-// }
-// impl TestInfix{
-//     fn new<S: Into<String>>(input: S,operator:S,lv:i64,rv:i64)->Self{
-//         TestInfix{
-//             input: input.into(),
-//             operator:operator.into(),
-//             left_val:lv,
-//             right_val:rv,
-//         }
-//     }
-// }
-
-// #[test]
-// fn test_infix_expr(){
-//     let test =vec![
-//         TestInfix::new("5*5;", "*", 5, 5),
-//         TestInfix::new("5/5;", "/", 5, 5),
-//         TestInfix::new("5>5;", ">", 5, 5),
-//         TestInfix::new("5<5;", "<", 5, 5),
-//         TestInfix::new("5 + 5;", "+", 5, 5),
-//         TestInfix::new("5-5;", "-", 5, 5),
-
-//         TestInfix::new("5==5;", "==", 5, 5),
-//         TestInfix::new("5!=5;", "!=", 5, 5),
-//     ];
-//     for i in test{
-//         let l = lexer::Lexer::new(i.input);
-//         let mut p = parser::Parser::new(l);
-//         let program = p.parse_program().unwrap();
-
-//         check_parse_error(&p);
-//         if program.statements.len() != 1 {
-//             panic!("program = {:#?}",program.statements);
-//         }
-
-//         match &program.statements[0] {
-//             Some(stmt)=>{
-//                 match stmt.as_any().downcast_ref::<ast::ExpressionStatement>(){
-//                     Some(expr_stmt)=>{
-//                         match &expr_stmt.expression{
-//                             Some(ls)=> {
-//                                 match ls.as_any().downcast_ref::<ast::InfixExpression>(){
-//                                     Some(b)=>{
-
-//                                         assert_eq!(b.operator, i.operator);
-//                                         test_int_literal(b.left.as_ref().unwrap(), i.left_val);
-//                                     },
-//                                     _=>panic!("not letstmt, has {:#?}",program.statements),
-//                                 }
-//                             },
-//                             _=>panic!("expression is none"),
-//                         }
-//                     },
-//                     _=>panic!("not ast::ExpressionStatement"),
-//                 }
-//             },
-//             _=> panic!("program.statment[0] is not ast::Expr"),
-//         }
-//     }
-// }
-
-// #[test]
-// fn test_operator_precedence() {
-//     let tests = vec![
-//         ("true","true"),
-//         ("false","false"),
-//         ("3>5==false","((3 > 5) == false)"),
-//         ("3<5==false","((3 < 5) == false)"),
-//         // "-a*b",
-//         // "!-a",
-//         // "a+b+c",
-//         // "a+b-c",
-//         // "a*b*c",
-//         // "a*b/c",
-//         // "a+b/c",
-//         // "a+b*c+d/e-f",
-//         // "3+4; -5*5",
-//         // "5>4==3<4",
-//         // "3+4*5==3*1+4*5",
-//         // "3+4*5==3*1+4*5",
-//     ];
-//     for (a,b) in tests{
-//         let l = lexer::Lexer::new(a);
-//         let mut p = parser::Parser::new(l);
-//         let program = p.parse_program().unwrap();
-
-//         check_parse_error(&p);
-
-//     }
-// }
-
-// // #[test]
-// // fn test_boolean() {
-// //     let tests = vec![
-// //         ("!true","!",true),
-// //         ("!false","!",false),
-// //     ];
-// //     for (a,b,c) in tests{
-// //         let l = lexer::Lexer::new(a);
-// //         let mut p = parser::Parser::new(l);
-// //         let program = p.parse_program().unwrap();
-
-// //         check_parse_error(&p);
-// //         let actual = program.node_to_string();
-// //         panic!("{}", actual);
-// //         // assert_eq!(actual.parse::<bool>(),Ok(c));
-// //     }
-// // }
-
-// #[test]
-// fn test_ifexpression(){
-//     let input = "if (x<y) {x}";
-
-//     let l = lexer::Lexer::new(input);
-//     let mut p = parser::Parser::new(l);
-//     let program = p.parse_program().unwrap();
-
-//     check_parse_error(&p);
-
-//     if program.statements.len() != 1 {
-//         panic!("program = {:?}",program.statements);
-//     }
-
-//     match &program.statements[0] {
-//         Some(stmt)=>{
-//             match stmt.as_any().downcast_ref::<ast::ExpressionStatement>(){
-//                 Some(expr_stmt)=>{
-//                     match &expr_stmt.expression{
-//                         Some(ls)=> {
-//                             match ls.as_any().downcast_ref::<ast::IfExpression>(){
-//                                 Some(b)=>{
-//                                     // panic!("{:#?}",b);
-
-//                                 },
-//                                 _=>panic!("not letstmt, has {:#?}",program.statements),
-//                             }
-//                         },
-//                         _=>panic!("expression is none"),
-//                     }
-//                 },
-//                 _=>panic!("not ast::ExpressionStatement"),
-//             }
-//         },
-//         _=> panic!("program.statment[0] is not ast::Expr"),
-//     }
-// }
-
-// // fn test_infix_expression<T, S:Into<String>>(exp: Option<Box<dyn ast::Expression>>, left: T,  operator: S, right: T) {
-
-// //     if let Some(exp) = exp {
-// //         match exp.as_any().downcast_ref::<ast::InfixExpression>(){
-// //             Some(infix_expr)=>{
-// //                 assert_eq!(infix_expr.operator, operator.into());
-// //             },
-// //             _=>panic!("not ast::IfExpression"),
-// //         }
-// //     }
-
-// // }
+#[test]
+fn test_doc_comment() {
+    let input = "/// the answer\n/// to everything\nlet x = 42;\n/// dropped\nx;\nlet y = x;";
+    let l = lexer::Lexer::new(input).with_doc_comments();
+    let mut p = parser::Parser::new(l);
+    let program = p.parse_program().unwrap();
+
+    let docs: Vec<_> = match program {
+        ast::ASTNode::Program(program) => program
+            .statements
+            .iter()
+            .filter_map(|stmt| match stmt.as_ref() {
+                ast::ASTNode::LetStatement(ref stmt) => Some(stmt.doc.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => panic!("parse program error: {:?}", program),
+    };
+    assert_eq!(
+        docs,
+        vec![Some("the answer\nto everything".to_string()), None]
+    );
+}
+
+#[test]
+fn test_error_recovery() {
+    use crate::token::token::TokenType::*;
+    let input = "let = 5;\nlet b = 10;\nlet c 3;\nfn(x { x };\nif (b) { let = 1; b } let d = ;\nd";
+    let mut p = parser::Parser::new(lexer::Lexer::new(input));
+    let errors = p.parse_program().unwrap_err();
+
+    let found: Vec<_> = errors
+        .diagnostics
+        .iter()
+        .map(|d| (d.to_string(), d.expected.clone(), d.span.slice(input)))
+        .collect();
+    assert_eq!(
+        found[0],
+        (
+            "1:5: expected `ident`, found `=`".to_string(),
+            vec![IDENT],
+            "="
+        )
+    );
+    assert_eq!(
+        found[1],
+        (
+            "3:7: expected `=`, found `3`".to_string(),
+            vec![ASSIGN],
+            "3"
+        )
+    );
+    assert_eq!(
+        found[2],
+        (
+            "4:6: expected one of `,`, `)`, found `{`".to_string(),
+            vec![COMMA, RPAREN],
+            "{"
+        )
+    );
+    assert_eq!(found[3].0, "5:14: expected `ident`, found `=`");
+    assert_eq!(found[4].0, "5:31: expected expression, found `;`");
+    assert!(found[4].1.contains(&INT));
+    assert_eq!(found.len(), 5);
+
+    let kinds: Vec<_> = match *errors.program {
+        ast::ASTNode::Program(program) => program.statements.iter().map(|s| s.kind()).collect(),
+        node => panic!("not a program: {:?}", node),
+    };
+    assert_eq!(
+        kinds,
+        vec!["LetStatement", "ExpressionStatement", "ExpressionStatement"]
+    );
+
+    // the `}` closing the failing `{` ends the statement
+    let input = "let a = 1; let f = fn( { } let c = 3; c";
+    let errors = parser::Parser::new(lexer::Lexer::new(input))
+        .parse_program()
+        .unwrap_err();
+    let found: Vec<_> = errors.diagnostics.iter().map(|d| d.to_string()).collect();
+    assert_eq!(found, ["1:24: expected one of `ident`, `...`, found `{`"]);
+    let statements = match *errors.program {
+        ast::ASTNode::Program(program) => program.statements,
+        node => panic!("not a program: {:?}", node),
+    };
+    let printed: Vec<_> = statements.iter().map(|s| s.to_string()).collect();
+    assert_eq!(printed, ["let a = 1;", "let c = 3;", "c;"]);
+}
+
+#[test]
+fn test_error_at_eof() {
+    let tests = [
+        ("let a = (1 + 2", "1:15: expected `)`, found end of input"),
+        ("let a = 1", "1:10: expected `;`, found end of input"),
+        ("if (a) { a", "1:11: expected `}`, found end of input"),
+        ("let s = \"abc", "1:9: unterminated string"),
+        ("}", "1:1: unexpected `}`"),
+    ];
+    for (input, expected) in tests.iter() {
+        let mut p = parser::Parser::new(lexer::Lexer::new(input));
+        let errors = p.parse_program().unwrap_err();
+        assert_eq!(errors.to_string(), *expected, "{}", input);
+    }
+}
+
+fn parse(input: &str) -> Vec<Box<ast::ASTNode>> {
+    let l = lexer::Lexer::new(input);
+    let mut p = parser::Parser::new(l);
+    match p.parse_program() {
+        Ok(ast::ASTNode::Program(program)) => program.statements,
+        other => panic!("parse program error: {:?}", other),
+    }
+}
+
+fn expression(stmt: &ast::ASTNode) -> &ast::ASTNode {
+    match stmt {
+        ast::ASTNode::ExpressionStatement(stmt) => stmt.expression.as_ref(),
+        _ => panic!("not ast::ExpressionStatement, got {:?}", stmt),
+    }
+}
+
+fn test_int_literal(il: &ast::ASTNode, value: i64) {
+    match il {
+        ast::ASTNode::IntegerLiteral(b) => {
+            assert_eq!(b.value, value);
+            assert_eq!(il.to_string(), format!("{}", value));
+        }
+        _ => panic!("not ast::IntegerLiteral, has {:#?}", il),
+    }
+}
+
+fn test_identifier(id: &ast::ASTNode, value: &str) {
+    match id {
+        ast::ASTNode::Identifier(b) => assert_eq!(b.value, value),
+        _ => panic!("not ast::Identifier, has {:#?}", id),
+    }
+}
+
+#[test]
+pub fn test_return_stmt() {
+    let input = "return 5;
+        return 10;
+        return 99999;
+    ";
+    let statements = parse(input);
+    assert_eq!(3, statements.len());
+
+    for (stmt, value) in statements.iter().zip(&[5, 10, 99999]) {
+        match stmt.as_ref() {
+            ast::ASTNode::ReturnStatement(stmt) => test_int_literal(&stmt.return_value, *value),
+            _ => panic!("stmt not returnStatment, got={:?}", stmt),
+        }
+    }
+}
+
+#[test]
+fn test_id_expr() {
+    let statements = parse("foobar;");
+    assert_eq!(statements.len(), 1, "program = {:?}", statements);
+    test_identifier(expression(&statements[0]), "foobar");
+}
+
+#[test]
+fn test_int_literal_expression() {
+    let statements = parse("5;");
+    assert_eq!(statements.len(), 1, "program = {:?}", statements);
+    test_int_literal(expression(&statements[0]), 5);
+}
+
+#[test]
+fn test_prefix_expr() {
+    let pretests = [("!5", "!", 5), ("-15", "-", 15)];
+    for (input, operator, value) in pretests.iter() {
+        let statements = parse(input);
+        assert_eq!(statements.len(), 1, "program = {:?}", statements);
+        match expression(&statements[0]) {
+            ast::ASTNode::PrefixExpression(b) => {
+                assert_eq!(b.operator, *operator);
+                test_int_literal(&b.right, *value);
+            }
+            other => panic!("not ast::PrefixExpression, has {:#?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_infix_expr() {
+    let test = [
+        ("5*5;", "*", 5, 5),
+        ("5/5;", "/", 5, 5),
+        ("5>5;", ">", 5, 5),
+        ("5<5;", "<", 5, 5),
+        ("5 + 5;", "+", 5, 5),
+        ("5-5;", "-", 5, 5),
+        ("5==5;", "==", 5, 5),
+        ("5!=5;", "!=", 5, 5),
+    ];
+    for (input, operator, left, right) in test.iter() {
+        let statements = parse(input);
+        assert_eq!(statements.len(), 1, "program = {:#?}", statements);
+        match expression(&statements[0]) {
+            ast::ASTNode::InfixExpression(b) => {
+                assert_eq!(b.operator, *operator);
+                test_int_literal(&b.left, *left);
+                test_int_literal(&b.right, *right);
+            }
+            other => panic!("not ast::InfixExpression, has {:#?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_operator_precedence() {
+    // each input must parse exactly like its fully parenthesised form
+    let tests = [
+        ("true", "true"),
+        ("false", "false"),
+        ("3>5==false", "((3 > 5) == false)"),
+        ("3<5==false", "((3 < 5) == false)"),
+        ("-a*b", "((-a) * b)"),
+        ("!-a", "(!(-a))"),
+        ("a+b+c", "((a + b) + c)"),
+        ("a+b-c", "((a + b) - c)"),
+        ("a-b+c", "((a - b) + c)"),
+        ("a-b*c", "(a - (b * c))"),
+        ("a-b<c", "((a - b) < c)"),
+        ("a*b*c", "((a * b) * c)"),
+        ("a*b/c", "((a * b) / c)"),
+        ("a+b/c", "(a + (b / c))"),
+        ("a+b*c+d/e-f", "(((a + (b * c)) + (d / e)) - f)"),
+        ("3+4; -5*5", "(3 + 4);((-5) * 5)"),
+        ("5>4==3<4", "((5 > 4) == (3 < 4))"),
+        ("3+4*5==3*1+4*5", "((3 + (4 * 5)) == ((3 * 1) + (4 * 5)))"),
+        ("a+add(b*c)+d", "((a + add((b * c))) + d)"),
+        (
+            "add(a, b, 1, 2 * 3, 4 + 5, add(6, 7 * 8))",
+            "add(a, b, 1, (2 * 3), (4 + 5), add(6, (7 * 8)))",
+        ),
+        ("a*[1,2,3,4][b*c]*d", "((a * ([1, 2, 3, 4][(b * c)])) * d)"),
+        (
+            "add(a*b[2], b[1], 2*[1,2][1])",
+            "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])))",
+        ),
+        ("a || b && c", "(a || (b && c))"),
+        ("1..n+1 == r", "((1 .. (n + 1)) == r)"),
+        ("-a..=b*2", "((-a) ..= (b * 2))"),
+    ];
+    for (a, b) in tests.iter() {
+        assert_eq!(parse(a), parse(b), "{}", a);
+    }
+}
+
+#[test]
+fn test_boolean() {
+    let tests = [("!true", "!", true), ("!false", "!", false)];
+    for (input, operator, value) in tests.iter() {
+        let statements = parse(input);
+        match expression(&statements[0]) {
+            ast::ASTNode::PrefixExpression(b) => {
+                assert_eq!(b.operator, *operator);
+                assert_eq!(b.right.to_string(), value.to_string());
+            }
+            other => panic!("not ast::PrefixExpression, has {:#?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_ifexpression() {
+    let input = "if (x<y) {x} else { let z = y; z }";
+    let statements = parse(input);
+    assert_eq!(statements.len(), 1, "program = {:?}", statements);
+
+    match expression(&statements[0]) {
+        ast::ASTNode::IfExpression(b) => {
+            assert_eq!(b.condition.to_string(), "x < y");
+            match (b.consequence.as_ref(), b.alternative.as_ref()) {
+                (ast::ASTNode::BlockStatement(c), ast::ASTNode::BlockStatement(a)) => {
+                    assert_eq!(c.statements.len(), 1);
+                    test_identifier(expression(&c.statements[0]), "x");
+                    assert_eq!(a.statements.len(), 2);
+                }
+                other => panic!("not blocks, has {:#?}", other),
+            }
+        }
+        other => panic!("not ast::IfExpression, has {:#?}", other),
+    }
+}
+
+#[test]
+fn test_array_and_index() {
+    let statements = parse("[1, 2 * 2, fn(x) { x }][1 + 1]; [];");
+    match expression(&statements[0]) {
+        ast::ASTNode::IndexLiteral(_, left, index, _) => {
+            match left.as_ref() {
+                ast::ASTNode::ArrayLiteral(_, elements, _) => assert_eq!(elements.len(), 3),
+                other => panic!("not ast::ArrayLiteral, has {:#?}", other),
+            }
+            assert_eq!(index.to_string(), "1 + 1");
+        }
+        other => panic!("not ast::IndexLiteral, has {:#?}", other),
+    }
+    match expression(&statements[1]) {
+        ast::ASTNode::ArrayLiteral(_, elements, _) => assert!(elements.is_empty()),
+        other => panic!("not ast::ArrayLiteral, has {:#?}", other),
+    }
+}
+
+#[test]
+fn test_slice_expression() {
+    let tests = [
+        ("a[1:3]", "1", "3"),
+        ("s[x + 1:]", "x + 1", ""),
+        ("a[:-1]", "", "-1"),
+        ("f()[:]", "", ""),
+    ];
+    for (input, low, high) in tests.iter() {
+        let statements = parse(input);
+        match expression(&statements[0]) {
+            ast::ASTNode::SliceExpression(slice) => {
+                assert_eq!(slice.low.to_string(), *low, "{}", input);
+                assert_eq!(slice.high.to_string(), *high, "{}", input);
+            }
+            other => panic!("not ast::SliceExpression, has {:#?}", other),
+        }
+    }
+
+    let mut p = parser::Parser::new(lexer::Lexer::new("a[1:2:3]"));
+    let errors = p.parse_program().unwrap_err();
+    assert_eq!(
+        errors.diagnostics[0].to_string(),
+        "1:6: expected `]`, found `:`"
+    );
+}
+
+#[test]
+fn test_assign_expression() {
+    let statements = parse("a[0][1] = v; x = y = 2;");
+    match expression(&statements[0]) {
+        ast::ASTNode::AssignExpression(assign) => {
+            assert_eq!(assign.target.to_string(), "a[0][1]");
+            test_identifier(&assign.value, "v");
+        }
+        other => panic!("not ast::AssignExpression, has {:#?}", other),
+    }
+    match expression(&statements[1]) {
+        ast::ASTNode::AssignExpression(assign) => {
+            assert_eq!(assign.value.kind(), "AssignExpression")
+        }
+        other => panic!("not ast::AssignExpression, has {:#?}", other),
+    }
+
+    let mut p = parser::Parser::new(lexer::Lexer::new("f()[0] = 1;"));
+    let errors = p.parse_program().unwrap_err();
+    assert_eq!(
+        errors.diagnostics[0].to_string(),
+        "1:1: cannot assign to CallExpression"
+    );
+}
+
+#[test]
+fn test_class_statement() {
+    let statements = parse("class Point < Base { let x = 0; fn norm(self) { self.x } } p.norm();");
+    match statements[0].as_ref() {
+        ast::ASTNode::ClassStatement(class) => {
+            assert_eq!(class.name.value, "Point");
+            assert_eq!(class.superclass.as_ref().unwrap().value, "Base");
+            let kinds: Vec<_> = class.members.iter().map(|m| m.kind()).collect();
+            assert_eq!(kinds, vec!["LetStatement", "FuncLiteral"]);
+            match class.members[1].as_ref() {
+                ast::ASTNode::FuncLiteral(method) => {
+                    assert_eq!(method.name.as_ref().unwrap().value, "norm");
+                    assert_eq!(method.params.len(), 1);
+                }
+                other => panic!("not ast::FuncLiteral, has {:#?}", other),
+            }
+        }
+        other => panic!("not ast::ClassStatement, has {:#?}", other),
+    }
+    match expression(&statements[1]) {
+        ast::ASTNode::CallExpression(call) => match call.func.as_ref() {
+            ast::ASTNode::MemberExpression(member) => {
+                test_identifier(&member.object, "p");
+                assert_eq!(member.name.value, "norm");
+            }
+            other => panic!("not ast::MemberExpression, has {:#?}", other),
+        },
+        other => panic!("not ast::CallExpression, has {:#?}", other),
+    }
+
+    for (input, message) in [
+        (
+            "class P { x = 1; }",
+            "1:11: expected one of `let`, `fn`, `}`, found `x`",
+        ),
+        ("super;", "1:6: expected `.`, found `;`"),
+        ("p.1;", "1:3: expected `ident`, found `1`"),
+    ] {
+        let mut p = parser::Parser::new(lexer::Lexer::new(input));
+        let errors = p.parse_program().unwrap_err();
+        assert_eq!(errors.diagnostics[0].to_string(), message);
+    }
+}
+
+#[test]
+fn test_match_expression() {
+    let statements =
+        parse("match v { 0 => zero, [x, ...rest] if x > 0 => { x } {\"k\": _} => k, n => n, }");
+    match expression(&statements[0]) {
+        ast::ASTNode::MatchExpression(exp) => {
+            test_identifier(&exp.value, "v");
+            let patterns: Vec<_> = exp.arms.iter().map(|a| a.pattern.to_string()).collect();
+            assert_eq!(patterns, vec!["0", "[x, ...rest]", "{\"k\": _}", "n"]);
+            assert_eq!(exp.arms[1].guard.to_string(), "x > 0");
+            assert!(exp.arms[0].guard.is_none());
+            assert_eq!(exp.arms[1].body.kind(), "BlockStatement");
+            test_identifier(&exp.arms[2].body, "k");
+        }
+        other => panic!("not ast::MatchExpression, has {:#?}", other),
+    }
+
+    for (input, message) in [
+        ("match v { + => 1 }", "1:11: expected pattern, found `+`"),
+        (
+            "match v { [...r, x] => 1 }",
+            "1:16: expected `]`, found `,`",
+        ),
+        (
+            "match v { {k: 1} => 1 }",
+            "1:12: expected one of `string`, `int`, `true`, `false`, found `k`",
+        ),
+        (
+            "match v { 1 => 1 2 => 2 }",
+            "1:18: expected one of `,`, `}`, found `2`",
+        ),
+        ("match v { 1 -> 1 }", "1:13: expected `=>`, found `->`"),
+    ] {
+        let mut p = parser::Parser::new(lexer::Lexer::new(input));
+        let errors = p.parse_program().unwrap_err();
+        assert_eq!(errors.diagnostics[0].to_string(), message);
+    }
+}
+
+#[test]
+fn test_destructuring_let() {
+    let statements = parse("let [head, ...tail] = xs; let {\"name\": n} = person;");
+    let patterns: Vec<_> = statements
+        .iter()
+        .map(|stmt| match stmt.as_ref() {
+            ast::ASTNode::LetStatement(letstmt) => letstmt.pattern.as_ref().unwrap().to_string(),
+            other => panic!("not ast::LetStatement, has {:#?}", other),
+        })
+        .collect();
+    assert_eq!(patterns, vec!["[head, ...tail]", "{\"name\": n}"]);
+
+    for (input, message) in [
+        (
+            "let [a, 1 + 2] = xs;",
+            "1:11: expected one of `,`, `]`, found `+`",
+        ),
+        (
+            "class P { let [a] = xs; }",
+            "1:15: expected `ident`, found `[`",
+        ),
+    ] {
+        let mut p = parser::Parser::new(lexer::Lexer::new(input));
+        let errors = p.parse_program().unwrap_err();
+        assert_eq!(errors.diagnostics[0].to_string(), message);
+    }
+}
+
+#[test]
+fn test_parameters_and_arguments() {
+    let statements = parse("fn(x, y = 10, ...rest) { x }(1, y: 2);");
+    match expression(&statements[0]) {
+        ast::ASTNode::CallExpression(call) => {
+            match call.func.as_ref() {
+                ast::ASTNode::FuncLiteral(func) => {
+                    let params: Vec<_> = func.params.iter().map(|p| p.to_string()).collect();
+                    assert_eq!(params, vec!["x", "y = 10", "...rest"]);
+                    assert!(func.params[2].rest);
+                }
+                other => panic!("not ast::FuncLiteral, has {:#?}", other),
+            }
+            assert_eq!(call.args.len(), 1);
+            assert_eq!(call.named.len(), 1);
+            assert_eq!(call.named[0].0.value, "y");
+        }
+        other => panic!("not ast::CallExpression, has {:#?}", other),
+    }
+
+    for (input, message) in [
+        ("fn(...rest, x) {}", "1:11: expected `)`, found `,`"),
+        ("fn(1) {}", "1:4: expected one of `ident`, `...`, found `1`"),
+        (
+            "f(x: 1, 2)",
+            "1:9: positional argument after named arguments",
+        ),
+        (
+            "class P { fn m() {} }",
+            "1:14: method m needs a parameter for the instance",
+        ),
+    ] {
+        let mut p = parser::Parser::new(lexer::Lexer::new(input));
+        let errors = p.parse_program().unwrap_err();
+        assert_eq!(errors.diagnostics[0].to_string(), message);
+    }
+}
+
+#[test]
+fn test_short_lambdas() {
+    // both forms desugar to the same FuncLiteral as the long form
+    let long = parse("fn(x, y = 1) { x * y }; fn(x) { x + 1 }; fn() { 0 };");
+    let short = parse("|x, y = 1| x * y; x => x + 1; || 0;");
+    assert_eq!(short, long);
+
+    // in a match guard `=>` ends the guard
+    let statements = parse("match v { n if ok => n, f => f(x => x) }");
+    match expression(&statements[0]) {
+        ast::ASTNode::MatchExpression(exp) => {
+            test_identifier(&exp.arms[0].guard, "ok");
+            assert_eq!(exp.arms[1].body.to_string(), "f(fn(x) { x; })");
+        }
+        other => panic!("not ast::MatchExpression, has {:#?}", other),
+    }
+
+    // but inside brackets in the guard, `x =>` is a lambda again
+    let statements = parse(
+        "match v { xs if xs.map(y => y > 0)[(z => z)(0)] => xs, [a] if {1: b => b}[1](a) => a }",
+    );
+    match expression(&statements[0]) {
+        ast::ASTNode::MatchExpression(exp) => {
+            assert_eq!(
+                exp.arms[0].guard.to_string(),
+                "xs.map(fn(y) { y > 0; })[fn(z) { z; }(0)]"
+            );
+            test_identifier(&exp.arms[0].body, "xs");
+            assert_eq!(exp.arms[1].guard.to_string(), "{1: fn(b) { b; }}[1](a)");
+        }
+        other => panic!("not ast::MatchExpression, has {:#?}", other),
+    }
+
+    let mut p = parser::Parser::new(lexer::Lexer::new("|x y| x"));
+    let errors = p.parse_program().unwrap_err();
+    assert_eq!(
+        errors.diagnostics[0].to_string(),
+        "1:4: expected one of `,`, `|`, found `y`"
+    );
+}
+
+#[test]
+fn test_interpolated_string() {
+    let statements = parse(r#""a ${x + 1} b ${"c"}""#);
+    match expression(&statements[0]) {
+        ast::ASTNode::InterpolatedString(parts) => {
+            let parts: Vec<_> = parts.iter().map(|p| p.to_string()).collect();
+            assert_eq!(parts, ["\"a \"", "x + 1", "\" b \"", "\"c\"", "\"\""]);
+        }
+        other => panic!("not ast::InterpolatedString, has {:#?}", other),
+    }
+
+    let tests = [
+        (r#""a ${x y}""#, "1:8: expected `}`, found `y`"),
+        (r#""a ${}""#, "1:6: expected expression, found `string`"),
+        (r#""a ${x"#, "1:7: expected `}`, found end of input"),
+    ];
+    for (input, expected) in tests.iter() {
+        let mut p = parser::Parser::new(lexer::Lexer::new(input));
+        let errors = p.parse_program().unwrap_err();
+        assert_eq!(errors.diagnostics[0].to_string(), *expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_hash_literal() {
+    let tests = [
+        ("{}", vec![]),
+        (
+            "{\"one\": 1, \"two\": 2, \"three\": 3}",
+            vec!["\"one\": 1", "\"two\": 2", "\"three\": 3"],
+        ),
+        (
+            "{1: 0 + 1, true: fn() { 2 }(),}",
+            vec!["1: 0 + 1", "true: fn() { 2; }()"],
+        ),
+    ];
+    for (input, expected) in tests.iter() {
+        let statements = parse(input);
+        match expression(&statements[0]) {
+            ast::ASTNode::HashLiteral(_, pairs, _) => {
+                let pairs: Vec<_> = pairs.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+                assert_eq!(&pairs, expected);
+            }
+            other => panic!("not ast::HashLiteral, has {:#?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_loops() {
+    let input = "while (i < 10) { i = i + 1; if (i == 5) { break; } continue }
+        for (x in [1, 2]) { print(x); }";
+    let statements = parse(input);
+    assert_eq!(statements.len(), 2);
+    match expression(&statements[0]) {
+        ast::ASTNode::WhileExpression(w) => {
+            assert_eq!(w.condition.to_string(), "i < 10");
+            match w.body.as_ref() {
+                ast::ASTNode::BlockStatement(b) => {
+                    assert_eq!(b.statements.len(), 3);
+                    assert_eq!(b.statements[2].kind(), "ContinueStatement");
+                }
+                other => panic!("not a block, has {:#?}", other),
+            }
+        }
+        other => panic!("not ast::WhileExpression, has {:#?}", other),
+    }
+    match expression(&statements[1]) {
+        ast::ASTNode::ForExpression(f) => {
+            assert_eq!(f.variable.value, "x");
+            assert_eq!(f.iterable.to_string(), "[1, 2]");
+        }
+        other => panic!("not ast::ForExpression, has {:#?}", other),
+    }
+    assert_eq!(
+        statements[0].to_string(),
+        "while (i < 10) { i = i + 1; if (i == 5) { break; }; continue; };"
+    );
+    assert_eq!(parse(&statements[1].to_string()), statements[1..]);
+
+    let mut p = parser::Parser::new(lexer::Lexer::new("for (1 in xs) {}"));
+    let errors = p.parse_program().unwrap_err();
+    assert_eq!(errors.to_string(), "1:6: expected `ident`, found `1`");
+}
+
+#[test]
+fn test_nested_functions() {
+    let input = "let adder = fn(x) { fn(y) { if (y > 0) { x + y } else { x } } };
+        adder(1)(2);
+        let counter = fn(n) { n = n + 1; n };";
+    let statements = parse(input);
+    assert_eq!(statements.len(), 3);
+    assert_eq!(
+        statements[0].to_string(),
+        "let adder = fn(x) { fn(y) { if (y > 0) { x + y; } else { x; }; }; };"
+    );
+    assert_eq!(statements[1].to_string(), "adder(1)(2);");
+    assert_eq!(
+        statements[2].to_string(),
+        "let counter = fn(n) { n = n + 1; n; };"
+    );
+}
+
+#[test]
+fn test_import_statement() {
+    let statements = parse("import \"lib/math.mk\" as math; math.pi");
+    assert_eq!(statements.len(), 2);
+    match statements[0].as_ref() {
+        ast::ASTNode::ImportStatement(import) => {
+            assert_eq!(import.path, "lib/math.mk");
+            assert_eq!(import.alias.value, "math");
+        }
+        other => panic!("not ast::ImportStatement, has {:#?}", other),
+    }
+    assert_eq!(statements[0].to_string(), "import \"lib/math.mk\" as math;");
+
+    let tests = [
+        ("import math;", "1:8: expected `string`, found `math`"),
+        ("import \"m.mk\";", "1:14: expected `as`, found `;`"),
+        (
+            "import \"m.mk\" as m",
+            "1:19: expected `;`, found end of input",
+        ),
+    ];
+    for (input, expected) in tests.iter() {
+        let mut p = parser::Parser::new(lexer::Lexer::new(input));
+        let errors = p.parse_program().unwrap_err();
+        assert_eq!(errors.diagnostics[0].to_string(), *expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_macro_literal() {
+    let statements = parse("macro(x, y) { x + y; }");
+    match expression(&statements[0]) {
+        ast::ASTNode::MacroLiteral(m) => {
+            let params: Vec<_> = m.params.iter().map(|p| p.to_string()).collect();
+            assert_eq!(params, ["x", "y"]);
+            assert_eq!(m.body.to_string(), "{ x + y; }");
+        }
+        other => panic!("not ast::MacroLiteral, has {:#?}", other),
+    }
+
+    let mut p = parser::Parser::new(lexer::Lexer::new("macro(x) -> int { x }"));
+    let errors = p.parse_program().unwrap_err();
+    assert_eq!(errors.to_string(), "1:10: expected `{`, found `->`");
+}
@@ -1,24 +1,105 @@
+use std::fs;
 use std::io;
 use std::io::prelude::*;
+use std::path::Path;
 
+use crate::checker::checker::Checker;
 use crate::evaluator::evaluator;
+use crate::evaluator::macro_expansion::expand_macros;
 use crate::mylexer::Lexer;
 use crate::parser::parser;
+use crate::typeck::typeck::{self, Typeck};
 
-const PROMPT: &'static str = ">>> ";
+const PROMPT: &str = ">>> ";
+
+/// Runs a script file, printing the error that stopped it, if any.
+pub fn run_file(path: &Path) {
+    let evaled = evaluator::eval_file(path);
+    if evaled.is_error() {
+        println!("{}", evaled.inspect());
+    }
+}
+
+/// Prints the inferred type of each top-level binding of a script file, or
+/// why inference failed. Inference is stricter than the evaluator, so a
+/// failure here does not mean the script cannot run.
+pub fn print_types(path: &Path) {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            println!("cannot read {:?}: {}", path, e);
+            return;
+        }
+    };
+    let mut program = match parser::Parser::new(Lexer::new(&source)).parse_program() {
+        Ok(program) => program,
+        Err(errors) => {
+            println!("{}", errors);
+            return;
+        }
+    };
+    if let Err(message) = expand_macros(&mut program, &mut evaluator::Environment::new()) {
+        println!("{}", message);
+        return;
+    }
+    match typeck::infer(&program) {
+        Ok(bindings) => {
+            for (name, scheme) in bindings {
+                println!("{}: {}", name, scheme);
+            }
+        }
+        Err(errors) => println!("{}", errors),
+    }
+}
 
 pub fn start() {
     let mut env = evaluator::Environment::new();
+    let mut macros = evaluator::Environment::new();
+    let mut checker = Checker::new();
+    let mut typeck = Typeck::new();
     loop {
         let mut scanned = String::new();
-        io::stdout().write(PROMPT.as_bytes()).unwrap();
+        io::stdout().write_all(PROMPT.as_bytes()).unwrap();
         io::stdout().flush().unwrap();
 
-        io::stdin().read_line(&mut scanned).unwrap();
+        // end of input
+        if io::stdin().read_line(&mut scanned).unwrap() == 0 {
+            return;
+        }
 
-        let mut lexer = Lexer::new(&scanned);
+        // `:type code` prints the type of code without running it
+        let (input, show_type) = match scanned.strip_prefix(":type ") {
+            Some(code) => (code, true),
+            None => (scanned.as_str(), false),
+        };
+        let lexer = Lexer::new(input);
         let mut p = parser::Parser::new(lexer);
-        let program = p.parse_program().unwrap();
+        let mut program = match p.parse_program() {
+            Ok(program) => program,
+            Err(errors) => {
+                println!("{}", errors);
+                continue;
+            }
+        };
+        if let Err(message) = expand_macros(&mut program, &mut macros) {
+            println!("{}", message);
+            continue;
+        }
+
+        if show_type {
+            match typeck.peek(&program) {
+                Ok(ty) => println!("{}", ty),
+                Err(errors) => println!("{}", errors),
+            }
+            continue;
+        }
+
+        if let Err(errors) = checker.check(&program) {
+            println!("{}", errors);
+            continue;
+        }
+        // only so that `:type` knows the names bound so far
+        let _ = typeck.check(&program);
 
         // println!("{:#?}",program);
         let evaled = evaluator::eval(&program, &mut env);
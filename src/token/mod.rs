@@ -1 +1,3 @@
+pub mod precedence;
+pub mod symbol;
 pub mod token;
@@ -0,0 +1,54 @@
+//! Binding power of the operators, shared by the parser, which parses by
+//! it, and the AST printer, which adds back the parentheses it needs.
+use crate::token::token::{TokenType, TokenType::*};
+use std::collections::HashMap;
+
+#[repr(u8)]
+pub enum Pri {
+    LOWEST,
+    ASSIGN,      // 赋值
+    OR,          // ||
+    AND,         // &&
+    EQUALS,      // ==, !=
+    LESSGREATER, // < ,>, <=, >=
+    RANGE,       // .., ..=
+    SUM,         //+,-
+    PRODUCT,     //*,/,%
+    PREFIX,      // !,-
+    CALL,        // func()
+    INDEX,       // array[0], map[0]
+}
+
+lazy_static! {
+    static ref HASHMAP: HashMap<TokenType, u8> = {
+        let mut mmap = HashMap::new();
+        mmap.insert(EQ, Pri::EQUALS as u8);
+        mmap.insert(NotEQ, Pri::EQUALS as u8);
+        mmap.insert(LT, Pri::LESSGREATER as u8);
+        mmap.insert(GT, Pri::LESSGREATER as u8);
+        mmap.insert(LtEQ, Pri::LESSGREATER as u8);
+        mmap.insert(GtEQ, Pri::LESSGREATER as u8);
+        mmap.insert(AND, Pri::AND as u8);
+        mmap.insert(OR, Pri::OR as u8);
+        mmap.insert(DOTDOT, Pri::RANGE as u8);
+        mmap.insert(DOTDOTEQ, Pri::RANGE as u8);
+        mmap.insert(PERCENT, Pri::PRODUCT as u8);
+        mmap.insert(PLUS, Pri::SUM as u8);
+        mmap.insert(MINUS, Pri::SUM as u8);
+        mmap.insert(SLASH, Pri::PRODUCT as u8);
+        mmap.insert(ASTERISK, Pri::PRODUCT as u8);
+        mmap.insert(LPAREN, Pri::CALL as u8);
+        mmap.insert(LBRACKET, Pri::INDEX as u8);
+        mmap.insert(DOT, Pri::INDEX as u8);
+        mmap.insert(ASSIGN, Pri::ASSIGN as u8);
+        mmap.insert(PlusASSIGN, Pri::ASSIGN as u8);
+        mmap.insert(MinusASSIGN, Pri::ASSIGN as u8);
+        mmap.insert(AsteriskASSIGN, Pri::ASSIGN as u8);
+        mmap.insert(SlashASSIGN, Pri::ASSIGN as u8);
+        mmap
+    };
+}
+
+pub(crate) fn get_precedence(t: TokenType) -> u8 {
+    HASHMAP.get(&t).copied().unwrap_or(Pri::LOWEST as u8)
+}
@@ -0,0 +1,209 @@
+//! String interning.
+//!
+//! A `Symbol` is a 4-byte handle to a string stored once in a global table,
+//! so comparing or hashing identifiers costs the same as comparing integers.
+//! Only names are interned: identifiers, keywords and the names the
+//! interpreter makes up. Interned strings live for the rest of the process.
+
+use crate::token::token::TokenType::{self, *};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Keywords, interned first so that their symbols are `1..=KEYWORDS.len()`.
+static KEYWORDS: [(&str, TokenType); 18] = [
+    ("fn", Function),
+    ("let", Let),
+    ("true", True),
+    ("false", False),
+    ("if", If),
+    ("else", Else),
+    ("return", Return),
+    ("class", Class),
+    ("while", While),
+    ("for", For),
+    ("in", In),
+    ("break", Break),
+    ("continue", Continue),
+    ("super", Super),
+    ("match", Match),
+    ("import", Import),
+    ("as", As),
+    ("macro", Macro),
+];
+
+/// The default symbol is the empty string.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Symbol(u32);
+
+// the symbol after the empty string and the keywords
+const FIRST: u32 = KEYWORDS.len() as u32 + 1;
+
+// The strings of the symbols from FIRST on, in chunks that never move once
+// allocated, so that reading one takes no lock. Chunk k holds 2^k strings.
+struct Strings {
+    chunks: [OnceLock<Box<[OnceLock<&'static str>]>>; 32],
+}
+
+impl Strings {
+    // the chunk holding string i, and its place there
+    fn locate(i: usize) -> (usize, usize) {
+        let chunk = (usize::BITS - 1 - (i + 1).leading_zeros()) as usize;
+        (chunk, i + 1 - (1 << chunk))
+    }
+
+    fn get(&self, i: usize) -> &'static str {
+        let (chunk, slot) = Strings::locate(i);
+        self.chunks[chunk]
+            .get()
+            .and_then(|c| c[slot].get())
+            .unwrap()
+    }
+
+    fn set(&self, i: usize, s: &'static str) {
+        let (chunk, slot) = Strings::locate(i);
+        let chunk =
+            self.chunks[chunk].get_or_init(|| (0..1 << chunk).map(|_| OnceLock::new()).collect());
+        let _ = chunk[slot].set(s);
+    }
+}
+
+static STRINGS: Strings = Strings {
+    chunks: [const { OnceLock::new() }; 32],
+};
+
+lazy_static! {
+    // writers only; the keywords are known without it
+    static ref NAMES: Mutex<HashMap<&'static str, Symbol>> = {
+        let mut names = HashMap::new();
+        names.insert("", Symbol(0));
+        for (i, (keyword, _)) in KEYWORDS.iter().enumerate() {
+            names.insert(*keyword, Symbol(i as u32 + 1));
+        }
+        Mutex::new(names)
+    };
+}
+
+impl Symbol {
+    pub fn intern(s: &str) -> Symbol {
+        let mut names = NAMES.lock().unwrap();
+        if let Some(sym) = names.get(s) {
+            return *sym;
+        }
+        let s: &'static str = Box::leak(s.to_string().into_boxed_str());
+        let sym = Symbol(names.len() as u32);
+        STRINGS.set((sym.0 - FIRST) as usize, s);
+        names.insert(s, sym);
+        sym
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self.0 {
+            0 => "",
+            i if i < FIRST => KEYWORDS[i as usize - 1].0,
+            i => STRINGS.get((i - FIRST) as usize),
+        }
+    }
+
+    /// The keyword this symbol spells, if any. No string is looked at.
+    pub fn keyword(self) -> Option<TokenType> {
+        let index = (self.0 as usize).checked_sub(1)?;
+        KEYWORDS.get(index).map(|(_, ty)| *ty)
+    }
+}
+
+impl std::ops::Deref for Symbol {
+    type Target = str;
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl std::fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(s: &str) -> Self {
+        Symbol::intern(s)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(s: String) -> Self {
+        Symbol::intern(&s)
+    }
+}
+
+impl From<char> for Symbol {
+    fn from(c: char) -> Self {
+        Symbol::intern(c.encode_utf8(&mut [0; 4]))
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<Symbol> for &str {
+    fn eq(&self, other: &Symbol) -> bool {
+        *self == other.as_str()
+    }
+}
+
+#[cfg(test)]
+mod test_symbol {
+    use super::Symbol;
+    use crate::token::token::TokenType;
+
+    #[test]
+    fn test_intern() {
+        let a = Symbol::intern("变量");
+        assert_eq!(a, Symbol::intern(&String::from("变量")));
+        assert_ne!(a, Symbol::intern("变"));
+        assert_eq!(a.as_str(), "变量");
+        assert_eq!(Symbol::from('x'), "x");
+        assert_eq!(Symbol::default(), "");
+    }
+
+    #[test]
+    fn test_intern_from_threads() {
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    (0..3000)
+                        .map(|i| Symbol::intern(&format!("thread-name-{}", i)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let symbols: Vec<Vec<Symbol>> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        for (i, sym) in symbols[0].iter().enumerate() {
+            assert_eq!(sym.as_str(), format!("thread-name-{}", i));
+            assert!(symbols.iter().all(|s| s[i] == *sym));
+        }
+    }
+
+    #[test]
+    fn test_keyword() {
+        assert_eq!(Symbol::intern("fn").keyword(), Some(TokenType::Function));
+        assert_eq!(Symbol::intern("class").keyword(), Some(TokenType::Class));
+        assert_eq!(Symbol::intern("func").keyword(), None);
+        assert_eq!(Symbol::default().keyword(), None);
+    }
+}
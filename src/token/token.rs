@@ -1,4 +1,5 @@
-use std::{collections::HashMap, io::Write};
+pub use crate::token::symbol::Symbol;
+use std::rc::Rc;
 
 // pub const ILLEGAL: &'static str = "ILLEGAL";
 // pub const EOF: &'static str = "EOF";
@@ -42,39 +43,73 @@ use std::{collections::HashMap, io::Write};
 // pub const STRING: &'static str = "STRING";
 // pub const CLASS: &'static str = "CLASS";
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum TokenType {
     EOF,
     IDENT,
     INT,
-    ASSIGN,    // =
-    PLUS,      // +
-    MINUS,     // -
-    BANG,      // !
-    ASTERISK,  // "*"
-    SLASH,     // "/"
-    LT,        // "<"
-    GT,        // >
-    EQ,        // ==
-    NotEQ,     // !=
-    COMMA,     // ,
-    SEMICOLON, // ;
-    LPAREN,    // (
-    RPAREN,    // )
-    LBRACE,    // {
-    RBRACE,    // }
-    LBRACKET,  // [
-    RBRACKET,  // ]
-    COLON,     // :
-    Function,  // fn
-    Let,       // let
-    True,      // true
-    False,     // false
-    If,        // if
-    Else,      // else
-    Return,    // return
-    Str,       // "..."
-    Class,     // class
+    FLOAT,
+    ASSIGN,         // =
+    PLUS,           // +
+    MINUS,          // -
+    BANG,           // !
+    ASTERISK,       // "*"
+    SLASH,          // "/"
+    PERCENT,        // %
+    LT,             // "<"
+    GT,             // >
+    LtEQ,           // <=
+    GtEQ,           // >=
+    EQ,             // ==
+    NotEQ,          // !=
+    AND,            // &&
+    OR,             // ||
+    PlusASSIGN,     // +=
+    MinusASSIGN,    // -=
+    AsteriskASSIGN, // *=
+    SlashASSIGN,    // /=
+    COMMA,          // ,
+    SEMICOLON,      // ;
+    LPAREN,         // (
+    RPAREN,         // )
+    LBRACE,         // {
+    RBRACE,         // }
+    LBRACKET,       // [
+    RBRACKET,       // ]
+    COLON,          // :
+    DOT,            // .
+    DOTDOT,         // ..
+    DOTDOTEQ,       // ..=
+    ELLIPSIS,       // ...
+    ARROW,          // =>
+    RARROW,         // ->
+    PIPE,           // |
+    Function,       // fn
+    Let,            // let
+    True,           // true
+    False,          // false
+    If,             // if
+    Else,           // else
+    Return,         // return
+    Str,            // "..."
+    StrHead,        // "...${  a string up to its first interpolation
+    StrMid,         // }...${  between two interpolations
+    StrTail,        // }..."   after the last one
+    Class,          // class
+    While,          // while
+    For,            // for
+    In,             // in
+    Break,          // break
+    Continue,       // continue
+    Super,          // super
+    Match,          // match
+    Import,         // import
+    As,             // as
+    Macro,          // macro
+    DocComment,     // /// ...
+    WHITESPACE,     // trivia, only produced by Lexer::with_trivia
+    COMMENT,        // trivia, only produced by Lexer::with_trivia
+    #[default]
     ILLEGAL,
 }
 
@@ -85,16 +120,26 @@ impl std::fmt::Display for TokenType {
             EOF => f.write_str("EOF"),
             IDENT => f.write_str("ident"),
             INT => f.write_str("int"),
+            FLOAT => f.write_str("float"),
             ASSIGN => f.write_str("="),
             PLUS => f.write_str("+"),
             MINUS => f.write_str("-"),
             BANG => f.write_str("!"),
             ASTERISK => f.write_str("*"),
             SLASH => f.write_str("/"),
+            PERCENT => f.write_str("%"),
             LT => f.write_str("<"),
             GT => f.write_str(">"),
+            LtEQ => f.write_str("<="),
+            GtEQ => f.write_str(">="),
             EQ => f.write_str("=="),
             NotEQ => f.write_str("!="),
+            AND => f.write_str("&&"),
+            OR => f.write_str("||"),
+            PlusASSIGN => f.write_str("+="),
+            MinusASSIGN => f.write_str("-="),
+            AsteriskASSIGN => f.write_str("*="),
+            SlashASSIGN => f.write_str("/="),
             COMMA => f.write_str(","),
             SEMICOLON => f.write_str(";"),
             LPAREN => f.write_str("("),
@@ -104,6 +149,13 @@ impl std::fmt::Display for TokenType {
             LBRACKET => f.write_str("["),
             RBRACKET => f.write_str("]"),
             COLON => f.write_str(":"),
+            DOT => f.write_str("."),
+            DOTDOT => f.write_str(".."),
+            DOTDOTEQ => f.write_str("..="),
+            ELLIPSIS => f.write_str("..."),
+            ARROW => f.write_str("=>"),
+            RARROW => f.write_str("->"),
+            PIPE => f.write_str("|"),
             Function => f.write_str("fn"),
             Let => f.write_str("let"),
             True => f.write_str("true"),
@@ -112,59 +164,260 @@ impl std::fmt::Display for TokenType {
             Else => f.write_str("else"),
             Return => f.write_str("return"),
             Str => f.write_str("string"),
+            StrHead | StrMid | StrTail => f.write_str("string"),
             Class => f.write_str("class"),
+            While => f.write_str("while"),
+            For => f.write_str("for"),
+            In => f.write_str("in"),
+            Break => f.write_str("break"),
+            Continue => f.write_str("continue"),
+            Super => f.write_str("super"),
+            Match => f.write_str("match"),
+            Import => f.write_str("import"),
+            As => f.write_str("as"),
+            Macro => f.write_str("macro"),
+            DocComment => f.write_str("doc comment"),
+            WHITESPACE => f.write_str("whitespace"),
+            COMMENT => f.write_str("comment"),
             ILLEGAL => f.write_str("ILLEGAL"),
         }
     }
 }
 
-pub fn lookup_ident(ident: &String) -> TokenType {
-    use TokenType::*;
-    let mmap: HashMap<String, TokenType> = {
-        let mut map = HashMap::new();
-        map.insert("fn".to_string(), Function);
-        map.insert("let".to_string(), Let);
-        map.insert("true".to_string(), True);
-        map.insert("false".to_string(), False);
-        map.insert("if".to_string(), If);
-        map.insert("else".to_string(), Else);
-        map.insert("return".to_string(), Return);
-        map.insert("class".to_string(), Class);
-        map
-    };
-    mmap.get(ident).map(|a| *a).unwrap_or(IDENT)
+pub fn lookup_ident(ident: Symbol) -> TokenType {
+    ident.keyword().unwrap_or(TokenType::IDENT)
 }
 
-impl Default for TokenType {
+/// A location in the source text.
+///
+/// `offset` is a byte offset into the input, `line` and `column` are 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new(offset: usize, line: usize, column: usize) -> Self {
+        Position {
+            offset,
+            line,
+            column,
+        }
+    }
+
+    /// The position of the first character of an input.
+    pub fn start() -> Self {
+        Position::new(0, 1, 1)
+    }
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A half-open range `[start, end)` of source text.
+///
+/// The default span (line 0) is a dummy used by nodes built by hand
+/// rather than by the parser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Span { start, end }
+    }
+
+    pub fn is_dummy(&self) -> bool {
+        self.start.line == 0
+    }
+
+    /// The smallest span covering both `self` and `other`. Dummy spans are ignored.
+    pub fn to(self, other: Span) -> Span {
+        if self.is_dummy() {
+            return other;
+        }
+        if other.is_dummy() {
+            return self;
+        }
+        Span {
+            start: std::cmp::min(self.start, other.start),
+            end: std::cmp::max(self.end, other.end),
+        }
+    }
+
+    /// The source text this span covers.
+    pub fn slice<'a>(&self, input: &'a str) -> &'a str {
+        &input[self.start.offset..self.end.offset]
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// The text of a token. Identifiers and keywords are interned, as they
+/// are looked up and compared often; any other text, such as a string's
+/// contents or a comment, is kept as it is.
+#[derive(Clone)]
+pub enum Literal {
+    Name(Symbol),
+    Text(Rc<str>),
+}
+
+impl Literal {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Literal::Name(sym) => sym.as_str(),
+            Literal::Text(text) => text,
+        }
+    }
+
+    /// The literal as a symbol, interned if it is not one already.
+    pub fn to_symbol(&self) -> Symbol {
+        match self {
+            Literal::Name(sym) => *sym,
+            Literal::Text(text) => Symbol::intern(text),
+        }
+    }
+}
+
+impl Default for Literal {
     fn default() -> Self {
-        TokenType::ILLEGAL
+        Literal::Name(Symbol::default())
+    }
+}
+
+impl std::ops::Deref for Literal {
+    type Target = str;
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for Literal {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Literal::Name(a), Literal::Name(b)) => a == b,
+            (a, b) => a.as_str() == b.as_str(),
+        }
+    }
+}
+
+impl Eq for Literal {}
+
+impl std::hash::Hash for Literal {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl PartialEq<str> for Literal {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Literal {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<Literal> for &str {
+    fn eq(&self, other: &Literal) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl std::fmt::Debug for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl std::fmt::Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+impl From<Symbol> for Literal {
+    fn from(sym: Symbol) -> Self {
+        Literal::Name(sym)
+    }
+}
+
+impl From<&str> for Literal {
+    fn from(s: &str) -> Self {
+        Literal::Text(s.into())
+    }
+}
+
+impl From<String> for Literal {
+    fn from(s: String) -> Self {
+        Literal::Text(s.into())
+    }
+}
+
+impl From<char> for Literal {
+    fn from(c: char) -> Self {
+        Literal::Text(c.encode_utf8(&mut [0; 4]).into())
+    }
+}
+
+/// Two tokens are equal when their type and literal are; the span is
+/// location metadata and takes no part in comparison or hashing.
+#[derive(Debug, Clone, Default)]
 pub struct Token {
     pub ty: TokenType,
-    pub literal: String,
+    pub literal: Literal,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new<S: Into<String>>(token_type: TokenType, ch: S) -> Token {
+    pub fn new<S: Into<Literal>>(token_type: TokenType, ch: S) -> Token {
         Token {
             ty: token_type,
             literal: ch.into(),
+            span: Span::default(),
         }
     }
 
+    pub fn with_span(mut self, span: Span) -> Token {
+        self.span = span;
+        self
+    }
+
     pub fn is_ty(&self, ty: TokenType) -> bool {
         self.ty == ty
     }
+
+    /// Whitespace and comments, which carry no meaning for the parser.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.ty, TokenType::WHITESPACE | TokenType::COMMENT)
+    }
 }
 
-impl Default for Token {
-    fn default() -> Self {
-        Token {
-            ty: TokenType::default(),
-            literal: String::new(),
-        }
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.ty == other.ty && self.literal == other.literal
+    }
+}
+
+impl Eq for Token {}
+
+impl std::hash::Hash for Token {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.ty.hash(state);
+        self.literal.hash(state);
     }
 }
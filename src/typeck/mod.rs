@@ -0,0 +1,3 @@
+#[cfg(test)]
+mod test;
+pub mod typeck;
@@ -0,0 +1,144 @@
+use super::typeck;
+use crate::ast::ast;
+use crate::mylexer::lexer::Lexer;
+use crate::parser::parser::Parser;
+
+fn parse(input: &str) -> ast::ASTNode {
+    Parser::new(Lexer::new(input)).parse_program().unwrap()
+}
+
+fn infer(input: &str) -> Vec<String> {
+    match typeck::infer(&parse(input)) {
+        Ok(bindings) => bindings
+            .iter()
+            .map(|(name, scheme)| format!("{}: {}", name, scheme))
+            .collect(),
+        Err(errors) => panic!("{}: {}", input, errors),
+    }
+}
+
+#[test]
+fn test_infer_bindings() {
+    let tests: [(&str, &[&str]); 11] = [
+        (
+            "let n = len([1]); let f = len; let s = \"${len(\"ab\")}\";",
+            &["n: INTEGER", "f: fn(a) -> INTEGER", "s: STRING"],
+        ),
+        (
+            "let n = 5; let s = \"a\"; let xs = [1, 2]; let m = {\"a\": 1.5};",
+            &["n: INTEGER", "s: STRING", "xs: [INTEGER]", "m: {STRING: FLOAT}"],
+        ),
+        (
+            "let id = fn(x) { x }; let a = id(1); let b = id(\"s\");",
+            &["id: fn(a) -> a", "a: INTEGER", "b: STRING"],
+        ),
+        (
+            "let compose = fn(f, g) { fn(x) { f(g(x)) } };",
+            &["compose: fn(fn(a) -> b, fn(c) -> a) -> fn(c) -> b"],
+        ),
+        (
+            "let apply = fn(f, x) { f(x) }; let inc = fn(n) { n + 1 }; let r = apply(inc, 2);",
+            &["apply: fn(fn(a) -> b, a) -> b", "inc: fn(a) -> a", "r: INTEGER"],
+        ),
+        (
+            "let first = fn(xs) { xs[0] }; let get = fn(m, k) { m[k] };",
+            &["first: fn([a]) -> a", "get: fn({a: b}, a) -> b"],
+        ),
+        (
+            "let fact = fn(n) { if (n < 2) { return 1; } n * fact(n - 1) };",
+            &["fact: fn(a) -> INTEGER"],
+        ),
+        (
+            "let greet = fn(name) { \"hi \" + name }; let pick = fn(c, a, b) { if (c) { a } else { b } };",
+            &["greet: fn(STRING) -> STRING", "pick: fn(a, b, b) -> b"],
+        ),
+        (
+            "let [a, ...b] = [1, 2]; let {\"k\": v} = {\"k\": true};",
+            &["a: INTEGER", "b: [INTEGER]", "v: BOOLEAN"],
+        ),
+        (
+            "let f = fn(x, y = 2, ...rest) { x + y }; let z = match 3 { 0 => \"zero\", n => \"${n}\" };",
+            &["f: fn(a, INTEGER) -> a", "z: STRING"],
+        ),
+        (
+            "let r = 1..3; let s = 0; for (i in r) { s += i; } let t = \"abc\"[1:];",
+            &["r: RANGE", "s: INTEGER", "t: STRING"],
+        ),
+    ];
+    for (input, expected) in tests.iter() {
+        assert_eq!(infer(input), *expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_infer_errors() {
+    let tests = [
+        ("let n = 5; n(1);", "1:12: calling INTEGER as a function"),
+        ("1 + \"a\"", "1:1: adding STRING to INTEGER"),
+        (
+            "let id = fn(x) { x }; id(1) + id(\"s\")",
+            "1:23: adding STRING to INTEGER",
+        ),
+        (
+            "let xs = []; xs[0] = 1; xs[1] = \"s\";",
+            "1:33: expected INTEGER, found STRING",
+        ),
+        (
+            "let f = fn(x) { x(x) };",
+            "1:17: expected fn(a) -> b, found a",
+        ),
+        (
+            "if (true) { 1 } else { \"s\" }",
+            "1:22: expected INTEGER, found STRING",
+        ),
+        ("missing + 1", "1:1: unknown identifier missing"),
+        ("for (x in 5) {}", "1:11: iterating over INTEGER"),
+        ("\"a\" == \"b\"", "1:1: comparing STRING with STRING"),
+        ("-true", "1:1: negating BOOLEAN"),
+        ("true[0]", "1:1: indexing BOOLEAN with INTEGER"),
+        (
+            "let [a] = 1; a * \"s\"",
+            "1:11: expected [a], found INTEGER\n1:14: multiplying a by STRING",
+        ),
+    ];
+    for (input, expected) in tests.iter() {
+        match typeck::infer(&parse(input)) {
+            Ok(_) => panic!("{} type checked", input),
+            Err(errors) => assert_eq!(errors.to_string(), *expected, "{}", input),
+        }
+    }
+}
+
+#[test]
+fn test_runnable_programs_infer() {
+    // programs the evaluator runs, which inference must not reject
+    let tests = [
+        "let add = fn(a, b) { a + b }; add(\"x\", \"y\"); add(1, 2.5);",
+        "let max = fn(a, b) { if (a > b) { a } else { b } }; max(1.5, 2.5);",
+        "let half = fn(x) { x / 2 }; half(3.0); half(3);",
+        "let person = {\"name\": \"bob\", \"age\": 30}; let xs = [1, \"a\", [2]];",
+        "let call = fn(f, ...args) { f(args[0], args[1]) }; call(fn(a, b = 1) { a + b }, 1, 2);",
+        "let twice = fn(f) { f(1, 2) }; twice(fn(...xs) { xs }); twice(fn(x) { x });",
+        "let f = fn(a, b = 2) { a * b }; f(1, b: 3);",
+    ];
+    for input in tests.iter() {
+        if let Err(errors) = typeck::infer(&parse(input)) {
+            panic!("{}: {}", input, errors);
+        }
+    }
+    assert_eq!(infer("let x = if (true) { 1 };"), ["x: a"]);
+}
+
+#[test]
+fn test_incremental() {
+    let mut typeck = typeck::Typeck::new();
+    let (ty, bindings) = typeck.check(&parse("let id = fn(x) { x };")).unwrap();
+    assert_eq!((ty.to_string(), bindings.len()), ("fn(a) -> a".into(), 1));
+    let (ty, _) = typeck.check(&parse("id(1) + 1")).unwrap();
+    assert_eq!(ty.to_string(), "INTEGER");
+
+    // a program with errors binds nothing
+    assert!(typeck.check(&parse("let n = 1; n + \"s\"")).is_err());
+    let errors = typeck.check(&parse("n")).unwrap_err();
+    assert_eq!(errors.to_string(), "1:1: unknown identifier n");
+}
@@ -0,0 +1,755 @@
+use crate::ast::ast::{self, ASTNode, Pattern};
+use crate::checker::checker::{TypeError, TypeErrors};
+use crate::object::object;
+use crate::token::token::{Span, Symbol};
+
+use std::collections::HashMap;
+
+/// An inferred type. `Var`s are unknowns that inference fills in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Var(usize),
+    Int,
+    Float,
+    Bool,
+    Str,
+    Null,
+    Range,
+    Array(Box<Ty>),
+    Map(Box<Ty>, Box<Ty>),
+    Func(Vec<Ty>, Box<Ty>),
+}
+
+impl Ty {
+    fn free_vars(&self, vars: &mut Vec<usize>) {
+        match self {
+            Ty::Var(v) if !vars.contains(v) => vars.push(*v),
+            Ty::Array(element) => element.free_vars(vars),
+            Ty::Map(key, value) => {
+                key.free_vars(vars);
+                value.free_vars(vars);
+            }
+            Ty::Func(params, ret) => {
+                for param in params {
+                    param.free_vars(vars);
+                }
+                ret.free_vars(vars);
+            }
+            _ => {}
+        }
+    }
+
+    // type variables are named a, b, .. in the order they are first printed
+    fn write(
+        &self,
+        f: &mut dyn std::fmt::Write,
+        names: &mut HashMap<usize, String>,
+    ) -> std::fmt::Result {
+        match self {
+            Ty::Var(v) => {
+                let next = names.len();
+                let name = names.entry(*v).or_insert_with(|| var_name(next));
+                f.write_str(name)
+            }
+            Ty::Int => f.write_str(object::INTEGER_OBJ),
+            Ty::Float => f.write_str(object::FLOAT_OBJ),
+            Ty::Bool => f.write_str(object::BOOLEAN_OBJ),
+            Ty::Str => f.write_str(object::STRING_OBJ),
+            Ty::Null => f.write_str(object::NULL_OBJ),
+            Ty::Range => f.write_str(object::RANGE_OBJ),
+            Ty::Array(element) => {
+                f.write_str("[")?;
+                element.write(f, names)?;
+                f.write_str("]")
+            }
+            Ty::Map(key, value) => {
+                f.write_str("{")?;
+                key.write(f, names)?;
+                f.write_str(": ")?;
+                value.write(f, names)?;
+                f.write_str("}")
+            }
+            Ty::Func(params, ret) => {
+                f.write_str("fn(")?;
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    param.write(f, names)?;
+                }
+                f.write_str(") -> ")?;
+                ret.write(f, names)
+            }
+        }
+    }
+}
+
+fn var_name(n: usize) -> String {
+    let letter = (b'a' + (n % 26) as u8) as char;
+    match n / 26 {
+        0 => letter.to_string(),
+        i => format!("{}{}", letter, i),
+    }
+}
+
+impl std::fmt::Display for Ty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, &mut HashMap::new())
+    }
+}
+
+// two types printed with the same names for the same variables
+fn show_both(a: &Ty, b: &Ty) -> (String, String) {
+    let mut names = HashMap::new();
+    let (mut a_text, mut b_text) = (String::new(), String::new());
+    // writing to a String cannot fail
+    let _ = a.write(&mut a_text, &mut names);
+    let _ = b.write(&mut b_text, &mut names);
+    (a_text, b_text)
+}
+
+/// A type that holds for every choice of its `vars`, such as the type of
+/// `fn(x) { x }`, which is `fn(a) -> a` for any `a`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scheme {
+    pub vars: Vec<usize>,
+    pub ty: Ty,
+}
+
+impl Scheme {
+    fn mono(ty: Ty) -> Self {
+        Scheme { vars: vec![], ty }
+    }
+}
+
+impl std::fmt::Display for Scheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.ty)
+    }
+}
+
+/// Infers the types of a program without any annotations, returning the
+/// type of each top-level binding in order.
+pub fn infer(program: &ASTNode) -> Result<Vec<(Symbol, Scheme)>, TypeErrors> {
+    Typeck::new().check(program).map(|(_, bindings)| bindings)
+}
+
+/// Hindley-Milner inference: every expression gets a type, unknowns are
+/// type variables solved by unification, and a let-bound function is
+/// generalized so each use may pick its own types.
+///
+/// Arithmetic and comparisons work on INTEGER and FLOAT, and + on STRING
+/// too; an operand that is still unknown is left unknown. Inference only
+/// reports: it is stricter than the evaluator, so nothing is refused to
+/// run because of it.
+pub struct Typeck {
+    subst: Vec<Option<Ty>>, // what each type variable stands for, if known
+    scopes: Vec<HashMap<Symbol, Scheme>>,
+    returns: Vec<Ty>, // the return type of each enclosing function
+    bindings: Vec<(Symbol, Scheme)>,
+    errors: Vec<TypeError>,
+}
+
+impl Typeck {
+    pub fn new() -> Self {
+        // the builtins; len takes a STRING, an array or a map
+        let len = Scheme {
+            vars: vec![0],
+            ty: Ty::Func(vec![Ty::Var(0)], Box::new(Ty::Int)),
+        };
+        Typeck {
+            subst: vec![None],
+            scopes: vec![HashMap::from([(Symbol::from("len"), len)])],
+            returns: vec![],
+            bindings: vec![],
+            errors: vec![],
+        }
+    }
+
+    /// Infers the type of `program`'s value and of each top-level binding
+    /// it makes. Its top-level names stay known to later calls, unless it
+    /// has errors.
+    pub fn check(&mut self, program: &ASTNode) -> Result<(Ty, Vec<(Symbol, Scheme)>), TypeErrors> {
+        let (globals, subst) = (self.scopes[0].clone(), self.subst.clone());
+        let ty = self.infer(program);
+        let bindings = std::mem::take(&mut self.bindings);
+        if !self.errors.is_empty() {
+            self.scopes = vec![globals];
+            self.subst = subst;
+            self.returns.clear();
+            return Err(TypeErrors {
+                errors: std::mem::take(&mut self.errors),
+            });
+        }
+        let bindings = bindings
+            .into_iter()
+            .map(|(name, scheme)| {
+                let ty = self.apply(&scheme.ty);
+                (name, Scheme { ty, ..scheme })
+            })
+            .collect();
+        Ok((self.apply(&ty), bindings))
+    }
+
+    /// Like `check`, but forgets `program`'s bindings afterwards.
+    pub fn peek(&mut self, program: &ASTNode) -> Result<Ty, TypeErrors> {
+        let (globals, subst) = (self.scopes[0].clone(), self.subst.clone());
+        let result = self.check(program).map(|(ty, _)| ty);
+        self.scopes = vec![globals];
+        self.subst = subst;
+        result
+    }
+
+    fn fresh(&mut self) -> Ty {
+        self.subst.push(None);
+        Ty::Var(self.subst.len() - 1)
+    }
+
+    fn error<S: Into<String>>(&mut self, message: S, span: Span) {
+        self.errors.push(TypeError {
+            message: message.into(),
+            span,
+        });
+    }
+
+    // follows solved variables until a constructor or an unsolved variable
+    fn resolve(&self, ty: &Ty) -> Ty {
+        match ty {
+            Ty::Var(v) => match &self.subst[*v] {
+                Some(ty) => self.resolve(ty),
+                None => ty.clone(),
+            },
+            ty => ty.clone(),
+        }
+    }
+
+    // `ty` with every solved variable replaced, all the way down
+    fn apply(&self, ty: &Ty) -> Ty {
+        match self.resolve(ty) {
+            Ty::Array(element) => Ty::Array(Box::new(self.apply(&element))),
+            Ty::Map(key, value) => {
+                Ty::Map(Box::new(self.apply(&key)), Box::new(self.apply(&value)))
+            }
+            Ty::Func(params, ret) => Ty::Func(
+                params.iter().map(|p| self.apply(p)).collect(),
+                Box::new(self.apply(&ret)),
+            ),
+            ty => ty,
+        }
+    }
+
+    fn occurs(&self, v: usize, ty: &Ty) -> bool {
+        let mut vars = vec![];
+        self.apply(ty).free_vars(&mut vars);
+        vars.contains(&v)
+    }
+
+    fn unify(&mut self, a: &Ty, b: &Ty) -> bool {
+        match (self.resolve(a), self.resolve(b)) {
+            (Ty::Var(a), Ty::Var(b)) if a == b => true,
+            (Ty::Var(v), ty) | (ty, Ty::Var(v)) => {
+                if self.occurs(v, &ty) {
+                    return false;
+                }
+                self.subst[v] = Some(ty);
+                true
+            }
+            (Ty::Array(a), Ty::Array(b)) => self.unify(&a, &b),
+            (Ty::Map(k1, v1), Ty::Map(k2, v2)) => self.unify(&k1, &k2) && self.unify(&v1, &v2),
+            // arities are not compared: defaults, a rest parameter and
+            // named arguments let a function take more or fewer arguments
+            (Ty::Func(p1, r1), Ty::Func(p2, r2)) => {
+                p1.iter().zip(&p2).all(|(a, b)| self.unify(a, b)) && self.unify(&r1, &r2)
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    // `actual`, the type of `node`, must be `expected`
+    fn expect(&mut self, expected: &Ty, actual: &Ty, node: &ASTNode) {
+        if !self.unify(expected, actual) {
+            let (expected, actual) = show_both(&self.apply(expected), &self.apply(actual));
+            self.error(
+                format!("expected {}, found {}", expected, actual),
+                node.span(),
+            );
+        }
+    }
+
+    fn bind(&mut self, name: Symbol, scheme: Scheme) {
+        self.scopes.last_mut().unwrap().insert(name, scheme);
+    }
+
+    // reports the type of a name that a top-level let just bound
+    fn record(&mut self, name: Symbol) {
+        if self.scopes.len() == 1 {
+            let scheme = self.scopes[0][&name].clone();
+            self.bindings.push((name, scheme));
+        }
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> Ty {
+        let mut map = HashMap::new();
+        for v in &scheme.vars {
+            map.insert(*v, self.fresh());
+        }
+        substitute(&self.apply(&scheme.ty), &map)
+    }
+
+    // quantifies the variables of `ty` that nothing in scope refers to
+    fn generalize(&self, ty: &Ty) -> Scheme {
+        let ty = self.apply(ty);
+        let mut in_scope = vec![];
+        for scheme in self.scopes.iter().flat_map(|s| s.values()) {
+            let mut vars = vec![];
+            self.apply(&scheme.ty).free_vars(&mut vars);
+            in_scope.extend(vars.into_iter().filter(|v| !scheme.vars.contains(v)));
+        }
+        let mut vars = vec![];
+        ty.free_vars(&mut vars);
+        vars.retain(|v| !in_scope.contains(v));
+        Scheme { vars, ty }
+    }
+
+    fn infer(&mut self, node: &ASTNode) -> Ty {
+        use ASTNode::*;
+        match node {
+            Program(p) => self.infer_statements(&p.statements),
+            BlockStatement(b) => self.infer_statements(&b.statements),
+            ExpressionStatement(e) => self.infer(&e.expression),
+            LetStatement(stmt) => self.infer_let(stmt),
+            ClassStatement(class) => {
+                let ty = self.fresh();
+                self.bind(class.name.value, Scheme::mono(ty.clone()));
+                self.scopes.push(HashMap::new());
+                for member in &class.members {
+                    self.infer(member);
+                }
+                self.scopes.pop();
+                ty
+            }
+            // modules are opaque: their members are fresh variables
+            ImportStatement(import) => {
+                let ty = self.fresh();
+                self.bind(import.alias.value, Scheme::mono(ty.clone()));
+                ty
+            }
+            AssignExpression(assign) => {
+                let target = self.infer(&assign.target);
+                let value = self.infer(&assign.value);
+                self.expect(&target, &value, &assign.value);
+                value
+            }
+            Identifier(id) => {
+                let scheme = self.scopes.iter().rev().find_map(|s| s.get(&id.value));
+                match scheme.cloned() {
+                    Some(scheme) => self.instantiate(&scheme),
+                    _ => {
+                        self.error(format!("unknown identifier {}", id.value), node.span());
+                        self.fresh()
+                    }
+                }
+            }
+            IntegerLiteral(_) => Ty::Int,
+            FloatLiteral(_) => Ty::Float,
+            Boolean(_) => Ty::Bool,
+            StringLiteral(_, _) => Ty::Str,
+            InterpolatedString(parts) => {
+                for part in parts {
+                    self.infer(part);
+                }
+                Ty::Str
+            }
+            PrefixExpression(p) => {
+                let right = self.infer(&p.right);
+                if p.operator == "!" {
+                    return Ty::Bool;
+                }
+                match self.resolve(&right) {
+                    Ty::Int | Ty::Float => right,
+                    Ty::Var(_) => {
+                        self.unify(&right, &Ty::Int);
+                        Ty::Int
+                    }
+                    other => {
+                        self.error(format!("negating {}", other), node.span());
+                        self.fresh()
+                    }
+                }
+            }
+            InfixExpression(i) => {
+                let left = self.infer(&i.left);
+                let right = self.infer(&i.right);
+                match i.operator.as_str() {
+                    "&&" | "||" => Ty::Bool,
+                    "==" | "!=" => {
+                        let same = self.unify(&left, &right);
+                        if same && self.resolve(&left) != Ty::Str {
+                            return Ty::Bool;
+                        }
+                        let (left, right) = show_both(&self.apply(&left), &self.apply(&right));
+                        self.error(format!("comparing {} with {}", left, right), node.span());
+                        Ty::Bool
+                    }
+                    op => match self.infer_arithmetic(op, &left, &right) {
+                        Some(ty) => ty,
+                        _ => {
+                            let (left, right) = show_both(&self.apply(&left), &self.apply(&right));
+                            self.error(describe_infix(op, &left, &right), node.span());
+                            self.fresh()
+                        }
+                    },
+                }
+            }
+            IfExpression(i) => {
+                self.infer(&i.condition);
+                let consequence = self.infer(&i.consequence);
+                // the branch's value, or NULL when it is not taken
+                if i.alternative.is_none() {
+                    return self.fresh();
+                }
+                let alternative = self.infer(&i.alternative);
+                self.expect(&consequence, &alternative, &i.alternative);
+                consequence
+            }
+            MatchExpression(m) => {
+                let value = self.infer(&m.value);
+                let result = self.fresh();
+                for arm in &m.arms {
+                    self.scopes.push(HashMap::new());
+                    self.bind_pattern(&arm.pattern, &value, &m.value);
+                    self.infer(&arm.guard);
+                    let body = self.infer(&arm.body);
+                    self.expect(&result, &body, &arm.body);
+                    self.scopes.pop();
+                }
+                result
+            }
+            WhileExpression(w) => {
+                self.infer(&w.condition);
+                self.infer(&w.body);
+                Ty::Null
+            }
+            ForExpression(f) => {
+                let iterable = self.infer(&f.iterable);
+                let item = match self.resolve(&iterable) {
+                    Ty::Array(element) => *element,
+                    Ty::Str => Ty::Str,
+                    Ty::Range => Ty::Int,
+                    Ty::Var(_) => {
+                        let element = self.fresh();
+                        self.unify(&iterable, &Ty::Array(Box::new(element.clone())));
+                        element
+                    }
+                    other => {
+                        self.error(format!("iterating over {}", other), f.iterable.span());
+                        self.fresh()
+                    }
+                };
+                self.bind(f.variable.value, Scheme::mono(item));
+                self.infer(&f.body);
+                Ty::Null
+            }
+            // these never produce a value where they stand
+            BreakStatement(_) | ContinueStatement(_) => self.fresh(),
+            ReturnStatement(r) => {
+                let value = self.infer(&r.return_value);
+                if let Some(ret) = self.returns.last().cloned() {
+                    self.expect(&ret, &value, &r.return_value);
+                }
+                self.fresh()
+            }
+            FuncLiteral(func) => self.infer_function(func),
+            MacroLiteral(_) => self.fresh(),
+            CallExpression(c) => {
+                let callee = self.infer(&c.func);
+                let args: Vec<Ty> = c.args.iter().map(|a| self.infer(a)).collect();
+                for (_, value) in &c.named {
+                    self.infer(value);
+                }
+                match self.resolve(&callee) {
+                    Ty::Func(params, ret) => {
+                        for ((param, arg), node) in params.iter().zip(&args).zip(&c.args) {
+                            self.expect(param, arg, node);
+                        }
+                        *ret
+                    }
+                    Ty::Var(_) => {
+                        let ret = self.fresh();
+                        let func = Ty::Func(args, Box::new(ret.clone()));
+                        self.expect(&func, &callee, &c.func);
+                        ret
+                    }
+                    other => {
+                        self.error(format!("calling {} as a function", other), c.func.span());
+                        self.fresh()
+                    }
+                }
+            }
+            ArrayLiteral(_, elements, _) => {
+                let types: Vec<Ty> = elements.iter().map(|node| self.infer(node)).collect();
+                Ty::Array(Box::new(self.common_type(&types)))
+            }
+            HashLiteral(_, pairs, _) => {
+                let mut keys = vec![];
+                let mut values = vec![];
+                for (k, v) in pairs {
+                    keys.push(self.infer(k));
+                    values.push(self.infer(v));
+                }
+                let (key, value) = (self.common_type(&keys), self.common_type(&values));
+                Ty::Map(Box::new(key), Box::new(value))
+            }
+            IndexLiteral(_, left, index, _) => {
+                let container = self.infer(left);
+                let index_ty = self.infer(index);
+                self.infer_index(&container, &index_ty, node, index)
+            }
+            SliceExpression(s) => {
+                let container = self.infer(&s.left);
+                for bound in [&s.low, &s.high] {
+                    if bound.is_some() {
+                        let ty = self.infer(bound);
+                        self.expect(&Ty::Int, &ty, bound);
+                    }
+                }
+                match self.resolve(&container) {
+                    Ty::Array(_) | Ty::Str => container,
+                    Ty::Var(_) => {
+                        let array = Ty::Array(Box::new(self.fresh()));
+                        self.unify(&container, &array);
+                        array
+                    }
+                    other => {
+                        self.error(format!("slicing {}", other), s.left.span());
+                        self.fresh()
+                    }
+                }
+            }
+            MemberExpression(m) => {
+                self.infer(&m.object);
+                self.fresh()
+            }
+            Super(_) => self.fresh(),
+            None => Ty::Null,
+        }
+    }
+
+    // a block's value is its last statement's
+    fn infer_statements(&mut self, statements: &[Box<ASTNode>]) -> Ty {
+        let mut ty = Ty::Null;
+        for stmt in statements {
+            ty = self.infer(stmt);
+        }
+        ty
+    }
+
+    fn infer_let(&mut self, stmt: &ast::LetStatement) -> Ty {
+        if let Some(pattern) = &stmt.pattern {
+            let value = self.infer(&stmt.value);
+            self.bind_pattern(pattern, &value, &stmt.value);
+            for id in pattern.bindings() {
+                self.record(id.value);
+            }
+            return value;
+        }
+        // only a function is generalized: any other value may be assigned
+        // to later, and must keep one type
+        let value = match stmt.value.as_ref() {
+            ASTNode::FuncLiteral(func) => {
+                // bound while inferring so that it can call itself
+                let this = self.fresh();
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert(stmt.name.value, Scheme::mono(this.clone()));
+                let value = self.infer_function(func);
+                self.unify(&this, &value);
+                self.scopes.last_mut().unwrap().remove(&stmt.name.value);
+                let scheme = self.generalize(&value);
+                self.bind(stmt.name.value, scheme);
+                value
+            }
+            _ => {
+                let value = self.infer(&stmt.value);
+                self.bind(stmt.name.value, Scheme::mono(value.clone()));
+                value
+            }
+        };
+        self.record(stmt.name.value);
+        value
+    }
+
+    fn infer_function(&mut self, func: &ast::FuncLiteral) -> Ty {
+        self.scopes.push(HashMap::new());
+        let mut params = vec![];
+        for param in &func.params {
+            let ty = self.fresh();
+            if param.default.is_some() {
+                let default = self.infer(&param.default);
+                self.expect(&ty, &default, &param.default);
+            }
+            if param.rest {
+                let array = Ty::Array(Box::new(ty));
+                self.bind(param.name.value, Scheme::mono(array));
+            } else {
+                self.bind(param.name.value, Scheme::mono(ty.clone()));
+                params.push(ty);
+            }
+        }
+        let ret = self.fresh();
+        self.returns.push(ret.clone());
+        let body = self.infer(&func.body);
+        match func.body.children().last() {
+            Some(last) => self.expect(&ret, &body, last),
+            _ => {
+                self.unify(&ret, &body);
+            }
+        }
+        self.returns.pop();
+        self.scopes.pop();
+        Ty::Func(params, Box::new(ret))
+    }
+
+    // container[index], where an unknown container is taken to be an array
+    // for an INTEGER index and a map otherwise
+    fn infer_index(&mut self, container: &Ty, index: &Ty, node: &ASTNode, at: &ASTNode) -> Ty {
+        match (self.resolve(container), self.resolve(index)) {
+            (Ty::Map(key, value), _) => {
+                self.expect(&key, index, at);
+                *value
+            }
+            (Ty::Array(element), Ty::Int) => *element,
+            (Ty::Array(_), Ty::Range) | (Ty::Str, Ty::Range) => container.clone(),
+            (Ty::Str, Ty::Int) => Ty::Str,
+            (Ty::Array(element), Ty::Var(_)) => {
+                self.unify(index, &Ty::Int);
+                *element
+            }
+            (Ty::Str, Ty::Var(_)) => {
+                self.unify(index, &Ty::Int);
+                Ty::Str
+            }
+            (Ty::Var(_), Ty::Int) => {
+                let element = self.fresh();
+                self.unify(container, &Ty::Array(Box::new(element.clone())));
+                element
+            }
+            (Ty::Var(_), _) => {
+                let value = self.fresh();
+                let map = Ty::Map(Box::new(index.clone()), Box::new(value.clone()));
+                self.unify(container, &map);
+                value
+            }
+            (container, index) => {
+                self.error(
+                    format!("indexing {} with {}", container, index),
+                    node.span(),
+                );
+                self.fresh()
+            }
+        }
+    }
+
+    // the result of `left op right` for arithmetic, comparisons and ranges,
+    // or None where the evaluator would fail
+    // the type of elements of `types`, or an unknown one where the
+    // elements differ, as they may in an array or a map
+    fn common_type(&mut self, types: &[Ty]) -> Ty {
+        let common = self.fresh();
+        let subst = self.subst.clone();
+        if types.iter().all(|ty| self.unify(&common, ty)) {
+            return common;
+        }
+        self.subst = subst;
+        self.fresh()
+    }
+
+    fn infer_arithmetic(&mut self, op: &str, left: &Ty, right: &Ty) -> Option<Ty> {
+        let compare = matches!(op, "<" | ">" | "<=" | ">=");
+        if op == ".." || op == "..=" {
+            let ok = self.unify(left, &Ty::Int) && self.unify(right, &Ty::Int);
+            return Some(Ty::Range).filter(|_| ok);
+        }
+        let numeric = |t: &Ty| matches!(t, Ty::Int | Ty::Float);
+        // an unknown operand is left unknown, as the operators take INTEGER,
+        // FLOAT and, for +, STRING; only a STRING fixes the other operand
+        let ty = match (self.resolve(left), self.resolve(right)) {
+            (Ty::Var(_), Ty::Var(_)) => self.fresh(),
+            (Ty::Var(_), Ty::Str) | (Ty::Str, Ty::Var(_)) if op == "+" => {
+                self.unify(left, &Ty::Str);
+                self.unify(right, &Ty::Str);
+                Ty::Str
+            }
+            (Ty::Var(_), Ty::Float) | (Ty::Float, Ty::Var(_)) => Ty::Float,
+            (var @ Ty::Var(_), Ty::Int) | (Ty::Int, var @ Ty::Var(_)) => var,
+            (Ty::Int, Ty::Int) => Ty::Int,
+            (l, r) if numeric(&l) && numeric(&r) => Ty::Float,
+            (Ty::Str, Ty::Str) if op == "+" => Ty::Str,
+            _ => return None,
+        };
+        Some(if compare { Ty::Bool } else { ty })
+    }
+
+    // binds the names in `pattern` to the parts of a value of type `ty`,
+    // the type of `node`
+    fn bind_pattern(&mut self, pattern: &Pattern, ty: &Ty, node: &ASTNode) {
+        match pattern {
+            Pattern::Literal(literal) => {
+                let literal_ty = self.infer(literal);
+                self.expect(ty, &literal_ty, literal);
+            }
+            Pattern::Binding(id) => self.bind(id.value, Scheme::mono(ty.clone())),
+            Pattern::Wildcard => {}
+            Pattern::Array(elements, rest) => {
+                let element = self.fresh();
+                let array = Ty::Array(Box::new(element.clone()));
+                self.expect(&array, ty, node);
+                for p in elements {
+                    self.bind_pattern(p, &element, node);
+                }
+                if let Some(rest) = rest {
+                    self.bind_pattern(rest, &array, node);
+                }
+            }
+            Pattern::Hash(pairs) => {
+                let (key, value) = (self.fresh(), self.fresh());
+                let map = Ty::Map(Box::new(key.clone()), Box::new(value.clone()));
+                self.expect(&map, ty, node);
+                for (k, p) in pairs {
+                    let key_ty = self.infer(k);
+                    self.expect(&key, &key_ty, k);
+                    self.bind_pattern(p, &value, node);
+                }
+            }
+        }
+    }
+}
+
+fn substitute(ty: &Ty, map: &HashMap<usize, Ty>) -> Ty {
+    match ty {
+        Ty::Var(v) => map.get(v).cloned().unwrap_or(Ty::Var(*v)),
+        Ty::Array(element) => Ty::Array(Box::new(substitute(element, map))),
+        Ty::Map(key, value) => Ty::Map(
+            Box::new(substitute(key, map)),
+            Box::new(substitute(value, map)),
+        ),
+        Ty::Func(params, ret) => Ty::Func(
+            params.iter().map(|p| substitute(p, map)).collect(),
+            Box::new(substitute(ret, map)),
+        ),
+        ty => ty.clone(),
+    }
+}
+
+fn describe_infix(op: &str, left: &str, right: &str) -> String {
+    match op {
+        "+" => format!("adding {} to {}", right, left),
+        "-" => format!("subtracting {} from {}", right, left),
+        "*" => format!("multiplying {} by {}", left, right),
+        "/" => format!("dividing {} by {}", left, right),
+        "%" => format!("taking {} modulo {}", left, right),
+        ".." | "..=" => format!("making a range from {} to {}", left, right),
+        _ => format!("comparing {} with {}", left, right),
+    }
+}
@@ -0,0 +1,74 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn monkey(args: &[&str], stdin: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_main"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn script(name: &str, source: &str) -> String {
+    let path = std::env::temp_dir().join(format!("monkey-cli-{}-{}", std::process::id(), name));
+    std::fs::write(&path, source).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn test_run_file() {
+    // inference would reject `add`, but the script runs as before
+    let path = script(
+        "runs.mk",
+        "let add = fn(a, b) { a + b }; add(\"x\", \"y\"); let f = fn(x) { x(x) }; 1 / 0;",
+    );
+    assert_eq!(monkey(&[&path], ""), "division by zero\n");
+}
+
+#[test]
+fn test_run_file_types() {
+    let path = script(
+        "typed.mk",
+        "let id = fn(x) { x }; let n = id(1); let s = id(\"a\");",
+    );
+    assert_eq!(
+        monkey(&["--types", &path], ""),
+        "id: fn(a) -> a\nn: INTEGER\ns: STRING\n"
+    );
+
+    // what inference rejects is reported, and the script still runs
+    let path = script("untyped.mk", "let f = fn(x) { x(x) }; 1 / 0;");
+    assert_eq!(
+        monkey(&["--types", &path], ""),
+        "1:17: expected fn(a) -> b, found a\ndivision by zero\n"
+    );
+}
+
+#[test]
+fn test_repl_types() {
+    let input = "let n = 2;\nn * 3\n:type [n]\nlet f = fn(x) { x(x) }; n\n:type f\n:type m\n";
+    let output = monkey(&[], input);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "Hello Monkey",
+            ">>> 2",
+            ">>> 6",
+            ">>> [INTEGER]",
+            ">>> 2",
+            ">>> 1:1: unknown identifier f",
+            ">>> 1:1: unknown identifier m",
+            ">>> ",
+        ]
+    );
+}